@@ -0,0 +1,298 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use serde::{Deserialize, Serialize};
+use sharks::{Share, Sharks};
+use std::collections::HashSet;
+use zeroize::Zeroize;
+
+use crate::keyring::{PlainKey, PlainQuantumKeys};
+
+/// Prefix identifying a backup share in text form
+const SHARE_PREFIX: &str = "pipe-share-1";
+
+/// zstd level for the payload before splitting; shares are as long as the payload
+const PAYLOAD_COMPRESSION_LEVEL: i32 = 19;
+
+/// Everything a key backup restores: keyring keys and, for whole-keyring
+/// backups, the quantum identities and per-file keypairs
+#[derive(Serialize, Deserialize, Default)]
+pub struct BackupPayload {
+    pub keys: Vec<PlainKey>,
+    #[serde(default)]
+    pub quantum: PlainQuantumKeys,
+}
+
+/// Payload as written by each backup version; the first one held only keys
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPayload {
+    Full(BackupPayload),
+    KeysOnly(Vec<PlainKey>),
+}
+
+/// One share of a key backup split with Shamir's secret sharing.
+///
+/// Text form: `pipe-share-1:<threshold>:<set id>:<share>:<check>`, where the set
+/// ID ties together shares of the same backup and the check digits catch typos
+/// in a transcribed share.
+pub struct BackupShare {
+    threshold: u8,
+    set_id: String,
+    data: Vec<u8>,
+}
+
+impl Drop for BackupShare {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+impl BackupShare {
+    /// Share number (1-based)
+    pub fn index(&self) -> u8 {
+        self.data[0]
+    }
+
+    pub fn to_text(&self) -> String {
+        let body = format!(
+            "{}:{}:{}:{}",
+            SHARE_PREFIX,
+            self.threshold,
+            self.set_id,
+            URL_SAFE_NO_PAD.encode(&self.data)
+        );
+        let check = share_check(&body);
+        format!("{}:{}", body, check)
+    }
+
+    /// Parse a share from its text form, ignoring surrounding whitespace
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let (body, check) = text
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("Not a pipe-cli backup share"))?;
+        let parts: Vec<&str> = body.split(':').collect();
+        if parts.len() != 4 || parts[0] != SHARE_PREFIX {
+            return Err(anyhow!("Not a pipe-cli backup share"));
+        }
+        if share_check(body) != check {
+            return Err(anyhow!("Share failed its checksum; check it for typos"));
+        }
+
+        let threshold = parts[1]
+            .parse::<u8>()
+            .map_err(|_| anyhow!("Invalid share threshold '{}'", parts[1]))?;
+        let data = URL_SAFE_NO_PAD
+            .decode(parts[3])
+            .map_err(|_| anyhow!("Invalid share encoding"))?;
+        if data.len() < 2 || data[0] == 0 {
+            return Err(anyhow!("Invalid share data"));
+        }
+
+        Ok(Self {
+            threshold,
+            set_id: parts[2].to_string(),
+            data,
+        })
+    }
+
+    /// Render the text form as a terminal QR code. Fails for shares too long
+    /// to fit in a QR code (large post-quantum keys).
+    pub fn to_qr(&self) -> Result<String> {
+        let code = QrCode::new(self.to_text().as_bytes())
+            .map_err(|e| anyhow!("Share does not fit in a QR code: {}", e))?;
+        Ok(code
+            .render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build())
+    }
+}
+
+/// Check digits over a share's text
+fn share_check(body: &str) -> String {
+    blake3::hash(body.as_bytes()).to_hex()[..6].to_string()
+}
+
+/// Split keys into `shares` shares, any `threshold` of which recover them
+pub fn split_keys(payload: &BackupPayload, shares: u8, threshold: u8) -> Result<Vec<BackupShare>> {
+    if payload.keys.is_empty() && payload.quantum.is_empty() {
+        return Err(anyhow!("No keys to back up"));
+    }
+    if threshold < 2 || threshold > shares {
+        return Err(anyhow!(
+            "Threshold must be between 2 and the number of shares ({}), got {}",
+            shares,
+            threshold
+        ));
+    }
+
+    let mut json = serde_json::to_vec(payload)?;
+    let compressed = zstd::stream::encode_all(json.as_slice(), PAYLOAD_COMPRESSION_LEVEL);
+    json.zeroize();
+    let mut payload = compressed?;
+
+    // The set ID doubles as an integrity check of the recovered payload
+    let set_id = blake3::hash(&payload).to_hex()[..8].to_string();
+    let backup_shares = Sharks(threshold)
+        .dealer(&payload)
+        .take(shares as usize)
+        .map(|share| BackupShare {
+            threshold,
+            set_id: set_id.clone(),
+            data: Vec::from(&share),
+        })
+        .collect();
+    payload.zeroize();
+
+    Ok(backup_shares)
+}
+
+/// Rebuild the keys from at least `threshold` shares of the same backup
+pub fn recover_keys(shares: &[BackupShare]) -> Result<BackupPayload> {
+    let first = shares.first().ok_or_else(|| anyhow!("No shares given"))?;
+    if shares
+        .iter()
+        .any(|s| s.set_id != first.set_id || s.threshold != first.threshold)
+    {
+        return Err(anyhow!("Shares belong to different backups"));
+    }
+
+    let distinct: HashSet<u8> = shares.iter().map(|s| s.index()).collect();
+    if distinct.len() < first.threshold as usize {
+        return Err(anyhow!(
+            "Need {} different shares, got {}",
+            first.threshold,
+            distinct.len()
+        ));
+    }
+
+    let parsed = shares
+        .iter()
+        .map(|s| Share::try_from(s.data.as_slice()).map_err(|e| anyhow!("Invalid share: {}", e)))
+        .collect::<Result<Vec<Share>>>()?;
+    let mut payload = Sharks(first.threshold)
+        .recover(parsed.iter())
+        .map_err(|e| anyhow!("Failed to recover backup: {}", e))?;
+
+    if blake3::hash(&payload).to_hex()[..8] != first.set_id {
+        payload.zeroize();
+        return Err(anyhow!("Recovered backup failed its integrity check"));
+    }
+
+    let json = zstd::stream::decode_all(payload.as_slice());
+    payload.zeroize();
+    let mut json = json?;
+    let payload = serde_json::from_slice(&json);
+    json.zeroize();
+    Ok(match payload? {
+        StoredPayload::Full(payload) => payload,
+        StoredPayload::KeysOnly(keys) => BackupPayload {
+            keys,
+            ..Default::default()
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::{KeyAlgorithm, KeyMetadata};
+
+    fn test_key(name: &str, byte: u8) -> PlainKey {
+        PlainKey {
+            name: name.to_string(),
+            algorithm: KeyAlgorithm::Aes256,
+            secret: Some(vec![byte; 32]),
+            public_key: None,
+            metadata: KeyMetadata::new(Some("archive".to_string())),
+        }
+    }
+
+    fn test_payload(keys: Vec<PlainKey>) -> BackupPayload {
+        BackupPayload {
+            keys,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_split_and_recover_with_threshold() {
+        let keys = test_payload(vec![test_key("archive", 7), test_key("photos", 9)]);
+        let shares = split_keys(&keys, 5, 3).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares[0].to_qr().is_ok());
+
+        // Any three shares, round-tripped through text, recover the keys
+        let picked: Vec<BackupShare> = [4, 0, 2]
+            .iter()
+            .map(|&i| BackupShare::parse(&shares[i].to_text()).unwrap())
+            .collect();
+        let recovered = recover_keys(&picked).unwrap().keys;
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(recovered[0].name, "archive");
+        assert_eq!(recovered[1].secret, Some(vec![9u8; 32]));
+
+        // Two shares are not enough, and a repeated share does not count twice
+        assert!(recover_keys(&picked[..2]).is_err());
+        let repeated = vec![
+            BackupShare::parse(&shares[0].to_text()).unwrap(),
+            BackupShare::parse(&shares[0].to_text()).unwrap(),
+            BackupShare::parse(&shares[1].to_text()).unwrap(),
+        ];
+        assert!(recover_keys(&repeated).is_err());
+
+        assert!(split_keys(&keys, 3, 4).is_err());
+        assert!(split_keys(&keys, 3, 1).is_err());
+        assert!(split_keys(&BackupPayload::default(), 3, 2).is_err());
+    }
+
+    #[test]
+    fn test_quantum_keys_and_older_payloads() {
+        let mut payload = test_payload(vec![test_key("archive", 3)]);
+        payload
+            .quantum
+            .identities
+            .push(crate::quantum_keyring::generate_quantum_keypair("me").unwrap());
+        payload
+            .quantum
+            .identity_files
+            .push(("notes.txt".to_string(), "me".to_string()));
+        let shares = split_keys(&payload, 3, 2).unwrap();
+        let recovered = recover_keys(&shares[1..]).unwrap();
+        assert_eq!(recovered.keys.len(), 1);
+        assert_eq!(
+            recovered.quantum.identities[0].kyber_secret,
+            payload.quantum.identities[0].kyber_secret
+        );
+        assert_eq!(recovered.quantum.identity_files, payload.quantum.identity_files);
+
+        // Backups made before quantum keys were included hold a bare key list
+        let json = serde_json::to_vec(&vec![test_key("archive", 3)]).unwrap();
+        match serde_json::from_slice::<StoredPayload>(&json).unwrap() {
+            StoredPayload::KeysOnly(keys) => assert_eq!(keys[0].name, "archive"),
+            StoredPayload::Full(_) => panic!("bare key list parsed as a full payload"),
+        }
+    }
+
+    #[test]
+    fn test_share_typos_and_mixed_backups_rejected() {
+        let shares = split_keys(&test_payload(vec![test_key("archive", 1)]), 3, 2).unwrap();
+        let text = shares[0].to_text();
+
+        // Change one character of the share data
+        let pos = text.len() - 10;
+        let replacement = if &text[pos..pos + 1] == "A" { "B" } else { "A" };
+        let typo = format!("{}{}{}", &text[..pos], replacement, &text[pos + 1..]);
+        assert!(BackupShare::parse(&typo).err().unwrap().to_string().contains("checksum"));
+
+        let other = split_keys(&test_payload(vec![test_key("archive", 2)]), 3, 2).unwrap();
+        let mixed = vec![
+            BackupShare::parse(&text).unwrap(),
+            BackupShare::parse(&other[1].to_text()).unwrap(),
+        ];
+        assert!(recover_keys(&mixed).is_err());
+    }
+}
@@ -0,0 +1,812 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction, TransactionBehavior};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::UploadLogEntry;
+
+/// Schema version stored in `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 3;
+
+/// How long to wait for another process holding the write lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Status recorded when a file is known to be gone from remote storage
+pub const DELETED_STATUS: &str = "DELETED";
+
+/// Ids of the newest successful record per remote path under the prefix in
+/// `?1`, leaving out paths recorded as deleted since
+const LATEST_UPLOAD_IDS: &str = "SELECT id FROM uploads WHERE status != 'DELETED' AND id IN (
+         SELECT MAX(id) FROM uploads
+         WHERE (instr(status, 'SUCCESS') > 0 OR status = 'DELETED')
+           AND substr(remote_path, 1, length(?1)) = ?1
+         GROUP BY remote_path
+     )";
+
+const COLUMNS: &str =
+    "id, local_path, remote_path, status, message, blake3_hash, file_size, timestamp, cost";
+
+/// Local catalog of uploads, stored in SQLite next to the old JSON-lines log.
+///
+/// Every upload attempt is one row, indexed by remote path, local path and
+/// Blake3 hash. Writes are transactional, so concurrent uploads cannot
+/// interleave records. The first time the catalog is opened, the old
+/// `~/.pipe-cli-uploads.json` log is imported.
+pub struct Catalog {
+    conn: Connection,
+}
+
+/// A catalog row
+#[derive(Debug)]
+pub struct CatalogEntry {
+    pub id: i64,
+    pub entry: UploadLogEntry,
+}
+
+/// Order of `Catalog::search` results
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum UploadSort {
+    /// Order the uploads were recorded in
+    #[default]
+    Recorded,
+    /// Newest first
+    Date,
+    /// Largest first
+    Size,
+    /// By remote path
+    Name,
+}
+
+impl UploadSort {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "date" => Ok(UploadSort::Date),
+            "size" => Ok(UploadSort::Size),
+            "name" => Ok(UploadSort::Name),
+            _ => Err(anyhow!("Unknown sort order '{}'. Use: size, date, name", value)),
+        }
+    }
+
+    fn order_by(self) -> &'static str {
+        match self {
+            UploadSort::Recorded => "id",
+            UploadSort::Date => "timestamp IS NULL, timestamp DESC, id DESC",
+            UploadSort::Size => "file_size IS NULL, file_size DESC, id",
+            UploadSort::Name => "remote_path, id",
+        }
+    }
+}
+
+/// Filters for `Catalog::search`; unset fields match everything
+#[derive(Debug, Default)]
+pub struct UploadQuery {
+    /// Status, compared case-insensitively
+    pub status: Option<String>,
+    pub remote_prefix: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound
+    pub until: Option<DateTime<Utc>>,
+    pub min_size: Option<u64>,
+    pub hash_prefix: Option<String>,
+    pub sort: UploadSort,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl UploadQuery {
+    /// WHERE clause and its parameters
+    fn conditions(&self) -> (String, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(ref status) = self.status {
+            conditions.push("status = ? COLLATE NOCASE");
+            values.push(Value::Text(status.clone()));
+        }
+        if let Some(ref prefix) = self.remote_prefix {
+            conditions.push("substr(remote_path, 1, length(?)) = ?");
+            values.push(Value::Text(prefix.clone()));
+            values.push(Value::Text(prefix.clone()));
+        }
+        if let Some(ref since) = self.since {
+            conditions.push("timestamp >= ?");
+            values.push(Value::Text(format_timestamp(since)));
+        }
+        if let Some(ref until) = self.until {
+            conditions.push("timestamp < ?");
+            values.push(Value::Text(format_timestamp(until)));
+        }
+        if let Some(min_size) = self.min_size {
+            conditions.push("file_size >= ?");
+            values.push(Value::Integer(min_size as i64));
+        }
+        if let Some(ref prefix) = self.hash_prefix {
+            // A range scan keeps the hash index usable for prefix searches
+            conditions.push("blake3_hash >= ? AND blake3_hash < ? || 'g'");
+            values.push(Value::Text(prefix.to_lowercase()));
+            values.push(Value::Text(prefix.to_lowercase()));
+        }
+
+        if conditions.is_empty() {
+            (String::new(), values)
+        } else {
+            (format!("WHERE {}", conditions.join(" AND ")), values)
+        }
+    }
+}
+
+/// Totals over every record matching a query, ignoring limit and offset
+#[derive(Debug, Default, PartialEq)]
+pub struct UploadTotals {
+    pub count: usize,
+    pub total_size: u64,
+    /// Records without a known size (from before sizes were logged)
+    pub unknown_size: usize,
+    /// Record count per status, most common first
+    pub by_status: Vec<(String, usize)>,
+}
+
+/// A public link created for a remote file
+#[derive(Debug, PartialEq)]
+pub struct PublicLink {
+    pub link_hash: String,
+    pub remote_path: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// One integrity check recorded by `pipe audit`
+#[derive(Debug, PartialEq)]
+pub struct AuditRecord {
+    pub remote_path: String,
+    pub checked_at: Option<DateTime<Utc>>,
+    /// `match`, `mismatch`, `unknown` or `error`
+    pub result: String,
+    pub detail: Option<String>,
+}
+
+/// Outcome of importing a JSON-lines upload log
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub malformed: usize,
+}
+
+impl Catalog {
+    pub fn default_path() -> PathBuf {
+        match dirs::home_dir() {
+            Some(home_dir) => home_dir.join(".pipe-cli-uploads.db"),
+            None => PathBuf::from(".pipe-cli-uploads.db"),
+        }
+    }
+
+    /// Open the default catalog, importing the old upload log on first use
+    pub fn open_default() -> Result<Self> {
+        let catalog = Self::open(&Self::default_path())?;
+        let legacy_log = crate::get_upload_log_path();
+        if !catalog.has_imported(&legacy_log)? && legacy_log.exists() {
+            let Some(summary) = catalog.import_log(&legacy_log)? else {
+                // Another process imported it first
+                return Ok(catalog);
+            };
+            eprintln!(
+                "Imported {} upload record(s) from {} into the upload catalog",
+                summary.imported,
+                legacy_log.display()
+            );
+            if summary.malformed > 0 {
+                eprintln!("⚠️  Skipped {} malformed line(s) in the old log", summary.malformed);
+            }
+        }
+        Ok(catalog)
+    }
+
+    /// Open or create a catalog file
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open upload catalog {}: {}", path.display(), e))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // WAL lets readers run while another process is recording an upload
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+
+        // Immediate so concurrent opens wait on the busy timeout instead of
+        // failing to upgrade a read lock, and only one of them migrates
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)?;
+        let version: i64 = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(anyhow!(
+                "Upload catalog {} was written by a newer version (schema {})",
+                path.display(),
+                version
+            ));
+        }
+        tx.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS uploads (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 local_path TEXT NOT NULL,
+                 remote_path TEXT NOT NULL,
+                 status TEXT NOT NULL,
+                 message TEXT NOT NULL,
+                 blake3_hash TEXT,
+                 file_size INTEGER,
+                 timestamp TEXT,
+                 cost REAL
+             );
+             CREATE INDEX IF NOT EXISTS uploads_remote_path ON uploads (remote_path);
+             CREATE INDEX IF NOT EXISTS uploads_local_path ON uploads (local_path);
+             CREATE INDEX IF NOT EXISTS uploads_blake3_hash ON uploads (blake3_hash);
+             CREATE TABLE IF NOT EXISTS imported_logs (
+                 path TEXT PRIMARY KEY,
+                 imported_at TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS public_links (
+                 link_hash TEXT PRIMARY KEY,
+                 remote_path TEXT NOT NULL,
+                 created_at TEXT
+             );
+             CREATE INDEX IF NOT EXISTS public_links_remote_path ON public_links (remote_path);
+             CREATE TABLE IF NOT EXISTS audits (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 remote_path TEXT NOT NULL,
+                 checked_at TEXT NOT NULL,
+                 result TEXT NOT NULL,
+                 detail TEXT
+             );
+             CREATE INDEX IF NOT EXISTS audits_remote_path ON audits (remote_path);
+             {}
+             PRAGMA user_version = {};",
+            // Version 1 catalogs predate upload costs
+            if version == 1 { "ALTER TABLE uploads ADD COLUMN cost REAL;" } else { "" },
+            SCHEMA_VERSION
+        ))?;
+        tx.commit()?;
+        Ok(Self { conn })
+    }
+
+    /// Record an upload attempt
+    pub fn record(&self, entry: &UploadLogEntry) -> Result<i64> {
+        insert(&self.conn, entry)?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Whether a JSON-lines log was already imported
+    pub fn has_imported(&self, log_path: &Path) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM imported_logs WHERE path = ?1",
+                params![log_path.to_string_lossy()],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Import a JSON-lines upload log in one transaction. Lines that do not
+    /// parse are counted instead of silently dropped. Returns None if the log
+    /// was already imported.
+    pub fn import_log(&self, log_path: &Path) -> Result<Option<ImportSummary>> {
+        let contents = fs::read_to_string(log_path)?;
+        // Take the write lock before checking, so two processes opening the
+        // catalog at once cannot both import the log
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        if self.has_imported(log_path)? {
+            return Ok(None);
+        }
+        let mut summary = ImportSummary::default();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<UploadLogEntry>(line) {
+                Ok(entry) => {
+                    insert(&tx, &entry)?;
+                    summary.imported += 1;
+                }
+                Err(_) => summary.malformed += 1,
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO imported_logs (path, imported_at) VALUES (?1, ?2)",
+            params![log_path.to_string_lossy(), format_timestamp(&Utc::now())],
+        )?;
+        tx.commit()?;
+        Ok(Some(summary))
+    }
+
+    /// All records, oldest first
+    pub fn entries(&self) -> Result<Vec<CatalogEntry>> {
+        self.query(&format!("SELECT {} FROM uploads ORDER BY id", COLUMNS), params![])
+    }
+
+    /// Records matching a query, sorted and paged
+    pub fn search(&self, query: &UploadQuery) -> Result<Vec<CatalogEntry>> {
+        let (conditions, mut values) = query.conditions();
+        let sql = format!(
+            "SELECT {} FROM uploads {} ORDER BY {} LIMIT ? OFFSET ?",
+            COLUMNS,
+            conditions,
+            query.sort.order_by()
+        );
+        values.push(Value::Integer(query.limit.map_or(-1, |limit| limit as i64)));
+        values.push(Value::Integer(query.offset as i64));
+        self.query(&sql, params_from_iter(values))
+    }
+
+    /// Count and size of the records matching a query
+    pub fn totals(&self, query: &UploadQuery) -> Result<UploadTotals> {
+        let (conditions, values) = query.conditions();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT status, COUNT(*), COALESCE(SUM(file_size), 0), COUNT(*) - COUNT(file_size)
+             FROM uploads {} GROUP BY status ORDER BY COUNT(*) DESC, status",
+            conditions
+        ))?;
+        let mut rows = stmt.query(params_from_iter(values))?;
+
+        let mut totals = UploadTotals::default();
+        while let Some(row) = rows.next()? {
+            let count = row.get::<_, i64>(1)? as usize;
+            totals.count += count;
+            totals.total_size += row.get::<_, i64>(2)? as u64;
+            totals.unknown_size += row.get::<_, i64>(3)? as usize;
+            totals.by_status.push((row.get(0)?, count));
+        }
+        Ok(totals)
+    }
+
+    /// Successful uploads whose remote path starts with `prefix`
+    pub fn successful_with_prefix(&self, prefix: &str) -> Result<Vec<CatalogEntry>> {
+        self.query(
+            &format!(
+                "SELECT {} FROM uploads
+                 WHERE status = 'SUCCESS' AND substr(remote_path, 1, length(?1)) = ?1
+                 ORDER BY id",
+                COLUMNS
+            ),
+            params![prefix],
+        )
+    }
+
+    /// The latest successful record for each remote path under `prefix`,
+    /// ordered by remote path. Files recorded as deleted afterwards are left out.
+    pub fn latest_uploads(&self, prefix: &str) -> Result<Vec<CatalogEntry>> {
+        self.query(
+            &format!(
+                "SELECT {} FROM uploads WHERE id IN ({}) ORDER BY remote_path",
+                COLUMNS, LATEST_UPLOAD_IDS
+            ),
+            params![prefix],
+        )
+    }
+
+    /// Records whose local path contains `pattern`
+    pub fn find_by_local_path(&self, pattern: &str) -> Result<Vec<CatalogEntry>> {
+        self.query(
+            &format!(
+                "SELECT {} FROM uploads WHERE instr(local_path, ?1) > 0 ORDER BY id",
+                COLUMNS
+            ),
+            params![pattern],
+        )
+    }
+
+    /// Records whose Blake3 hash starts with `prefix`
+    pub fn find_by_hash_prefix(&self, prefix: &str) -> Result<Vec<CatalogEntry>> {
+        self.search(&UploadQuery {
+            hash_prefix: Some(prefix.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Local paths that were uploaded (or handed to a background upload)
+    pub fn uploaded_local_paths(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT local_path FROM uploads
+             WHERE instr(status, 'SUCCESS') > 0 OR instr(status, 'BACKGROUND') > 0",
+        )?;
+        let paths = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<HashSet<String>>>()?;
+        Ok(paths)
+    }
+
+    /// Records for an exact remote path, oldest first
+    pub fn find_by_remote_path(&self, remote_path: &str) -> Result<Vec<CatalogEntry>> {
+        self.query(
+            &format!("SELECT {} FROM uploads WHERE remote_path = ?1 ORDER BY id", COLUMNS),
+            params![remote_path],
+        )
+    }
+
+    /// Remember a public link so `file-info` can list it
+    pub fn record_public_link(&self, link_hash: &str, remote_path: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO public_links (link_hash, remote_path, created_at)
+             VALUES (?1, ?2, ?3)",
+            params![link_hash, remote_path, format_timestamp(&Utc::now())],
+        )?;
+        Ok(())
+    }
+
+    /// Forget a deleted public link
+    pub fn remove_public_link(&self, link_hash: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM public_links WHERE link_hash = ?1", params![link_hash])?;
+        Ok(())
+    }
+
+    /// Public links created from this machine for a remote file
+    pub fn public_links(&self, remote_path: &str) -> Result<Vec<PublicLink>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT link_hash, remote_path, created_at FROM public_links
+             WHERE remote_path = ?1 ORDER BY created_at",
+        )?;
+        let links = stmt
+            .query_map(params![remote_path], |row| {
+                Ok(PublicLink {
+                    link_hash: row.get(0)?,
+                    remote_path: row.get(1)?,
+                    created_at: parse_timestamp(row.get(2)?),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(links)
+    }
+
+    /// Record the result of an integrity check
+    pub fn record_audit(&self, remote_path: &str, result: &str, detail: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO audits (remote_path, checked_at, result, detail) VALUES (?1, ?2, ?3, ?4)",
+            params![remote_path, format_timestamp(&Utc::now()), result, detail],
+        )?;
+        Ok(())
+    }
+
+    /// Uploaded remote paths under `prefix` whose hash was never checked.
+    /// Audits that could not verify the file (`unknown`, e.g. encrypted
+    /// uploads) do not count.
+    pub fn never_audited(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT remote_path FROM uploads
+             WHERE id IN ({})
+               AND remote_path NOT IN (
+                   SELECT remote_path FROM audits WHERE result IN ('match', 'mismatch')
+               )
+             ORDER BY remote_path",
+            LATEST_UPLOAD_IDS
+        ))?;
+        let paths = stmt
+            .query_map(params![prefix], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(paths)
+    }
+
+    /// Files under `prefix` whose most recent audit found a mismatch or error
+    pub fn failed_audits(&self, prefix: &str) -> Result<Vec<AuditRecord>> {
+        self.latest_audits(prefix, "'mismatch', 'error'")
+    }
+
+    /// Files under `prefix` whose most recent audit could not check the hash
+    pub fn unverifiable_audits(&self, prefix: &str) -> Result<Vec<AuditRecord>> {
+        self.latest_audits(prefix, "'unknown'")
+    }
+
+    // Latest audit per path under `prefix`, kept if its result is in `results`
+    fn latest_audits(&self, prefix: &str, results: &str) -> Result<Vec<AuditRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT remote_path, checked_at, result, detail FROM audits
+             WHERE id IN (
+                 SELECT MAX(id) FROM audits
+                 WHERE substr(remote_path, 1, length(?1)) = ?1
+                 GROUP BY remote_path
+             )
+               AND result IN ({})
+             ORDER BY remote_path",
+            results
+        ))?;
+        let records = stmt
+            .query_map(params![prefix], |row| {
+                Ok(AuditRecord {
+                    remote_path: row.get(0)?,
+                    checked_at: parse_timestamp(row.get(1)?),
+                    result: row.get(2)?,
+                    detail: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    /// Fill in the hash and size of a record
+    pub fn set_hash(&self, id: i64, blake3_hash: &str, file_size: u64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE uploads SET blake3_hash = ?1, file_size = ?2 WHERE id = ?3",
+            params![blake3_hash, file_size as i64, id],
+        )?;
+        Ok(())
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<CatalogEntry>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let entries = stmt
+            .query_map(params, read_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+}
+
+fn insert(conn: &Connection, entry: &UploadLogEntry) -> Result<()> {
+    conn.execute(
+        "INSERT INTO uploads (local_path, remote_path, status, message, blake3_hash, file_size, timestamp, cost)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            entry.local_path,
+            entry.remote_path,
+            entry.status,
+            entry.message,
+            entry.blake3_hash,
+            entry.file_size.map(|size| size as i64),
+            entry.timestamp.as_ref().map(format_timestamp),
+            entry.cost,
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_row(row: &Row) -> rusqlite::Result<CatalogEntry> {
+    Ok(CatalogEntry {
+        id: row.get(0)?,
+        entry: UploadLogEntry {
+            local_path: row.get(1)?,
+            remote_path: row.get(2)?,
+            status: row.get(3)?,
+            message: row.get(4)?,
+            blake3_hash: row.get(5)?,
+            file_size: row.get::<_, Option<i64>>(6)?.map(|size| size as u64),
+            timestamp: parse_timestamp(row.get(7)?),
+            cost: row.get(8)?,
+        },
+    })
+}
+
+fn parse_timestamp(timestamp: Option<String>) -> Option<DateTime<Utc>> {
+    timestamp
+        .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Fixed-width RFC 3339, so timestamps sort correctly as text
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(local: &str, remote: &str, status: &str, hash: Option<&str>) -> UploadLogEntry {
+        UploadLogEntry {
+            local_path: local.to_string(),
+            remote_path: remote.to_string(),
+            status: status.to_string(),
+            message: String::new(),
+            blake3_hash: hash.map(str::to_string),
+            file_size: hash.map(|_| 42),
+            timestamp: Some(Utc::now()),
+            cost: None,
+        }
+    }
+
+    #[test]
+    fn test_catalog_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let catalog = Catalog::open(&temp_dir.path().join("uploads.db")).unwrap();
+        catalog.record(&entry("/photos/a.jpg", "photos/a.jpg", "SUCCESS", Some("abc123"))).unwrap();
+        catalog.record(&entry("/photos/b.jpg", "photos/b.jpg", "FAIL", None)).unwrap();
+        let id = catalog.record(&entry("/docs/c.pdf", "docs/c.pdf", "SUCCESS", None)).unwrap();
+        catalog
+            .record(&entry("/big.iso", "big.iso", "BACKGROUND-SUCCESS", None))
+            .unwrap();
+
+        let photos = catalog.successful_with_prefix("photos/").unwrap();
+        assert_eq!(photos.len(), 1);
+        assert_eq!(photos[0].entry.local_path, "/photos/a.jpg");
+        assert!(photos[0].entry.timestamp.is_some());
+
+        assert_eq!(catalog.find_by_local_path("photos").unwrap().len(), 2);
+        assert_eq!(catalog.find_by_hash_prefix("ABC").unwrap().len(), 1);
+        assert!(catalog.find_by_hash_prefix("abd").unwrap().is_empty());
+
+        catalog
+            .record(&entry("/photos/a2.jpg", "photos/a.jpg", "PRIORITY SUCCESS", Some("abc999")))
+            .unwrap();
+        let latest = catalog.latest_uploads("").unwrap();
+        let latest: Vec<(&str, Option<&str>)> = latest
+            .iter()
+            .map(|e| (e.entry.remote_path.as_str(), e.entry.blake3_hash.as_deref()))
+            .collect();
+        assert_eq!(
+            latest,
+            [("big.iso", None), ("docs/c.pdf", None), ("photos/a.jpg", Some("abc999"))]
+        );
+        catalog.record(&entry("/big.iso", "big.iso", DELETED_STATUS, None)).unwrap();
+        assert_eq!(catalog.latest_uploads("b").unwrap().len(), 0);
+
+        let uploaded = catalog.uploaded_local_paths().unwrap();
+        assert!(uploaded.contains("/photos/a.jpg") && uploaded.contains("/big.iso"));
+        assert!(!uploaded.contains("/photos/b.jpg"));
+
+        catalog.set_hash(id, "def456", 7).unwrap();
+        let found = catalog.find_by_hash_prefix("def").unwrap();
+        assert_eq!(found[0].entry.file_size, Some(7));
+    }
+
+    #[test]
+    fn test_search_filters_sorting_and_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let catalog = Catalog::open(&temp_dir.path().join("uploads.db")).unwrap();
+        let now = Utc::now();
+        let records = [
+            ("photos/a.jpg", "SUCCESS", Some(300), now - chrono::Duration::days(10), "aa11"),
+            ("photos/b.jpg", "SUCCESS", Some(100), now - chrono::Duration::days(1), "bb22"),
+            ("photos/c.jpg", "FAIL", None, now - chrono::Duration::days(2), "cc33"),
+            ("docs/d.pdf", "SUCCESS", Some(200), now, "dd44"),
+        ];
+        for (remote, status, size, timestamp, hash) in records {
+            let mut record = entry(&format!("/{}", remote), remote, status, Some(hash));
+            record.file_size = size;
+            record.timestamp = Some(timestamp);
+            catalog.record(&record).unwrap();
+        }
+        let remotes = |query: &UploadQuery| -> Vec<String> {
+            catalog
+                .search(query)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.entry.remote_path)
+                .collect()
+        };
+
+        let photos = UploadQuery {
+            remote_prefix: Some("photos/".to_string()),
+            sort: UploadSort::Size,
+            ..Default::default()
+        };
+        assert_eq!(remotes(&photos), ["photos/a.jpg", "photos/b.jpg", "photos/c.jpg"]);
+
+        let recent_successes = UploadQuery {
+            status: Some("success".to_string()),
+            since: Some(now - chrono::Duration::days(5)),
+            sort: UploadSort::Date,
+            ..Default::default()
+        };
+        assert_eq!(remotes(&recent_successes), ["docs/d.pdf", "photos/b.jpg"]);
+
+        let paged = UploadQuery {
+            min_size: Some(150),
+            sort: UploadSort::Name,
+            limit: Some(1),
+            offset: 1,
+            ..Default::default()
+        };
+        assert_eq!(remotes(&paged), ["photos/a.jpg"]);
+        assert_eq!(
+            remotes(&UploadQuery { hash_prefix: Some("CC".to_string()), ..Default::default() }),
+            ["photos/c.jpg"]
+        );
+
+        // Totals cover every match, not just the page
+        let totals = catalog.totals(&photos).unwrap();
+        assert_eq!(totals.count, 3);
+        assert_eq!(totals.total_size, 400);
+        assert_eq!(totals.unknown_size, 1);
+        assert_eq!(totals.by_status, [("SUCCESS".to_string(), 2), ("FAIL".to_string(), 1)]);
+        assert_eq!(catalog.totals(&paged).unwrap().count, 2);
+    }
+
+    #[test]
+    fn test_import_legacy_log_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("uploads.json");
+        let mut log = String::new();
+        log.push_str(&serde_json::to_string(&entry("/a", "a", "SUCCESS", Some("aa"))).unwrap());
+        log.push_str("\n{not json\n\n");
+        log.push_str(&serde_json::to_string(&entry("/b", "b", "FAIL", None)).unwrap());
+        fs::write(&log_path, log).unwrap();
+
+        let db_path = temp_dir.path().join("uploads.db");
+        let catalog = Catalog::open(&db_path).unwrap();
+        assert!(!catalog.has_imported(&log_path).unwrap());
+        let summary = catalog.import_log(&log_path).unwrap();
+        assert_eq!(summary, Some(ImportSummary { imported: 2, malformed: 1 }));
+
+        // Concurrent openers import the log only once
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let (db_path, log_path) = (db_path.clone(), log_path.clone());
+                std::thread::spawn(move || Catalog::open(&db_path).unwrap().import_log(&log_path).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), None);
+        }
+
+        // Reopening keeps the records and remembers the import
+        let catalog = Catalog::open(&db_path).unwrap();
+        assert!(catalog.has_imported(&log_path).unwrap());
+        let entries = catalog.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].entry.blake3_hash.as_deref(), Some("aa"));
+        assert_eq!(entries[1].entry.status, "FAIL");
+    }
+
+    #[test]
+    fn test_upgrade_adds_costs_and_public_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("uploads.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE uploads (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 local_path TEXT NOT NULL,
+                 remote_path TEXT NOT NULL,
+                 status TEXT NOT NULL,
+                 message TEXT NOT NULL,
+                 blake3_hash TEXT,
+                 file_size INTEGER,
+                 timestamp TEXT
+             );
+             INSERT INTO uploads (local_path, remote_path, status, message)
+             VALUES ('/a', 'a', 'SUCCESS', '');
+             PRAGMA user_version = 1;",
+        )
+        .unwrap();
+        drop(conn);
+
+        let catalog = Catalog::open(&db_path).unwrap();
+        let mut paid = entry("/a", "a", "SUCCESS", Some("aa"));
+        paid.cost = Some(0.25);
+        catalog.record(&paid).unwrap();
+        let records = catalog.find_by_remote_path("a").unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].entry.cost, None);
+        assert_eq!(records[1].entry.cost, Some(0.25));
+
+        catalog.record_public_link("hash1", "a").unwrap();
+        catalog.record_public_link("hash2", "b").unwrap();
+        let links = catalog.public_links("a").unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].link_hash, "hash1");
+        catalog.remove_public_link("hash1").unwrap();
+        assert!(catalog.public_links("a").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_audit_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let catalog = Catalog::open(&temp_dir.path().join("uploads.db")).unwrap();
+        for remote in ["backups/a", "backups/b", "backups/c", "other/d"] {
+            catalog.record(&entry(remote, remote, "SUCCESS", Some("aa"))).unwrap();
+        }
+        catalog.record(&entry("x", "backups/failed", "FAIL", None)).unwrap();
+
+        catalog.record_audit("backups/a", "mismatch", Some("remote ff")).unwrap();
+        catalog.record_audit("backups/b", "error", Some("404")).unwrap();
+        // A later good check clears the earlier failure
+        catalog.record_audit("backups/b", "match", None).unwrap();
+
+        // An unverifiable check is not evidence that the file is intact
+        catalog.record_audit("backups/c", "unknown", Some("encrypted: password")).unwrap();
+
+        assert_eq!(catalog.never_audited("backups/").unwrap(), ["backups/c"]);
+        let unverifiable = catalog.unverifiable_audits("backups/").unwrap();
+        assert_eq!(unverifiable.len(), 1);
+        assert_eq!(unverifiable[0].remote_path, "backups/c");
+        let failed = catalog.failed_audits("backups/").unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].remote_path, "backups/a");
+        assert_eq!(failed[0].detail.as_deref(), Some("remote ff"));
+        assert!(failed[0].checked_at.is_some());
+        assert!(catalog.failed_audits("other/").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Magic bytes to identify compressed (unencrypted) files
+const MAGIC_BYTES: &[u8] = b"PIPE-ZST";
+
+/// Version of the compressed container format
+const VERSION: u8 = 1;
+
+/// Algorithm identifier for zstd in the container header
+const ALGORITHM_ZSTD: u8 = 1;
+
+/// Size of the container header: magic + version + algorithm
+pub(crate) const HEADER_SIZE: usize = 8 + 1 + 1;
+
+/// Default zstd level when only `zstd` is given
+pub const DEFAULT_LEVEL: i32 = 3;
+
+/// Compression settings requested with `--compress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionSpec {
+    pub level: i32,
+    /// The input is already a zstd frame (see `CompressedFile`); encryption
+    /// only sets the compression flag
+    pub precompressed: bool,
+}
+
+impl CompressionSpec {
+    /// Parses `zstd` or `zstd:<level>` (levels 1-22)
+    pub fn parse(s: &str) -> Result<Self> {
+        let (algorithm, level) = match s.split_once(':') {
+            Some((algorithm, level)) => (algorithm, Some(level)),
+            None => (s, None),
+        };
+
+        if !algorithm.eq_ignore_ascii_case("zstd") {
+            return Err(anyhow!(
+                "Unsupported compression '{}'. Use zstd or zstd:<level>",
+                algorithm
+            ));
+        }
+
+        let level = match level {
+            Some(l) => l
+                .parse::<i32>()
+                .map_err(|_| anyhow!("Invalid zstd level '{}'", l))?,
+            None => DEFAULT_LEVEL,
+        };
+
+        if !(1..=22).contains(&level) {
+            return Err(anyhow!("zstd level must be between 1 and 22, got {}", level));
+        }
+
+        Ok(Self {
+            level,
+            precompressed: false,
+        })
+    }
+}
+
+/// Counts bytes written without storing them
+struct CountingWriter {
+    count: u64,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.count += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Compresses a stream into the PIPE-ZST container format
+pub fn compress_stream<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    spec: CompressionSpec,
+) -> Result<()> {
+    writer.write_all(MAGIC_BYTES)?;
+    writer.write_all(&[VERSION, ALGORITHM_ZSTD])?;
+    zstd::stream::copy_encode(&mut reader, &mut writer, spec.level)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decompresses a PIPE-ZST container stream
+pub fn decompress_stream<R: Read, W: Write>(mut reader: R, mut writer: W) -> Result<()> {
+    let mut header = [0u8; HEADER_SIZE];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| anyhow!("Invalid compressed header"))?;
+
+    if &header[..8] != MAGIC_BYTES {
+        return Err(anyhow!("Not a compressed file"));
+    }
+    if header[8] != VERSION {
+        return Err(anyhow!("Unsupported compression version"));
+    }
+    if header[9] != ALGORITHM_ZSTD {
+        return Err(anyhow!("Unsupported compression algorithm {}", header[9]));
+    }
+
+    zstd::stream::copy_decode(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Returns the size a file would occupy once compressed (including header)
+pub fn compressed_size(path: &Path, spec: CompressionSpec) -> Result<u64> {
+    let input = BufReader::new(File::open(path)?);
+    let mut counter = CountingWriter { count: 0 };
+    compress_stream(input, &mut counter, spec)?;
+    Ok(counter.count)
+}
+
+/// Compresses a file into a temporary file that is removed on drop
+pub fn compress_to_temp_file(path: &Path, spec: CompressionSpec) -> Result<NamedTempFile> {
+    let input = BufReader::new(File::open(path)?);
+    let temp = NamedTempFile::new()?;
+    compress_stream(input, BufWriter::new(temp.as_file()), spec)?;
+    Ok(temp)
+}
+
+/// A file compressed once into a temporary PIPE-ZST container, so the same
+/// bytes serve the size estimate, every upload attempt and encryption
+pub struct CompressedFile {
+    spec: CompressionSpec,
+    temp: NamedTempFile,
+}
+
+impl CompressedFile {
+    pub fn new(path: &Path, spec: CompressionSpec) -> Result<Self> {
+        Ok(Self {
+            spec,
+            temp: compress_to_temp_file(path, spec)?,
+        })
+    }
+
+    /// The container, for uploading without encryption
+    pub fn path(&self) -> &Path {
+        self.temp.path()
+    }
+
+    /// Size of the container, header included
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.temp.as_file().metadata()?.len())
+    }
+
+    /// The zstd frame after the container header, with the spec to pass to
+    /// encryption so it is not compressed again
+    pub fn frame(&self) -> Result<(File, CompressionSpec)> {
+        let mut file = File::open(self.temp.path())?;
+        file.seek(SeekFrom::Start(HEADER_SIZE as u64))?;
+        Ok((
+            file,
+            CompressionSpec {
+                precompressed: true,
+                ..self.spec
+            },
+        ))
+    }
+}
+
+/// Decompresses a downloaded file in place if it carries the PIPE-ZST header.
+/// Returns true when the file was decompressed.
+pub fn decompress_file_in_place(path: &Path) -> Result<bool> {
+    if !is_compressed_file(File::open(path)?)? {
+        return Ok(false);
+    }
+
+    let temp_path = path.with_file_name(format!(
+        "{}.unzst.tmp",
+        path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    ));
+
+    let result = (|| {
+        let input = BufReader::new(File::open(path)?);
+        let output = BufWriter::new(File::create(&temp_path)?);
+        decompress_stream(input, output)
+    })();
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(anyhow!("Decompression failed: {}", e));
+    }
+
+    std::fs::rename(&temp_path, path)?;
+    Ok(true)
+}
+
+/// Check if a file is compressed by reading the magic bytes
+pub fn is_compressed_file<R: Read + Seek>(mut reader: R) -> Result<bool> {
+    let mut magic = [0u8; 8];
+    match reader.read_exact(&mut magic) {
+        Ok(_) => {
+            reader.seek(SeekFrom::Start(0))?;
+            Ok(magic == MAGIC_BYTES)
+        }
+        Err(_) => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(CompressionSpec::parse("zstd").unwrap().level, DEFAULT_LEVEL);
+        assert_eq!(CompressionSpec::parse("zstd:19").unwrap().level, 19);
+        assert_eq!(CompressionSpec::parse("ZSTD:1").unwrap().level, 1);
+        assert!(CompressionSpec::parse("gzip").is_err());
+        assert!(CompressionSpec::parse("zstd:0").is_err());
+        assert!(CompressionSpec::parse("zstd:23").is_err());
+        assert!(CompressionSpec::parse("zstd:fast").is_err());
+    }
+
+    #[test]
+    fn test_compress_decompress_roundtrip() {
+        let data = b"timestamp,level,message\n".repeat(1000);
+        let spec = CompressionSpec::parse("zstd:5").unwrap();
+
+        let mut compressed = Vec::new();
+        compress_stream(Cursor::new(&data), &mut compressed, spec).unwrap();
+        assert!(compressed.len() < data.len() / 10);
+        assert!(is_compressed_file(Cursor::new(&compressed)).unwrap());
+
+        let mut decompressed = Vec::new();
+        decompress_stream(Cursor::new(&compressed), &mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_decompress_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = b"some log line that repeats\n".repeat(500);
+        let source = temp_dir.path().join("source.log");
+        std::fs::write(&source, &data).unwrap();
+
+        let spec = CompressionSpec::parse("zstd").unwrap();
+        let estimate = compressed_size(&source, spec).unwrap();
+        let temp = CompressedFile::new(&source, spec).unwrap();
+        assert_eq!(temp.size().unwrap(), estimate);
+
+        let downloaded = temp_dir.path().join("downloaded.log");
+        std::fs::copy(temp.path(), &downloaded).unwrap();
+        assert!(decompress_file_in_place(&downloaded).unwrap());
+        assert_eq!(std::fs::read(&downloaded).unwrap(), data);
+
+        // Plain files are left alone
+        assert!(!decompress_file_in_place(&downloaded).unwrap());
+        assert_eq!(std::fs::read(&downloaded).unwrap(), data);
+    }
+}
@@ -1,5 +1,5 @@
 use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit, OsRng},
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
     Aes256Gcm, Key, Nonce,
 };
 use anyhow::{anyhow, Result};
@@ -7,11 +7,18 @@ use argon2::{
     password_hash::{PasswordHasher, SaltString},
     Argon2,
 };
+use hkdf::Hkdf;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::compression::CompressionSpec;
+
 /// Size of the AES-256 key in bytes
 const KEY_SIZE: usize = 32;
 
@@ -33,12 +40,138 @@ const MAGIC_BYTES: &[u8] = b"PIPE-ENC";
 /// Version of the encryption format
 const VERSION: u8 = 1;
 
+/// Version of the encryption format with a trailing flags byte
+const VERSION_WITH_FLAGS: u8 = 2;
+
+/// Header flag: plaintext was zstd-compressed before encryption
+pub const FLAG_ZSTD: u8 = 0x01;
+
+/// Header flag: an encrypted metadata block follows the header
+pub const FLAG_METADATA: u8 = 0x02;
+
+/// Header flag: content key is a random data key wrapped by a master key
+pub const FLAG_ENVELOPE: u8 = 0x04;
+
+/// Header flag: content key is an HKDF subkey of a password key shared by a
+/// batch of files; the per-file HKDF salt follows the flags byte
+pub const FLAG_SUBKEY: u8 = 0x08;
+
+/// HKDF info string for per-file subkeys
+const SUBKEY_INFO: &[u8] = b"pipe-cli file subkey v1";
+
+/// Upper bound for the wrapped data key block
+pub const MAX_KEY_BLOCK_SIZE: usize = 4096;
+
+/// Upper bound for the encrypted metadata block
+const MAX_METADATA_SIZE: usize = 64 * 1024;
+
+/// Chunk counter reserved for the metadata block nonce
+const METADATA_NONCE_COUNTER: u64 = u64::MAX;
+
 /// Secure container for encryption keys that zeroes memory on drop
-#[derive(Zeroize, ZeroizeOnDrop)]
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct EncryptionKey {
     pub key: [u8; KEY_SIZE],
 }
 
+/// Original file metadata, stored encrypted and authenticated in the header
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileMetadata {
+    /// Original file name (without directories)
+    pub name: String,
+    /// Plaintext size in bytes
+    pub size: u64,
+    /// Modification time as Unix seconds
+    pub modified: Option<i64>,
+    /// Unix permission bits
+    pub mode: Option<u32>,
+    /// Blake3 hash of the plaintext
+    pub blake3: String,
+}
+
+impl FileMetadata {
+    /// Collects metadata and the plaintext Blake3 hash for a local file
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let meta = std::fs::metadata(path)?;
+
+        let mut hasher = blake3::Hasher::new();
+        let mut file = std::fs::File::open(path)?;
+        std::io::copy(&mut file, &mut hasher)?;
+
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64);
+
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(meta.permissions().mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Ok(Self {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            size: meta.len(),
+            modified,
+            mode,
+            blake3: hasher.finalize().to_hex().to_string(),
+        })
+    }
+
+    /// File name safe to restore, with any directory components stripped
+    pub fn safe_name(&self) -> Option<String> {
+        Path::new(&self.name)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .filter(|n| !n.is_empty())
+    }
+
+    /// Restores the modification time and permissions on a decrypted file
+    pub fn apply_to(&self, path: &Path) -> Result<()> {
+        if let Some(secs) = self.modified {
+            let time = UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64);
+            std::fs::File::options()
+                .write(true)
+                .open(path)?
+                .set_modified(time)?;
+        }
+
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            // Never restore setuid/setgid/sticky bits from a downloaded file
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Header details that can be read without a key
+#[derive(Debug, Clone)]
+pub struct HeaderInfo {
+    pub version: u8,
+    pub flags: u8,
+    /// Whether the key is derived from a password (salt present)
+    pub password_protected: bool,
+    /// Size of the encrypted metadata block, if present
+    pub metadata_size: Option<usize>,
+    /// Master key that wraps the data key, for envelope-encrypted files
+    pub master_key_id: Option<String>,
+}
+
+impl HeaderInfo {
+    pub fn is_compressed(&self) -> bool {
+        self.flags & FLAG_ZSTD != 0
+    }
+}
+
 /// Header for encrypted files
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncryptedFileHeader {
@@ -50,18 +183,36 @@ pub struct EncryptedFileHeader {
     salt: Option<[u8; SALT_SIZE]>,
     /// Nonce for AES-GCM
     nonce: [u8; NONCE_SIZE],
+    /// Feature flags (version 2 only)
+    flags: u8,
+    /// Per-file HKDF salt for batch-derived subkeys (FLAG_SUBKEY only)
+    subkey_salt: Option<[u8; SALT_SIZE]>,
 }
 
 impl EncryptedFileHeader {
-    fn new(salt: Option<[u8; SALT_SIZE]>, nonce: [u8; NONCE_SIZE]) -> Self {
+    fn new(
+        salt: Option<[u8; SALT_SIZE]>,
+        nonce: [u8; NONCE_SIZE],
+        mut flags: u8,
+        subkey_salt: Option<[u8; SALT_SIZE]>,
+    ) -> Self {
         let mut magic = [0u8; 8];
         magic.copy_from_slice(MAGIC_BYTES);
 
+        if subkey_salt.is_some() {
+            flags |= FLAG_SUBKEY;
+        }
+
+        // Files without flags keep the version 1 layout so older clients can read them
+        let version = if flags == 0 { VERSION } else { VERSION_WITH_FLAGS };
+
         Self {
             magic,
-            version: VERSION,
+            version,
             salt,
             nonce,
+            flags,
+            subkey_salt,
         }
     }
 
@@ -80,6 +231,13 @@ impl EncryptedFileHeader {
         }
 
         bytes.extend_from_slice(&self.nonce);
+
+        if self.version == VERSION_WITH_FLAGS {
+            bytes.push(self.flags);
+        }
+        if let Some(subkey_salt) = &self.subkey_salt {
+            bytes.extend_from_slice(subkey_salt);
+        }
         bytes
     }
 
@@ -96,7 +254,7 @@ impl EncryptedFileHeader {
         }
 
         let version = bytes[8];
-        if version != VERSION {
+        if version != VERSION && version != VERSION_WITH_FLAGS {
             return Err(anyhow!("Unsupported encryption version"));
         }
 
@@ -112,19 +270,343 @@ impl EncryptedFileHeader {
         let mut nonce = [0u8; NONCE_SIZE];
         nonce.copy_from_slice(&bytes[10 + SALT_SIZE..10 + SALT_SIZE + NONCE_SIZE]);
 
+        let flags = if version == VERSION_WITH_FLAGS {
+            *bytes
+                .get(Self::size())
+                .ok_or_else(|| anyhow!("Invalid header size"))?
+        } else {
+            0
+        };
+
+        let subkey_salt = if flags & FLAG_SUBKEY != 0 {
+            let start = Self::size() + 1;
+            let salt_bytes = bytes
+                .get(start..start + SALT_SIZE)
+                .ok_or_else(|| anyhow!("Invalid header size"))?;
+            let mut subkey_salt = [0u8; SALT_SIZE];
+            subkey_salt.copy_from_slice(salt_bytes);
+            Some(subkey_salt)
+        } else {
+            None
+        };
+
         Ok(Self {
             magic,
             version,
             salt,
             nonce,
+            flags,
+            subkey_salt,
         })
     }
 
+    /// Reads a header from a stream, including the flags byte for version 2
+    /// and the encrypted metadata block when present
+    fn read_from<R: Read>(reader: &mut R) -> Result<ParsedHeader> {
+        let mut parsed = Self::read_through_key_block(reader)?;
+
+        parsed.metadata_block = if parsed.header.flags & FLAG_METADATA != 0 {
+            let mut len_bytes = [0u8; 4];
+            reader.read_exact(&mut len_bytes)?;
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            if len == 0 || len > MAX_METADATA_SIZE {
+                return Err(anyhow!("Invalid metadata block size"));
+            }
+            let mut block = vec![0u8; len];
+            reader.read_exact(&mut block)?;
+            Some(block)
+        } else {
+            None
+        };
+
+        Ok(parsed)
+    }
+
+    /// Reads a header up to and including the wrapped key block, leaving any
+    /// metadata block unread
+    fn read_through_key_block<R: Read>(reader: &mut R) -> Result<ParsedHeader> {
+        let mut header_bytes = vec![0u8; Self::size()];
+        reader.read_exact(&mut header_bytes)?;
+
+        if header_bytes[8] == VERSION_WITH_FLAGS {
+            let mut flags = [0u8; 1];
+            reader.read_exact(&mut flags)?;
+            header_bytes.push(flags[0]);
+
+            if flags[0] & FLAG_SUBKEY != 0 {
+                let mut subkey_salt = [0u8; SALT_SIZE];
+                reader.read_exact(&mut subkey_salt)?;
+                header_bytes.extend_from_slice(&subkey_salt);
+            }
+        }
+
+        let header = Self::from_bytes(&header_bytes)?;
+
+        let key_block = if header.flags & FLAG_ENVELOPE != 0 {
+            let mut len_bytes = [0u8; 4];
+            reader.read_exact(&mut len_bytes)?;
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            if len == 0 || len > MAX_KEY_BLOCK_SIZE {
+                return Err(anyhow!("Invalid wrapped key block size"));
+            }
+            let mut block = vec![0u8; len];
+            reader.read_exact(&mut block)?;
+            Some(serde_json::from_slice(&block)?)
+        } else {
+            None
+        };
+
+        Ok(ParsedHeader {
+            header,
+            raw: header_bytes,
+            key_block,
+            metadata_block: None,
+        })
+    }
+
+    /// Size of the version 1 header
     fn size() -> usize {
         8 + 1 + 1 + SALT_SIZE + NONCE_SIZE
     }
 }
 
+/// Header as read from a stream, with the raw bytes that authenticate the metadata
+struct ParsedHeader {
+    header: EncryptedFileHeader,
+    raw: Vec<u8>,
+    key_block: Option<WrappedKeyBlock>,
+    metadata_block: Option<Vec<u8>>,
+}
+
+impl ParsedHeader {
+    /// Content key for a password-protected file, applying the per-file HKDF
+    /// step for batch-encrypted files
+    fn password_key(&self, password: &str) -> Result<EncryptionKey> {
+        let salt = self
+            .header
+            .salt
+            .ok_or_else(|| anyhow!("File was encrypted with a key, not a password"))?;
+        let key = derive_key_from_password(password, &salt)?;
+        match self.header.subkey_salt {
+            Some(ref subkey_salt) => derive_subkey(&key, subkey_salt),
+            None => Ok(key),
+        }
+    }
+
+    /// Writes the header and any blocks that follow it
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.raw)?;
+        if let Some(ref key_block) = self.key_block {
+            let block = serde_json::to_vec(key_block)?;
+            writer.write_all(&(block.len() as u32).to_le_bytes())?;
+            writer.write_all(&block)?;
+        }
+        if let Some(ref block) = self.metadata_block {
+            writer.write_all(&(block.len() as u32).to_le_bytes())?;
+            writer.write_all(block)?;
+        }
+        Ok(())
+    }
+
+    /// Unwraps the data key of an envelope-encrypted file with whichever
+    /// master key it names
+    fn unwrap_data_key(
+        &self,
+        master_keys: &HashMap<String, EncryptionKey>,
+    ) -> Result<EncryptionKey> {
+        let block = self
+            .key_block
+            .as_ref()
+            .ok_or_else(|| anyhow!("File is not envelope-encrypted"))?;
+        let master_key = master_keys.get(&block.master_key_id).ok_or_else(|| {
+            anyhow!(
+                "Master key '{}' for this file is not in your master key store",
+                block.master_key_id
+            )
+        })?;
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&master_key.key));
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&block.nonce),
+                Payload {
+                    msg: &block.wrapped_key,
+                    aad: &self.raw,
+                },
+            )
+            .map_err(|_| anyhow!("Failed to unwrap data key. Corrupted or tampered header?"))?;
+        if plaintext.len() != KEY_SIZE {
+            return Err(anyhow!("Invalid data key length"));
+        }
+
+        let mut key = [0u8; KEY_SIZE];
+        key.copy_from_slice(&plaintext);
+        Ok(EncryptionKey { key })
+    }
+
+    fn decrypt_metadata(&self, cipher: &Aes256Gcm) -> Result<Option<FileMetadata>> {
+        let Some(ref block) = self.metadata_block else {
+            return Ok(None);
+        };
+
+        let nonce = metadata_nonce(&self.header.nonce);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: block,
+                    aad: &self.raw,
+                },
+            )
+            .map_err(|_| {
+                anyhow!("Failed to decrypt file metadata. Wrong password or tampered header?")
+            })?;
+
+        Ok(Some(serde_json::from_slice(&plaintext)?))
+    }
+}
+
+/// Data key wrapped by a master key, stored after the header of envelope files.
+/// The header bytes are authenticated as associated data.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WrappedKeyBlock {
+    master_key_id: String,
+    nonce: [u8; NONCE_SIZE],
+    wrapped_key: Vec<u8>,
+}
+
+impl WrappedKeyBlock {
+    fn wrap(
+        master_key_id: &str,
+        master_key: &EncryptionKey,
+        data_key: &EncryptionKey,
+        header_bytes: &[u8],
+    ) -> Result<Self> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&master_key.key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped_key = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &data_key.key,
+                    aad: header_bytes,
+                },
+            )
+            .map_err(|e| anyhow!("Failed to wrap data key: {}", e))?;
+
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        nonce_bytes.copy_from_slice(&nonce);
+        Ok(Self {
+            master_key_id: master_key_id.to_string(),
+            nonce: nonce_bytes,
+            wrapped_key,
+        })
+    }
+}
+
+/// Derives a per-file subkey from a batch password key with HKDF-SHA256
+fn derive_subkey(key: &EncryptionKey, subkey_salt: &[u8]) -> Result<EncryptionKey> {
+    let mut subkey = EncryptionKey { key: [0u8; KEY_SIZE] };
+    Hkdf::<Sha256>::new(Some(subkey_salt), &key.key)
+        .expand(SUBKEY_INFO, &mut subkey.key)
+        .map_err(|e| anyhow!("Subkey derivation failed: {}", e))?;
+    Ok(subkey)
+}
+
+/// Password key derived once and shared by a batch of files (e.g. a directory
+/// upload). Each file is encrypted under its own HKDF subkey with a random
+/// per-file salt, and still decrypts standalone with the password.
+#[derive(Clone)]
+pub struct BatchPasswordKey {
+    salt: [u8; SALT_SIZE],
+    key: EncryptionKey,
+}
+
+impl BatchPasswordKey {
+    /// Runs the Argon2id derivation for the batch
+    pub fn derive(password: &str) -> Result<Self> {
+        let salt = generate_salt();
+        let key = derive_key_from_password(password, &salt)?;
+        Ok(Self { salt, key })
+    }
+}
+
+/// How the content key is recorded in the header
+enum HeaderKey<'a> {
+    /// Raw key; nothing is stored
+    Raw,
+    /// Argon2id salt of the password key
+    Password { salt: [u8; SALT_SIZE] },
+    /// Argon2id salt of a batch password key and the per-file HKDF salt
+    Subkey {
+        salt: [u8; SALT_SIZE],
+        subkey_salt: [u8; SALT_SIZE],
+    },
+    /// Data key wrapped by a master key
+    Envelope {
+        master_key_id: &'a str,
+        master_key: &'a EncryptionKey,
+        data_key: &'a EncryptionKey,
+    },
+}
+
+/// Nonce for the metadata block; chunk counters never reach this value
+fn metadata_nonce(base_nonce: &[u8; NONCE_SIZE]) -> [u8; NONCE_SIZE] {
+    let mut nonce = *base_nonce;
+    nonce[..8].copy_from_slice(&METADATA_NONCE_COUNTER.to_le_bytes());
+    nonce
+}
+
+/// Writer adapter that hashes and counts everything written through it
+struct HashingWriter<W> {
+    inner: W,
+    hasher: blake3::Hasher,
+    size: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader adapter that reports consumed bytes to a progress callback
+struct ProgressReader<R> {
+    inner: R,
+    callback: Option<Box<dyn Fn(usize) + Send>>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(ref callback) = self.callback {
+            callback(n);
+        }
+        Ok(n)
+    }
+}
+
+/// Fills the buffer as far as possible, returning fewer bytes only at EOF
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
 /// Derives an encryption key from a password using Argon2id
 pub fn derive_key_from_password(password: &str, salt: &[u8]) -> Result<EncryptionKey> {
     let argon2 = Argon2::default();
@@ -188,31 +670,217 @@ pub fn decrypt_data(
 }
 
 /// Encrypts a file with password-based encryption
+#[allow(dead_code)]
 pub async fn encrypt_file_with_password<R: Read, W: Write>(
-    mut reader: R,
-    mut writer: W,
+    reader: R,
+    writer: W,
+    password: &str,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<()> {
+    encrypt_file_with_password_and_options(reader, writer, password, None, None, progress_callback)
+        .await
+}
+
+/// Encrypts a file with password-based encryption, optionally compressing it first
+/// and embedding the original file metadata. The progress callback reports
+/// uncompressed input bytes.
+pub async fn encrypt_file_with_password_and_options<R: Read, W: Write>(
+    reader: R,
+    writer: W,
     password: &str,
+    compression: Option<CompressionSpec>,
+    metadata: Option<&FileMetadata>,
     progress_callback: Option<Box<dyn Fn(usize) + Send>>,
 ) -> Result<()> {
     // Generate salt and derive key
     let salt = generate_salt();
     let key = derive_key_from_password(password, &salt)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+
+    write_encrypted_stream(
+        reader,
+        writer,
+        &cipher,
+        HeaderKey::Password { salt },
+        compression,
+        metadata,
+        progress_callback,
+    )
+}
+
+/// Encrypts a file under a per-file subkey of a batch password key, skipping
+/// the Argon2id derivation that dominates the cost of small files
+pub async fn encrypt_file_with_batch_key<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    batch_key: &BatchPasswordKey,
+    compression: Option<CompressionSpec>,
+    metadata: Option<&FileMetadata>,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<()> {
+    let subkey_salt = generate_salt();
+    let key = derive_subkey(&batch_key.key, &subkey_salt)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+
+    write_encrypted_stream(
+        reader,
+        writer,
+        &cipher,
+        HeaderKey::Subkey {
+            salt: batch_key.salt,
+            subkey_salt,
+        },
+        compression,
+        metadata,
+        progress_callback,
+    )
+}
+
+/// Encrypts a file with a raw 256-bit key. The header carries no salt, which is
+/// how decryption tells these files apart from password-protected ones.
+#[allow(dead_code)]
+pub async fn encrypt_file_with_key<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    key: &EncryptionKey,
+    compression: Option<CompressionSpec>,
+    metadata: Option<&FileMetadata>,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<()> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+    write_encrypted_stream(
+        reader,
+        writer,
+        &cipher,
+        HeaderKey::Raw,
+        compression,
+        metadata,
+        progress_callback,
+    )
+}
+
+/// Encrypts a file with a random data key wrapped by a master key. Rotating or
+/// re-protecting the master key only needs the wrapped key block rewritten.
+pub async fn encrypt_file_with_envelope<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    master_key_id: &str,
+    master_key: &EncryptionKey,
+    compression: Option<CompressionSpec>,
+    metadata: Option<&FileMetadata>,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<()> {
+    let mut data_key = EncryptionKey { key: [0u8; KEY_SIZE] };
+    OsRng.fill_bytes(&mut data_key.key);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key.key));
+
+    write_encrypted_stream(
+        reader,
+        writer,
+        &cipher,
+        HeaderKey::Envelope {
+            master_key_id,
+            master_key,
+            data_key: &data_key,
+        },
+        compression,
+        metadata,
+        progress_callback,
+    )
+}
 
+/// Writes the header, optional key and metadata blocks, and encrypted chunks
+fn write_encrypted_stream<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    cipher: &Aes256Gcm,
+    header_key: HeaderKey,
+    compression: Option<CompressionSpec>,
+    metadata: Option<&FileMetadata>,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<()> {
     // Generate nonce
     let mut nonce = [0u8; NONCE_SIZE];
     OsRng.fill_bytes(&mut nonce);
 
     // Write header
-    let header = EncryptedFileHeader::new(Some(salt), nonce);
-    writer.write_all(&header.to_bytes())?;
+    let mut flags = 0;
+    if compression.is_some() {
+        flags |= FLAG_ZSTD;
+    }
+    if metadata.is_some() {
+        flags |= FLAG_METADATA;
+    }
+    let (salt, subkey_salt) = match header_key {
+        HeaderKey::Raw => (None, None),
+        HeaderKey::Password { salt } => (Some(salt), None),
+        HeaderKey::Subkey { salt, subkey_salt } => (Some(salt), Some(subkey_salt)),
+        HeaderKey::Envelope { .. } => {
+            flags |= FLAG_ENVELOPE;
+            (None, None)
+        }
+    };
+    let header = EncryptedFileHeader::new(salt, nonce, flags, subkey_salt);
+    let header_bytes = header.to_bytes();
+    writer.write_all(&header_bytes)?;
+
+    // Wrapped data key block, bound to the header bytes
+    if let HeaderKey::Envelope {
+        master_key_id,
+        master_key,
+        data_key,
+    } = header_key
+    {
+        let key_block = WrappedKeyBlock::wrap(master_key_id, master_key, data_key, &header_bytes)?;
+        let block = serde_json::to_vec(&key_block)?;
+        writer.write_all(&(block.len() as u32).to_le_bytes())?;
+        writer.write_all(&block)?;
+    }
 
-    // Encrypt file in chunks
-    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+    // Metadata block, authenticated together with the header bytes
+    if let Some(metadata) = metadata {
+        let block = cipher
+            .encrypt(
+                Nonce::from_slice(&metadata_nonce(&nonce)),
+                Payload {
+                    msg: &serde_json::to_vec(metadata)?,
+                    aad: &header_bytes,
+                },
+            )
+            .map_err(|e| anyhow!("Metadata encryption failed: {}", e))?;
+        writer.write_all(&(block.len() as u32).to_le_bytes())?;
+        writer.write_all(&block)?;
+    }
+
+    let reader = ProgressReader {
+        inner: reader,
+        callback: progress_callback,
+    };
+
+    match compression {
+        Some(spec) if !spec.precompressed => {
+            let mut encoder = zstd::stream::read::Encoder::new(reader, spec.level)?;
+            encrypt_chunks(&mut encoder, &mut writer, cipher, nonce)
+        }
+        _ => {
+            let mut reader = reader;
+            encrypt_chunks(&mut reader, &mut writer, cipher, nonce)
+        }
+    }
+}
+
+/// Encrypts a stream in fixed-size chunks, each with a counter-derived nonce
+fn encrypt_chunks<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    cipher: &Aes256Gcm,
+    nonce: [u8; NONCE_SIZE],
+) -> Result<()> {
     let mut buffer = vec![0u8; CHUNK_SIZE];
-    let mut total_encrypted = 0;
+    let mut chunk_counter = 0u64;
 
     loop {
-        let bytes_read = reader.read(&mut buffer)?;
+        let bytes_read = read_full(reader, &mut buffer)?;
         if bytes_read == 0 {
             break;
         }
@@ -220,8 +888,7 @@ pub async fn encrypt_file_with_password<R: Read, W: Write>(
         // For each chunk, we need a unique nonce
         let mut chunk_nonce = nonce;
         // Add chunk counter to nonce to ensure uniqueness
-        let counter = (total_encrypted / CHUNK_SIZE) as u64;
-        chunk_nonce[..8].copy_from_slice(&counter.to_le_bytes());
+        chunk_nonce[..8].copy_from_slice(&chunk_counter.to_le_bytes());
 
         let chunk_data = &buffer[..bytes_read];
         let nonce_obj = Nonce::from_slice(&chunk_nonce);
@@ -234,35 +901,238 @@ pub async fn encrypt_file_with_password<R: Read, W: Write>(
         writer.write_all(&(ciphertext.len() as u32).to_le_bytes())?;
         writer.write_all(&ciphertext)?;
 
-        total_encrypted += bytes_read;
-
-        if let Some(ref callback) = progress_callback {
-            callback(bytes_read);
-        }
+        chunk_counter += 1;
     }
 
     Ok(())
 }
 
-/// Decrypts a file with password-based encryption
+/// Decrypts a file with password-based encryption.
+/// Files encrypted with compression are decompressed transparently. When the
+/// header carries file metadata, the plaintext size and Blake3 hash are verified
+/// and the metadata is returned so callers can restore the name and timestamps.
 pub async fn decrypt_file_with_password<R: Read, W: Write>(
     mut reader: R,
-    mut writer: W,
+    writer: W,
     password: &str,
     progress_callback: Option<Box<dyn Fn(usize) + Send>>,
-) -> Result<()> {
+) -> Result<Option<FileMetadata>> {
     // Read header
-    let mut header_bytes = vec![0u8; EncryptedFileHeader::size()];
-    reader.read_exact(&mut header_bytes)?;
-    let header = EncryptedFileHeader::from_bytes(&header_bytes)?;
+    let parsed = EncryptedFileHeader::read_from(&mut reader)?;
 
     // Derive key from password
-    let salt = header.salt.ok_or_else(|| anyhow!("No salt in header"))?;
-    let key = derive_key_from_password(password, &salt)?;
+    let key = parsed.password_key(password)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+
+    read_encrypted_stream(&parsed, reader, writer, &cipher, progress_callback)
+}
+
+/// Decrypts a file encrypted with a raw 256-bit key, with the same integrity
+/// checks as [`decrypt_file_with_password`]
+pub async fn decrypt_file_with_key<R: Read, W: Write>(
+    mut reader: R,
+    writer: W,
+    key: &EncryptionKey,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<Option<FileMetadata>> {
+    let parsed = EncryptedFileHeader::read_from(&mut reader)?;
+    if parsed.header.salt.is_some() {
+        return Err(anyhow!("File is password-protected, not encrypted with a key"));
+    }
+    if parsed.key_block.is_some() {
+        return Err(anyhow!("File is envelope-encrypted; use the master key store"));
+    }
 
-    // Decrypt file in chunks
     let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
-    let base_nonce = header.nonce;
+    read_encrypted_stream(&parsed, reader, writer, &cipher, progress_callback)
+}
+
+/// Decrypts an envelope-encrypted file, unwrapping its data key with the
+/// master key named in the header
+pub async fn decrypt_file_with_master_keys<R: Read, W: Write>(
+    mut reader: R,
+    writer: W,
+    master_keys: &HashMap<String, EncryptionKey>,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<Option<FileMetadata>> {
+    let parsed = EncryptedFileHeader::read_from(&mut reader)?;
+    let data_key = parsed.unwrap_data_key(master_keys)?;
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key.key));
+    read_encrypted_stream(&parsed, reader, writer, &cipher, progress_callback)
+}
+
+/// Rewraps the data key of an envelope-encrypted file under a new master key.
+/// Only the key block changes; the encrypted content is copied unchanged.
+/// Returns false, writing nothing, if the file already uses the new master key.
+pub fn rewrap_envelope<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    master_keys: &HashMap<String, EncryptionKey>,
+    new_master_key_id: &str,
+) -> Result<bool> {
+    let mut parsed = EncryptedFileHeader::read_from(&mut reader)?;
+    let current_id = parsed
+        .key_block
+        .as_ref()
+        .map(|b| b.master_key_id.clone())
+        .ok_or_else(|| anyhow!("File is not envelope-encrypted"))?;
+    if current_id == new_master_key_id {
+        return Ok(false);
+    }
+
+    let data_key = parsed.unwrap_data_key(master_keys)?;
+    let new_master_key = master_keys
+        .get(new_master_key_id)
+        .ok_or_else(|| anyhow!("Master key '{}' not found", new_master_key_id))?;
+    parsed.key_block = Some(WrappedKeyBlock::wrap(
+        new_master_key_id,
+        new_master_key,
+        &data_key,
+        &parsed.raw,
+    )?);
+
+    parsed.write_to(&mut writer)?;
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(true)
+}
+
+/// Rewraps the data key of an envelope-encrypted file under a new master key
+/// given only the start of the file (e.g. from a ranged download), so the file
+/// itself is not rewritten. Returns the new key block, to be stored next to the
+/// file and applied with `apply_envelope_key_block`, or None if the file
+/// already uses the new master key.
+pub fn rewrap_envelope_key_block(
+    head: &[u8],
+    master_keys: &HashMap<String, EncryptionKey>,
+    new_master_key_id: &str,
+) -> Result<Option<Vec<u8>>> {
+    let parsed = EncryptedFileHeader::read_through_key_block(&mut std::io::Cursor::new(head))?;
+    let current_id = parsed
+        .key_block
+        .as_ref()
+        .map(|b| b.master_key_id.as_str())
+        .ok_or_else(|| anyhow!("File is not envelope-encrypted"))?;
+    if current_id == new_master_key_id {
+        return Ok(None);
+    }
+
+    let data_key = parsed.unwrap_data_key(master_keys)?;
+    let new_master_key = master_keys
+        .get(new_master_key_id)
+        .ok_or_else(|| anyhow!("Master key '{}' not found", new_master_key_id))?;
+    let block = WrappedKeyBlock::wrap(new_master_key_id, new_master_key, &data_key, &parsed.raw)?;
+    Ok(Some(serde_json::to_vec(&block)?))
+}
+
+/// Replaces the key block of an envelope-encrypted file with one returned by
+/// `rewrap_envelope_key_block`. The block is bound to the header it was
+/// wrapped for, so a block from another file fails to unwrap on decryption.
+pub fn apply_envelope_key_block<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    key_block: &[u8],
+) -> Result<()> {
+    let mut parsed = EncryptedFileHeader::read_from(&mut reader)?;
+    if parsed.key_block.is_none() {
+        return Err(anyhow!("File is not envelope-encrypted"));
+    }
+    if key_block.is_empty() || key_block.len() > MAX_KEY_BLOCK_SIZE {
+        return Err(anyhow!("Invalid wrapped key block size"));
+    }
+    parsed.key_block = Some(
+        serde_json::from_slice(key_block).map_err(|e| anyhow!("Invalid wrapped key block: {}", e))?,
+    );
+
+    parsed.write_to(&mut writer)?;
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decrypts the metadata block and chunk stream that follow a parsed header
+fn read_encrypted_stream<R: Read, W: Write>(
+    parsed: &ParsedHeader,
+    mut reader: R,
+    writer: W,
+    cipher: &Aes256Gcm,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<Option<FileMetadata>> {
+    let header = &parsed.header;
+    let metadata = parsed.decrypt_metadata(cipher)?;
+
+    let mut output = HashingWriter {
+        inner: writer,
+        hasher: blake3::Hasher::new(),
+        size: 0,
+    };
+
+    if header.flags & FLAG_ZSTD != 0 {
+        let mut decoder = zstd::stream::write::Decoder::new(&mut output)?;
+        decrypt_chunks(&mut reader, &mut decoder, cipher, header.nonce, progress_callback)?;
+        decoder.flush()?;
+    } else {
+        decrypt_chunks(&mut reader, &mut output, cipher, header.nonce, progress_callback)?;
+    }
+    output.flush()?;
+
+    if let Some(ref metadata) = metadata {
+        if output.size != metadata.size {
+            return Err(anyhow!(
+                "Integrity check failed: expected {} bytes, got {}",
+                metadata.size,
+                output.size
+            ));
+        }
+        let hash = output.hasher.finalize().to_hex().to_string();
+        if hash != metadata.blake3 {
+            return Err(anyhow!(
+                "Integrity check failed: Blake3 mismatch (expected {}, got {})",
+                metadata.blake3,
+                hash
+            ));
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Reads the header fields that are visible without a key
+pub fn read_header_info<R: Read>(mut reader: R) -> Result<HeaderInfo> {
+    let parsed = EncryptedFileHeader::read_from(&mut reader)?;
+    Ok(HeaderInfo {
+        version: parsed.header.version,
+        flags: parsed.header.flags,
+        password_protected: parsed.header.salt.is_some(),
+        metadata_size: parsed.metadata_block.as_ref().map(|b| b.len()),
+        master_key_id: parsed.key_block.map(|b| b.master_key_id),
+    })
+}
+
+/// Decrypts only the metadata block, without touching the file contents
+pub fn read_metadata_with_password<R: Read>(
+    mut reader: R,
+    password: &str,
+) -> Result<Option<FileMetadata>> {
+    let parsed = EncryptedFileHeader::read_from(&mut reader)?;
+    if parsed.metadata_block.is_none() {
+        return Ok(None);
+    }
+
+    let key = parsed.password_key(password)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+    parsed.decrypt_metadata(&cipher)
+}
+
+/// Decrypts the chunk stream that follows the header
+fn decrypt_chunks<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    cipher: &Aes256Gcm,
+    base_nonce: [u8; NONCE_SIZE],
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<()> {
     let mut chunk_counter = 0u64;
 
     loop {
@@ -324,6 +1194,145 @@ pub fn is_encrypted_file<R: Read + Seek>(mut reader: R) -> Result<bool> {
     }
 }
 
+/// Encryption format of a file, detected from its leading bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionFormat {
+    /// PIPE-ENC protected by a password (Argon2id salt in the header)
+    Password,
+    /// PIPE-ENC encrypted with a raw key
+    Key,
+    /// PIPE-ENC with a data key wrapped by a master key
+    Envelope,
+    /// PIPE-PQ1 stream with a Kyber-encapsulated key
+    Kyber,
+    /// Sign-and-encrypt container written by quantum uploads (.qenc)
+    QuantumSigned,
+    /// PIPE-ZST, compressed but not encrypted
+    Compressed,
+    /// Not a pipe-cli container
+    Plain,
+}
+
+impl std::fmt::Display for EncryptionFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncryptionFormat::Password => write!(f, "PIPE-ENC (password)"),
+            EncryptionFormat::Key => write!(f, "PIPE-ENC (key)"),
+            EncryptionFormat::Envelope => write!(f, "PIPE-ENC (envelope)"),
+            EncryptionFormat::Kyber => write!(f, "PIPE-PQ1 (Kyber1024)"),
+            EncryptionFormat::QuantumSigned => write!(f, "quantum sign-and-encrypt (.qenc)"),
+            EncryptionFormat::Compressed => write!(f, "PIPE-ZST (compressed)"),
+            EncryptionFormat::Plain => write!(f, "not encrypted"),
+        }
+    }
+}
+
+/// Detects the encryption format of a file from its magic bytes. The reader is
+/// left at the start of the stream.
+pub fn detect_format<R: Read + Seek>(mut reader: R) -> Result<EncryptionFormat> {
+    if is_encrypted_file(&mut reader)? {
+        let mut prefix = [0u8; 8 + 1 + 1 + SALT_SIZE + NONCE_SIZE + 1];
+        let len = read_full(&mut reader, &mut prefix)?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let envelope = len == prefix.len()
+            && prefix[8] == VERSION_WITH_FLAGS
+            && prefix[prefix.len() - 1] & FLAG_ENVELOPE != 0;
+        return Ok(if prefix[9] == 1 {
+            EncryptionFormat::Password
+        } else if envelope {
+            EncryptionFormat::Envelope
+        } else {
+            EncryptionFormat::Key
+        });
+    }
+    if crate::quantum::is_kyber_encrypted_file(&mut reader)? {
+        return Ok(EncryptionFormat::Kyber);
+    }
+    if crate::compression::is_compressed_file(&mut reader)? {
+        return Ok(EncryptionFormat::Compressed);
+    }
+    Ok(EncryptionFormat::Plain)
+}
+
+/// Like `detect_format`, but also recognises sign-and-encrypt (.qenc)
+/// containers when `expect_quantum` is set. Those have no magic bytes, so the
+/// check is only made when the name or the user says the file is one;
+/// otherwise a plain file could be mistaken for a container.
+pub fn detect_format_expecting<R: Read + Seek>(mut reader: R, expect_quantum: bool) -> Result<EncryptionFormat> {
+    let format = detect_format(&mut reader)?;
+    if format == EncryptionFormat::Plain && expect_quantum && crate::quantum::is_quantum_container(&mut reader)? {
+        return Ok(EncryptionFormat::QuantumSigned);
+    }
+    Ok(format)
+}
+
+/// Encryption details that can be read from the first bytes of a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderSummary {
+    pub format: EncryptionFormat,
+    /// How the content key is derived, when the format says so
+    pub kdf: Option<&'static str>,
+    /// Who can decrypt the file, as far as the header tells
+    pub recipients: Vec<String>,
+    pub compressed: bool,
+    /// Whether an encrypted name/size/hash block is present
+    pub has_metadata: bool,
+}
+
+/// Summarizes the header at the start of `head`, which may be a truncated
+/// prefix of the file (e.g. from a ranged download). Fields that lie beyond
+/// the prefix are left unset. `expect_quantum` is passed on to
+/// `detect_format_expecting`.
+pub fn summarize_header(head: &[u8], expect_quantum: bool) -> Result<HeaderSummary> {
+    let format = detect_format_expecting(std::io::Cursor::new(head), expect_quantum)?;
+    let mut summary = HeaderSummary {
+        format,
+        kdf: None,
+        recipients: Vec::new(),
+        compressed: false,
+        has_metadata: false,
+    };
+
+    // Kyber streams wrap a key-encrypted PIPE-ENC stream after the ciphertext
+    let inner = match format {
+        EncryptionFormat::Password | EncryptionFormat::Key | EncryptionFormat::Envelope => Some(head),
+        EncryptionFormat::Kyber => {
+            summary.kdf = Some("Kyber1024 shared secret + SHA3-256");
+            summary.recipients.push("one Kyber1024 key (not named in the header)".to_string());
+            head.get(8..12)
+                .map(|len| 12 + u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize)
+                .and_then(|start| head.get(start..))
+        }
+        EncryptionFormat::QuantumSigned => {
+            summary.kdf = Some("Kyber1024 shared secret + SHA3-256");
+            summary.recipients.push("one Kyber1024 key (not named in the header)".to_string());
+            None
+        }
+        EncryptionFormat::Compressed => {
+            summary.compressed = true;
+            None
+        }
+        EncryptionFormat::Plain => None,
+    };
+
+    if let Some(info) = inner.and_then(|bytes| read_header_info(bytes).ok()) {
+        summary.compressed = info.is_compressed();
+        summary.has_metadata = info.metadata_size.is_some();
+        if info.password_protected {
+            summary.kdf = Some(if info.flags & FLAG_SUBKEY != 0 {
+                "Argon2id + HKDF-SHA256 per-file subkey"
+            } else {
+                "Argon2id"
+            });
+        }
+        if let Some(id) = info.master_key_id {
+            summary.recipients.push(format!("master key '{}'", id));
+        }
+    }
+    Ok(summary)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -351,6 +1360,397 @@ mod tests {
         assert_eq!(data.as_ref(), decrypted.as_slice());
     }
 
+    #[tokio::test]
+    async fn test_encrypt_decrypt_compressed() {
+        let data = b"2024-01-01,INFO,request served\n".repeat(4000);
+        let password = "test_password_123";
+        let spec = CompressionSpec::parse("zstd:9").unwrap();
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_password_and_options(
+            Cursor::new(&data),
+            &mut encrypted,
+            password,
+            Some(spec),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Compressed payload should be far smaller than the input
+        assert!(encrypted.len() < data.len() / 10);
+        assert_eq!(encrypted[8], VERSION_WITH_FLAGS);
+
+        let mut decrypted = Vec::new();
+        decrypt_file_with_password(Cursor::new(encrypted), &mut decrypted, password, None)
+            .await
+            .unwrap();
+
+        assert_eq!(data, decrypted);
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_precompressed_frame() {
+        let data = b"2024-01-01,INFO,request served\n".repeat(4000);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("access.log");
+        std::fs::write(&source, &data).unwrap();
+
+        let compressed =
+            crate::compression::CompressedFile::new(&source, CompressionSpec::parse("zstd").unwrap())
+                .unwrap();
+        let (frame, spec) = compressed.frame().unwrap();
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_password_and_options(frame, &mut encrypted, "pw", Some(spec), None, None)
+            .await
+            .unwrap();
+        assert!(encrypted.len() < data.len() / 10);
+
+        let mut decrypted = Vec::new();
+        decrypt_file_with_password(Cursor::new(encrypted), &mut decrypted, "pw", None)
+            .await
+            .unwrap();
+
+        assert_eq!(data, decrypted);
+    }
+
+    #[tokio::test]
+    async fn test_uncompressed_keeps_v1_header() {
+        let data = vec![7u8; CHUNK_SIZE * 2 + 100];
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_password(Cursor::new(&data), &mut encrypted, "pw", None)
+            .await
+            .unwrap();
+        assert_eq!(encrypted[8], VERSION);
+
+        let mut decrypted = Vec::new();
+        decrypt_file_with_password(Cursor::new(encrypted), &mut decrypted, "pw", None)
+            .await
+            .unwrap();
+        assert_eq!(data, decrypted);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_roundtrip_and_inspect() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("report.csv");
+        std::fs::write(&source, b"a,b,c\n1,2,3\n").unwrap();
+        let metadata = FileMetadata::from_path(&source).unwrap();
+        assert_eq!(metadata.name, "report.csv");
+        assert_eq!(metadata.size, 12);
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_password_and_options(
+            std::fs::File::open(&source).unwrap(),
+            &mut encrypted,
+            "pw",
+            None,
+            Some(&metadata),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Header is readable without a key, metadata is not
+        let info = read_header_info(Cursor::new(&encrypted)).unwrap();
+        assert_eq!(info.version, VERSION_WITH_FLAGS);
+        assert!(info.metadata_size.is_some());
+        assert!(!info.is_compressed());
+        assert!(read_metadata_with_password(Cursor::new(&encrypted), "wrong").is_err());
+        assert_eq!(
+            read_metadata_with_password(Cursor::new(&encrypted), "pw").unwrap(),
+            Some(metadata.clone())
+        );
+
+        let mut decrypted = Vec::new();
+        let restored = decrypt_file_with_password(Cursor::new(&encrypted), &mut decrypted, "pw", None)
+            .await
+            .unwrap();
+        assert_eq!(restored, Some(metadata));
+        assert_eq!(decrypted, b"a,b,c\n1,2,3\n");
+    }
+
+    #[tokio::test]
+    async fn test_tampered_header_rejected() {
+        let metadata = FileMetadata {
+            name: "x.bin".to_string(),
+            size: 3,
+            modified: None,
+            mode: None,
+            blake3: blake3::hash(b"abc").to_hex().to_string(),
+        };
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_password_and_options(
+            Cursor::new(b"abc"),
+            &mut encrypted,
+            "pw",
+            None,
+            Some(&metadata),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Setting the compression flag must break metadata authentication
+        let flags_offset = EncryptedFileHeader::size();
+        encrypted[flags_offset] |= FLAG_ZSTD;
+        let mut out = Vec::new();
+        assert!(decrypt_file_with_password(Cursor::new(&encrypted), &mut out, "pw", None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_file_fails_hash_check() {
+        let data = vec![42u8; CHUNK_SIZE * 3];
+        let metadata = FileMetadata {
+            name: "big.bin".to_string(),
+            size: data.len() as u64,
+            modified: None,
+            mode: None,
+            blake3: blake3::hash(&data).to_hex().to_string(),
+        };
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_password_and_options(
+            Cursor::new(&data),
+            &mut encrypted,
+            "pw",
+            None,
+            Some(&metadata),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Drop the last whole chunk: every remaining chunk still authenticates
+        encrypted.truncate(encrypted.len() - (4 + CHUNK_SIZE + TAG_SIZE));
+        let mut out = Vec::new();
+        let err = decrypt_file_with_password(Cursor::new(&encrypted), &mut out, "pw", None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Integrity check failed"));
+    }
+
+    #[test]
+    fn test_safe_name_strips_directories() {
+        let mut metadata = FileMetadata {
+            name: "../../etc/passwd".to_string(),
+            size: 0,
+            modified: None,
+            mode: None,
+            blake3: String::new(),
+        };
+        assert_eq!(metadata.safe_name().as_deref(), Some("passwd"));
+        metadata.name = "..".to_string();
+        assert_eq!(metadata.safe_name(), None);
+    }
+
+    #[tokio::test]
+    async fn test_key_encryption_and_format_detection() {
+        let data = b"encrypted with a raw key".repeat(100);
+        let key = EncryptionKey { key: [7u8; KEY_SIZE] };
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_key(Cursor::new(&data), &mut encrypted, &key, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            detect_format(Cursor::new(&encrypted)).unwrap(),
+            EncryptionFormat::Key
+        );
+
+        let mut decrypted = Vec::new();
+        decrypt_file_with_key(Cursor::new(&encrypted), &mut decrypted, &key, None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted, data);
+
+        // A key-encrypted file is not mistaken for a password-protected one
+        let mut out = Vec::new();
+        assert!(decrypt_file_with_password(Cursor::new(&encrypted), &mut out, "pw", None)
+            .await
+            .is_err());
+
+        let mut password_encrypted = Vec::new();
+        encrypt_file_with_password(Cursor::new(&data), &mut password_encrypted, "pw", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            detect_format(Cursor::new(&password_encrypted)).unwrap(),
+            EncryptionFormat::Password
+        );
+        assert_eq!(
+            detect_format(Cursor::new(&data)).unwrap(),
+            EncryptionFormat::Plain
+        );
+        assert_eq!(
+            detect_format(Cursor::new(b"short")).unwrap(),
+            EncryptionFormat::Plain
+        );
+    }
+
+    #[tokio::test]
+    async fn test_envelope_rewrap_keeps_content() {
+        let data = b"wrapped by a master key".repeat(4000);
+        let mut master_keys = HashMap::new();
+        master_keys.insert("old".to_string(), EncryptionKey { key: [1u8; KEY_SIZE] });
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_envelope(
+            Cursor::new(&data),
+            &mut encrypted,
+            "old",
+            &master_keys["old"],
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            detect_format(Cursor::new(&encrypted)).unwrap(),
+            EncryptionFormat::Envelope
+        );
+        assert_eq!(
+            read_header_info(Cursor::new(&encrypted)).unwrap().master_key_id.as_deref(),
+            Some("old")
+        );
+
+        let summary = summarize_header(&encrypted[..4096], false).unwrap();
+        assert_eq!(summary.recipients, ["master key 'old'"]);
+        assert_eq!(summary.kdf, None);
+        assert!(!summary.compressed);
+
+        // Rewrapping swaps only the key block; the chunks are byte-identical
+        master_keys.insert("new".to_string(), EncryptionKey { key: [2u8; KEY_SIZE] });
+        let mut rewrapped = Vec::new();
+        assert!(rewrap_envelope(Cursor::new(&encrypted), &mut rewrapped, &master_keys, "new").unwrap());
+        let chunks_len = encrypted.len() - 1000;
+        assert_eq!(
+            encrypted[encrypted.len() - chunks_len..],
+            rewrapped[rewrapped.len() - chunks_len..]
+        );
+        assert!(!rewrap_envelope(Cursor::new(&rewrapped), &mut Vec::new(), &master_keys, "new").unwrap());
+
+        // Only the new master key is needed afterwards
+        master_keys.remove("old");
+        let mut decrypted = Vec::new();
+        decrypt_file_with_master_keys(Cursor::new(&rewrapped), &mut decrypted, &master_keys, None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted, data);
+
+        // The original file now names a master key that is no longer available
+        let err = decrypt_file_with_master_keys(Cursor::new(&encrypted), &mut Vec::new(), &master_keys, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Master key 'old'"));
+    }
+
+    #[tokio::test]
+    async fn test_envelope_key_block_rewrapped_from_head() {
+        let data = b"rewrapped without rewriting the file".repeat(4000);
+        let mut master_keys = HashMap::new();
+        master_keys.insert("old".to_string(), EncryptionKey { key: [1u8; KEY_SIZE] });
+        master_keys.insert("new".to_string(), EncryptionKey { key: [2u8; KEY_SIZE] });
+
+        let mut encrypted = Vec::new();
+        let mut other = Vec::new();
+        for output in [&mut encrypted, &mut other] {
+            encrypt_file_with_envelope(Cursor::new(&data), output, "old", &master_keys["old"], None, None, None)
+                .await
+                .unwrap();
+        }
+
+        // Only the start of the file is needed to rewrap its data key
+        let key_block = rewrap_envelope_key_block(&encrypted[..8192], &master_keys, "new")
+            .unwrap()
+            .unwrap();
+        assert!(rewrap_envelope_key_block(&encrypted[..8192], &master_keys, "old")
+            .unwrap()
+            .is_none());
+
+        master_keys.remove("old");
+        let mut rewrapped = Vec::new();
+        apply_envelope_key_block(Cursor::new(&encrypted), &mut rewrapped, &key_block).unwrap();
+        let mut decrypted = Vec::new();
+        decrypt_file_with_master_keys(Cursor::new(&rewrapped), &mut decrypted, &master_keys, None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted, data);
+
+        // The block is bound to the header of the file it was rewrapped for
+        let mut misapplied = Vec::new();
+        apply_envelope_key_block(Cursor::new(&other), &mut misapplied, &key_block).unwrap();
+        assert!(
+            decrypt_file_with_master_keys(Cursor::new(&misapplied), &mut Vec::new(), &master_keys, None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_key_files_decrypt_standalone() {
+        let batch_key = BatchPasswordKey::derive("batch password").unwrap();
+        let metadata = FileMetadata {
+            name: "a.txt".to_string(),
+            size: 5,
+            modified: None,
+            mode: None,
+            blake3: blake3::hash(b"first").to_hex().to_string(),
+        };
+
+        let mut first = Vec::new();
+        encrypt_file_with_batch_key(Cursor::new(b"first"), &mut first, &batch_key, None, Some(&metadata), None)
+            .await
+            .unwrap();
+        let mut second = Vec::new();
+        encrypt_file_with_batch_key(Cursor::new(b"second"), &mut second, &batch_key, None, None, None)
+            .await
+            .unwrap();
+
+        // Same Argon2 salt, different per-file subkey salts
+        let first_header = EncryptedFileHeader::read_from(&mut Cursor::new(&first)).unwrap().header;
+        let second_header = EncryptedFileHeader::read_from(&mut Cursor::new(&second)).unwrap().header;
+        assert_eq!(first_header.salt, second_header.salt);
+        assert_ne!(first_header.subkey_salt, second_header.subkey_salt);
+        assert_eq!(detect_format(Cursor::new(&first)).unwrap(), EncryptionFormat::Password);
+
+        let summary = summarize_header(&first, false).unwrap();
+        assert_eq!(summary.kdf, Some("Argon2id + HKDF-SHA256 per-file subkey"));
+        assert!(summary.has_metadata);
+
+        // Each file decrypts on its own with just the password
+        let mut decrypted = Vec::new();
+        let restored = decrypt_file_with_password(Cursor::new(&first), &mut decrypted, "batch password", None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted, b"first");
+        assert_eq!(restored.unwrap().name, "a.txt");
+        assert_eq!(
+            read_metadata_with_password(Cursor::new(&first), "batch password").unwrap().unwrap().name,
+            "a.txt"
+        );
+
+        let mut decrypted = Vec::new();
+        decrypt_file_with_password(Cursor::new(&second), &mut decrypted, "batch password", None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted, b"second");
+
+        // The subkey salt is authenticated as part of the header
+        let subkey_salt_offset = EncryptedFileHeader::size() + 1;
+        second[subkey_salt_offset] ^= 0xff;
+        assert!(decrypt_file_with_password(Cursor::new(&second), &mut Vec::new(), "batch password", None)
+            .await
+            .is_err());
+    }
+
     #[test]
     fn test_key_derivation() {
         let password = "test_password";
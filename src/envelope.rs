@@ -0,0 +1,275 @@
+use aes_gcm::aead::OsRng;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::encryption::{
+    decrypt_data, derive_key_from_password, encrypt_data, generate_salt, EncryptionKey,
+};
+
+/// How the master key store is protected
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Protection {
+    /// Key derived from a password with Argon2id
+    Password { salt: [u8; 32] },
+    /// AES-256 key held in the keyring
+    Keyring { key_name: String },
+}
+
+impl std::fmt::Display for Protection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protection::Password { .. } => write!(f, "password"),
+            Protection::Keyring { key_name } => write!(f, "keyring key '{}'", key_name),
+        }
+    }
+}
+
+/// A master key encrypted under the store's protection key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WrappedMasterKey {
+    id: String,
+    created_at: DateTime<Utc>,
+    nonce: [u8; 12],
+    encrypted_key: Vec<u8>,
+}
+
+/// Local store of the master keys that wrap per-file data keys.
+/// Changing the password only re-encrypts this file; encrypted uploads are untouched.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MasterKeyStore {
+    version: u8,
+    protection: Protection,
+    /// Master key used for new uploads
+    active: String,
+    /// All master keys, including rotated ones still needed by older files
+    keys: Vec<WrappedMasterKey>,
+}
+
+/// Decrypted master keys, indexed by ID
+pub struct UnlockedMasterKeys {
+    active: String,
+    keys: HashMap<String, EncryptionKey>,
+}
+
+impl UnlockedMasterKeys {
+    /// ID and key of the master key used for new uploads
+    pub fn active(&self) -> (&str, &EncryptionKey) {
+        (&self.active, &self.keys[&self.active])
+    }
+
+    /// All master keys, for unwrapping data keys of older files
+    pub fn keys(&self) -> &HashMap<String, EncryptionKey> {
+        &self.keys
+    }
+}
+
+impl MasterKeyStore {
+    const CURRENT_VERSION: u8 = 1;
+
+    /// Get the default master key store path (next to the keyring)
+    pub fn default_path() -> Result<PathBuf> {
+        let mut path =
+            dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+        path.push("pipe-cli");
+        path.push("master-keys.json");
+        Ok(path)
+    }
+
+    /// Create a store with a fresh master key, protected by `kek`
+    pub fn create(kek: &EncryptionKey, protection: Protection) -> Result<(Self, UnlockedMasterKeys)> {
+        let mut store = Self {
+            version: Self::CURRENT_VERSION,
+            protection,
+            active: String::new(),
+            keys: Vec::new(),
+        };
+        let mut unlocked = UnlockedMasterKeys {
+            active: String::new(),
+            keys: HashMap::new(),
+        };
+        store.rotate(kek, &mut unlocked)?;
+        Ok((store, unlocked))
+    }
+
+    /// Load the store from file
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let store: Self = serde_json::from_str(&contents)?;
+
+        if store.version > Self::CURRENT_VERSION {
+            return Err(anyhow!(
+                "Master key store version {} is newer than supported version {}",
+                store.version,
+                Self::CURRENT_VERSION
+            ));
+        }
+
+        Ok(store)
+    }
+
+    /// Save the store, replacing the previous file atomically
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+
+        // Set restrictive permissions on Unix
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = fs::metadata(&temp_path)?.permissions();
+            perms.set_mode(0o600); // Read/write for owner only
+            fs::set_permissions(&temp_path, perms)?;
+        }
+
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// How the store is protected
+    pub fn protection(&self) -> &Protection {
+        &self.protection
+    }
+
+    /// Number of master keys, including rotated ones
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Decrypt all master keys with the protection key
+    pub fn unlock(&self, kek: &EncryptionKey) -> Result<UnlockedMasterKeys> {
+        let mut keys = HashMap::new();
+        for wrapped in &self.keys {
+            let plaintext = decrypt_data(&wrapped.encrypted_key, kek, &wrapped.nonce)
+                .map_err(|_| anyhow!("Failed to unlock master keys. Wrong password?"))?;
+            if plaintext.len() != 32 {
+                return Err(anyhow!("Master key '{}' is corrupted", wrapped.id));
+            }
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&plaintext);
+            keys.insert(wrapped.id.clone(), EncryptionKey { key });
+        }
+
+        if !keys.contains_key(&self.active) {
+            return Err(anyhow!("Active master key '{}' is missing", self.active));
+        }
+
+        Ok(UnlockedMasterKeys {
+            active: self.active.clone(),
+            keys,
+        })
+    }
+
+    /// Re-encrypt every master key under a new protection key
+    pub fn reprotect(
+        &mut self,
+        unlocked: &UnlockedMasterKeys,
+        kek: &EncryptionKey,
+        protection: Protection,
+    ) -> Result<()> {
+        for wrapped in &mut self.keys {
+            let key = unlocked
+                .keys
+                .get(&wrapped.id)
+                .ok_or_else(|| anyhow!("Master key '{}' is not unlocked", wrapped.id))?;
+            let (encrypted_key, nonce) = encrypt_data(&key.key, kek)?;
+            wrapped.encrypted_key = encrypted_key;
+            wrapped.nonce = nonce;
+        }
+        self.protection = protection;
+        Ok(())
+    }
+
+    /// Generate a new active master key. Older keys are kept so files that
+    /// have not been rewrapped yet still decrypt.
+    pub fn rotate(&mut self, kek: &EncryptionKey, unlocked: &mut UnlockedMasterKeys) -> Result<String> {
+        let mut key = EncryptionKey { key: [0u8; 32] };
+        OsRng.fill_bytes(&mut key.key);
+
+        let id = Uuid::new_v4().simple().to_string()[..16].to_string();
+        let (encrypted_key, nonce) = encrypt_data(&key.key, kek)?;
+        self.keys.push(WrappedMasterKey {
+            id: id.clone(),
+            created_at: Utc::now(),
+            nonce,
+            encrypted_key,
+        });
+        self.active = id.clone();
+
+        unlocked.keys.insert(id.clone(), key);
+        unlocked.active = id.clone();
+        Ok(id)
+    }
+}
+
+/// Protection key for a password, with a fresh salt
+pub fn new_password_protection(password: &str) -> Result<(EncryptionKey, Protection)> {
+    let salt = generate_salt();
+    let kek = derive_key_from_password(password, &salt)?;
+    Ok((kek, Protection::Password { salt }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_create_unlock_and_reprotect() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("master-keys.json");
+
+        let (kek, protection) = new_password_protection("old password").unwrap();
+        let (store, unlocked) = MasterKeyStore::create(&kek, protection).unwrap();
+        let (active_id, active_key) = unlocked.active();
+        let original_key = active_key.key;
+        let active_id = active_id.to_string();
+        store.save_to_file(&path).unwrap();
+
+        // Changing the password keeps the master key itself
+        let mut store = MasterKeyStore::load_from_file(&path).unwrap();
+        let Protection::Password { salt } = store.protection().clone() else {
+            panic!("expected password protection");
+        };
+        assert!(store.unlock(&derive_key_from_password("wrong", &salt).unwrap()).is_err());
+        let unlocked = store.unlock(&derive_key_from_password("old password", &salt).unwrap()).unwrap();
+
+        let (new_kek, new_protection) = new_password_protection("new password").unwrap();
+        store.reprotect(&unlocked, &new_kek, new_protection).unwrap();
+        assert!(store.unlock(&kek).is_err());
+        let unlocked = store.unlock(&new_kek).unwrap();
+        assert_eq!(unlocked.active().0, active_id);
+        assert_eq!(unlocked.active().1.key, original_key);
+    }
+
+    #[test]
+    fn test_rotate_keeps_previous_keys() {
+        let kek = EncryptionKey { key: [3u8; 32] };
+        let (mut store, mut unlocked) = MasterKeyStore::create(
+            &kek,
+            Protection::Keyring {
+                key_name: "backup".to_string(),
+            },
+        )
+        .unwrap();
+        let first = unlocked.active().0.to_string();
+
+        let second = store.rotate(&kek, &mut unlocked).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(store.key_count(), 2);
+
+        let unlocked = store.unlock(&kek).unwrap();
+        assert_eq!(unlocked.active().0, second);
+        assert!(unlocked.keys().contains_key(&first));
+    }
+}
@@ -1,8 +1,6 @@
 use eframe::egui;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::path::PathBuf;
-use std::env;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
@@ -79,46 +77,30 @@ impl ListUploadsState {
     }
 }
 
-fn get_uploads_file_path() -> PathBuf {
-    let home_dir = env::var("USERPROFILE")
-        .or_else(|_| env::var("HOME"))
-        .unwrap_or_else(|_| ".".to_string());
-    
-    PathBuf::from(home_dir).join(".pipe-cli-uploads.json")
-}
-
 fn load_uploads_from_file() -> Vec<UploadRecord> {
-    let file_path = get_uploads_file_path();
-    
-    if !file_path.exists() {
-        return Vec::new();
-    }
-    
-    match std::fs::read_to_string(&file_path) {
-        Ok(content) => {
-            let mut records = Vec::new();
-            
-            for line in content.lines() {
-                if line.trim().is_empty() {
-                    continue;
-                }
-                
-                match serde_json::from_str::<UploadRecord>(line) {
-                    Ok(record) => records.push(record),
-                    Err(e) => {
-                        eprintln!("Failed to parse upload record: {} - Line: {}", e, line);
-                    }
-                }
-            }
-            // Sort by timestamp (newest first)
-            records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-            records
-        }
+    let entries = match pipe::catalog::Catalog::open_default().and_then(|catalog| catalog.entries()) {
+        Ok(entries) => entries,
         Err(e) => {
-            eprintln!("Failed to read uploads file: {}", e);
-            Vec::new()
+            eprintln!("Failed to read upload catalog: {}", e);
+            return Vec::new();
         }
-    }
+    };
+
+    let mut records: Vec<UploadRecord> = entries
+        .into_iter()
+        .map(|e| UploadRecord {
+            local_path: e.entry.local_path,
+            remote_path: e.entry.remote_path,
+            status: e.entry.status,
+            message: e.entry.message,
+            blake3_hash: e.entry.blake3_hash.unwrap_or_default(),
+            file_size: e.entry.file_size.unwrap_or_default(),
+            timestamp: e.entry.timestamp.unwrap_or_default(),
+        })
+        .collect();
+    // Sort by timestamp (newest first)
+    records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    records
 }
 
 fn format_file_size(size: u64) -> String {
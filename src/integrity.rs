@@ -0,0 +1,368 @@
+use anyhow::{anyhow, Result};
+use futures_util::stream::{self, StreamExt};
+use rand::seq::SliceRandom;
+use reqwest::Client;
+use std::fmt;
+use std::io::{Cursor, Write};
+
+use crate::catalog::CatalogEntry;
+use crate::encryption::{self, EncryptionFormat};
+use crate::{add_auth_headers, SavedCredentials};
+
+/// Bytes needed to recognise a pipe-cli container from its magic bytes
+const SNIFF_LEN: usize = 16;
+
+/// Result of checking a remote file against its recorded Blake3 hash
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Match,
+    Mismatch { actual: String },
+    /// The check could not be made; the reason says why
+    Unknown(String),
+}
+
+impl Verdict {
+    pub fn is_mismatch(&self) -> bool {
+        matches!(self, Verdict::Mismatch { .. })
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Match => write!(f, "match"),
+            Verdict::Mismatch { .. } => write!(f, "MISMATCH"),
+            Verdict::Unknown(reason) => write!(f, "unknown ({})", reason),
+        }
+    }
+}
+
+/// Outcome of verifying one catalogued upload
+#[derive(Debug)]
+pub struct Verification {
+    pub remote_path: String,
+    pub expected: Option<String>,
+    /// Download or protocol failure; counted as a failed check
+    pub result: Result<Verdict>,
+}
+
+impl Verification {
+    /// Whether the file is known to be damaged or could not be read
+    pub fn failed(&self) -> bool {
+        match &self.result {
+            Ok(verdict) => verdict.is_mismatch(),
+            Err(_) => true,
+        }
+    }
+
+    /// Result and detail as stored in the audit history
+    pub fn audit_result(&self) -> (&'static str, Option<String>) {
+        match &self.result {
+            Ok(Verdict::Match) => ("match", None),
+            Ok(Verdict::Mismatch { actual }) => ("mismatch", Some(format!("remote Blake3 {}", actual))),
+            Ok(Verdict::Unknown(reason)) => ("unknown", Some(reason.clone())),
+            Err(e) => ("error", Some(e.to_string())),
+        }
+    }
+}
+
+/// How many files an audit checks: a fixed count or a share of the candidates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    Count(usize),
+    Percent(f64),
+}
+
+impl SampleSize {
+    /// Parses `50` or `2%`
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid sample size '{}'. Use a file count or a percentage like 2%", value);
+        match value.trim().strip_suffix('%') {
+            Some(percent) => {
+                let percent: f64 = percent.trim().parse().map_err(|_| invalid())?;
+                if !(percent > 0.0 && percent <= 100.0) {
+                    return Err(anyhow!("Sample percentage must be above 0 and at most 100"));
+                }
+                Ok(SampleSize::Percent(percent))
+            }
+            None => Ok(SampleSize::Count(value.trim().parse().map_err(|_| invalid())?)),
+        }
+    }
+
+    /// Files to check out of `total`. A percentage rounds up, so a non-empty
+    /// set always gets at least one check.
+    pub fn of(self, total: usize) -> usize {
+        match self {
+            SampleSize::Count(count) => count.min(total),
+            SampleSize::Percent(percent) => ((total as f64 * percent / 100.0).ceil() as usize).min(total),
+        }
+    }
+}
+
+/// A uniform random sample of `entries`
+pub fn choose_sample(mut entries: Vec<CatalogEntry>, size: SampleSize) -> Vec<CatalogEntry> {
+    let count = size.of(entries.len());
+    entries.shuffle(&mut rand::thread_rng());
+    entries.truncate(count);
+    entries.sort_by(|a, b| a.entry.remote_path.cmp(&b.entry.remote_path));
+    entries
+}
+
+/// What a remote stream hashes to
+#[derive(Debug, PartialEq)]
+pub enum StreamDigest {
+    /// Blake3 of the file as uploaded (PIPE-ZST containers decompressed)
+    Blake3 { hash: String, size: u64 },
+    /// Encrypted; the catalog holds the plaintext hash, which needs a key
+    Encrypted(EncryptionFormat),
+}
+
+enum Sink {
+    Raw(Box<blake3::Hasher>),
+    Zstd(Box<zstd::stream::write::Decoder<'static, blake3::Hasher>>),
+    Encrypted(EncryptionFormat),
+}
+
+/// Incremental Blake3 over a download. Uploads made with `--compress` are
+/// hashed after decompression, because the catalog records the hash of the
+/// original file.
+pub struct StreamHasher {
+    head: Vec<u8>,
+    sink: Option<Sink>,
+}
+
+impl Default for StreamHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamHasher {
+    pub fn new() -> Self {
+        Self {
+            head: Vec::with_capacity(SNIFF_LEN),
+            sink: None,
+        }
+    }
+
+    /// Feed the next bytes. Returns false once the rest of the stream is not
+    /// needed (encrypted files).
+    pub fn update(&mut self, bytes: &[u8]) -> Result<bool> {
+        match self.sink {
+            None => {
+                self.head.extend_from_slice(bytes);
+                if self.head.len() >= SNIFF_LEN {
+                    self.start()?;
+                }
+            }
+            Some(Sink::Raw(ref mut hasher)) => {
+                hasher.update(bytes);
+            }
+            Some(Sink::Zstd(ref mut decoder)) => decoder.write_all(bytes)?,
+            Some(Sink::Encrypted(_)) => {}
+        }
+        Ok(!matches!(self.sink, Some(Sink::Encrypted(_))))
+    }
+
+    fn start(&mut self) -> Result<()> {
+        let head = std::mem::take(&mut self.head);
+        let sink = match encryption::detect_format(Cursor::new(&head))? {
+            // A file shorter than the compression header that merely starts
+            // with the magic is hashed as stored
+            EncryptionFormat::Compressed if head.len() >= crate::compression::HEADER_SIZE => {
+                let mut decoder = zstd::stream::write::Decoder::new(blake3::Hasher::new())?;
+                decoder.write_all(&head[crate::compression::HEADER_SIZE..])?;
+                Sink::Zstd(Box::new(decoder))
+            }
+            EncryptionFormat::Plain | EncryptionFormat::Compressed => {
+                let mut hasher = Box::new(blake3::Hasher::new());
+                hasher.update(&head);
+                Sink::Raw(hasher)
+            }
+            format => Sink::Encrypted(format),
+        };
+        self.sink = Some(sink);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<StreamDigest> {
+        if self.sink.is_none() {
+            self.start()?;
+        }
+        let hasher = match self.sink {
+            Some(Sink::Raw(hasher)) => *hasher,
+            Some(Sink::Zstd(mut decoder)) => {
+                decoder
+                    .flush()
+                    .map_err(|e| anyhow!("Compressed stream is damaged: {}", e))?;
+                decoder.into_inner()
+            }
+            Some(Sink::Encrypted(format)) => return Ok(StreamDigest::Encrypted(format)),
+            None => unreachable!("sink is set by start"),
+        };
+        Ok(StreamDigest::Blake3 {
+            hash: hasher.finalize().to_hex().to_string(),
+            size: hasher.count(),
+        })
+    }
+}
+
+/// Stream a remote file through Blake3 without writing it to disk
+pub async fn hash_remote_file(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+    remote_path: &str,
+) -> Result<StreamDigest> {
+    let request = client
+        .get(format!("{}/download-stream", base_url))
+        .query(&[("file_name", remote_path)]);
+    let resp = add_auth_headers(request, creds, false).send().await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("Download failed with status {}", resp.status()));
+    }
+
+    let mut hasher = StreamHasher::new();
+    let mut body = resp.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        if !hasher.update(&chunk?)? {
+            break;
+        }
+    }
+    hasher.finish()
+}
+
+/// Compare a remote file with the Blake3 hash recorded at upload
+pub async fn verify_remote_file(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+    remote_path: &str,
+    expected: Option<&str>,
+) -> Result<Verdict> {
+    let Some(expected) = expected else {
+        return Ok(Verdict::Unknown("no hash recorded".to_string()));
+    };
+    // Sign-and-encrypt containers have no magic bytes to detect
+    if remote_path.ends_with(".qenc") {
+        return Ok(Verdict::Unknown(format!("encrypted: {}", EncryptionFormat::QuantumSigned)));
+    }
+
+    Ok(match hash_remote_file(client, base_url, creds, remote_path).await? {
+        StreamDigest::Blake3 { hash, .. } if hash.eq_ignore_ascii_case(expected) => Verdict::Match,
+        StreamDigest::Blake3 { hash, .. } => Verdict::Mismatch { actual: hash },
+        StreamDigest::Encrypted(format) => Verdict::Unknown(format!("encrypted: {}", format)),
+    })
+}
+
+/// Verify catalogued uploads, `parallel` at a time. `on_done` is called as
+/// each check finishes; results come back in the order of `entries`.
+pub async fn verify_entries(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+    entries: Vec<CatalogEntry>,
+    parallel: usize,
+    on_done: impl Fn(&Verification),
+) -> Vec<Verification> {
+    let on_done = &on_done;
+    let mut results: Vec<(usize, Verification)> = stream::iter(entries.into_iter().enumerate())
+        .map(|(index, record)| async move {
+            let entry = record.entry;
+            let result = verify_remote_file(
+                client,
+                base_url,
+                creds,
+                &entry.remote_path,
+                entry.blake3_hash.as_deref(),
+            )
+            .await;
+            let verification = Verification {
+                remote_path: entry.remote_path,
+                expected: entry.blake3_hash,
+                result,
+            };
+            on_done(&verification);
+            (index, verification)
+        })
+        .buffer_unordered(parallel.max(1))
+        .collect()
+        .await;
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, verification)| verification).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::{compress_stream, CompressionSpec};
+
+    fn digest_in_pieces(data: &[u8], piece: usize) -> StreamDigest {
+        let mut hasher = StreamHasher::new();
+        for chunk in data.chunks(piece) {
+            if !hasher.update(chunk).unwrap() {
+                break;
+            }
+        }
+        hasher.finish().unwrap()
+    }
+
+    #[test]
+    fn test_stream_hash_matches_original() {
+        let data = b"catalogued upload contents ".repeat(500);
+        let expected = StreamDigest::Blake3 {
+            hash: blake3::hash(&data).to_hex().to_string(),
+            size: data.len() as u64,
+        };
+        assert_eq!(digest_in_pieces(&data, 7), expected);
+        let short = StreamDigest::Blake3 {
+            hash: blake3::hash(&data[..5]).to_hex().to_string(),
+            size: 5,
+        };
+        assert_eq!(digest_in_pieces(&data[..5], 3), short);
+
+        // Compressed uploads hash to the original file
+        let mut compressed = Vec::new();
+        compress_stream(Cursor::new(&data), &mut compressed, CompressionSpec::parse("zstd").unwrap())
+            .unwrap();
+        assert_eq!(digest_in_pieces(&compressed, 5), expected);
+
+        // Too short for a compression header, so hashed as stored
+        for lookalike in [&b"PIPE-ZST"[..], &b"PIPE-ZST\x01"[..]] {
+            let raw = StreamDigest::Blake3 {
+                hash: blake3::hash(lookalike).to_hex().to_string(),
+                size: lookalike.len() as u64,
+            };
+            assert_eq!(digest_in_pieces(lookalike, 4), raw);
+        }
+    }
+
+    #[test]
+    fn test_sample_size() {
+        assert_eq!(SampleSize::parse("2%").unwrap(), SampleSize::Percent(2.0));
+        assert_eq!(SampleSize::parse("50").unwrap(), SampleSize::Count(50));
+        assert!(SampleSize::parse("0%").is_err());
+        assert!(SampleSize::parse("150%").is_err());
+        assert!(SampleSize::parse("some").is_err());
+
+        assert_eq!(SampleSize::Percent(2.0).of(1000), 20);
+        assert_eq!(SampleSize::Percent(2.0).of(10), 1);
+        assert_eq!(SampleSize::Percent(2.0).of(0), 0);
+        assert_eq!(SampleSize::Count(50).of(10), 10);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_stream_stops_early() {
+        let mut encrypted = Vec::new();
+        encryption::encrypt_file_with_password(Cursor::new(b"secret".repeat(100)), &mut encrypted, "pw", None)
+            .await
+            .unwrap();
+
+        let mut hasher = StreamHasher::new();
+        assert!(!hasher.update(&encrypted[..32]).unwrap());
+        assert_eq!(
+            hasher.finish().unwrap(),
+            StreamDigest::Encrypted(EncryptionFormat::Password)
+        );
+    }
+}
@@ -10,6 +10,7 @@ use uuid::Uuid;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::encryption::{derive_key_from_password, generate_salt};
+use crate::quantum_keyring::QuantumKeyPair;
 
 /// Algorithm types supported by the keyring
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -19,6 +20,7 @@ pub enum KeyAlgorithm {
     Kyber1024,
     Dilithium5,
     KyberAes, // Hybrid mode
+    Ed25519Dilithium5, // Hybrid signatures
 }
 
 impl std::fmt::Display for KeyAlgorithm {
@@ -28,6 +30,23 @@ impl std::fmt::Display for KeyAlgorithm {
             KeyAlgorithm::Kyber1024 => write!(f, "Kyber1024"),
             KeyAlgorithm::Dilithium5 => write!(f, "Dilithium5"),
             KeyAlgorithm::KyberAes => write!(f, "Kyber1024+AES-256"),
+            KeyAlgorithm::Ed25519Dilithium5 => write!(f, "Ed25519+Dilithium5"),
+        }
+    }
+}
+
+impl KeyAlgorithm {
+    /// Whether keys of this algorithm sign data
+    pub fn is_signing(&self) -> bool {
+        matches!(self, KeyAlgorithm::Dilithium5 | KeyAlgorithm::Ed25519Dilithium5)
+    }
+
+    /// Algorithm of a signing public key, told apart by its size
+    pub fn for_signing_key(public_key: &[u8]) -> Self {
+        if crate::quantum::is_hybrid_public_key(public_key) {
+            KeyAlgorithm::Ed25519Dilithium5
+        } else {
+            KeyAlgorithm::Dilithium5
         }
     }
 }
@@ -38,7 +57,85 @@ pub struct KeyMetadata {
     pub created_at: DateTime<Utc>,
     pub last_used: Option<DateTime<Utc>>,
     pub description: Option<String>,
+    /// Number of encryptions and signatures made with this key
     pub usage_count: u64,
+    /// New encryptions and signatures are refused after this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// New encryptions and signatures are refused once the key has been used this often
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u64>,
+}
+
+/// Days before expiry from which a key is reported as due for rotation
+const EXPIRY_NOTICE_DAYS: i64 = 30;
+
+/// Share of `max_uses` from which a key is reported as due for rotation
+const USAGE_NOTICE_PERCENT: u64 = 90;
+
+impl KeyMetadata {
+    /// Metadata for a key created now, with no lifecycle policy
+    pub fn new(description: Option<String>) -> Self {
+        Self {
+            created_at: Utc::now(),
+            last_used: None,
+            description,
+            usage_count: 0,
+            expires_at: None,
+            max_uses: None,
+        }
+    }
+
+    /// Why the key may no longer be used for new data, if it has expired or
+    /// reached its usage limit
+    pub fn retired_reason(&self, now: DateTime<Utc>) -> Option<String> {
+        if let Some(expires_at) = self.expires_at {
+            if expires_at <= now {
+                return Some(format!("expired on {}", expires_at.format("%Y-%m-%d")));
+            }
+        }
+        if let Some(max_uses) = self.max_uses {
+            if self.usage_count >= max_uses {
+                return Some(format!("reached its limit of {} uses", max_uses));
+            }
+        }
+        None
+    }
+
+    /// Why the key should be rotated soon: it is close to its expiry or usage
+    /// limit, or has no expiry and is older than `max_age`
+    pub fn rotation_reason(&self, now: DateTime<Utc>, max_age: chrono::Duration) -> Option<String> {
+        if let Some(expires_at) = self.expires_at {
+            let remaining = expires_at - now;
+            if remaining < chrono::Duration::days(EXPIRY_NOTICE_DAYS) {
+                return Some(format!(
+                    "expires on {} (in {} days)",
+                    expires_at.format("%Y-%m-%d"),
+                    remaining.num_days()
+                ));
+            }
+        } else if now - self.created_at > max_age {
+            return Some(format!(
+                "created {} days ago and has no expiry",
+                (now - self.created_at).num_days()
+            ));
+        }
+        if let Some(max_uses) = self.max_uses {
+            if self.usage_count * 100 >= max_uses * USAGE_NOTICE_PERCENT {
+                return Some(format!("used {} of {} times", self.usage_count, max_uses));
+            }
+        }
+        None
+    }
+}
+
+/// What key material is needed for; retired keys only serve existing data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyUse {
+    /// Encrypting or signing new data
+    Encrypt,
+    /// Decrypting or exporting existing data
+    Decrypt,
 }
 
 /// A stored encryption key
@@ -55,6 +152,72 @@ pub struct StoredKey {
 
     // For post-quantum keys
     pub public_key: Option<Vec<u8>>,
+
+    /// Fingerprint recorded when the key was created or imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl StoredKey {
+    /// Hex fingerprint, computed from the public key for entries that predate stored fingerprints
+    pub fn fingerprint_hex(&self) -> Option<String> {
+        self.fingerprint.clone().or_else(|| {
+            self.public_key
+                .as_ref()
+                .map(|public_key| key_fingerprint(&self.algorithm, public_key))
+        })
+    }
+
+    /// True for imported public keys that have no secret part
+    pub fn is_public_only(&self) -> bool {
+        self.encrypted_key.is_empty()
+    }
+}
+
+/// Stable fingerprint of a key: Blake3 over the algorithm and the public key,
+/// or over the secret itself for symmetric keys (domain-separated, so it
+/// reveals nothing usable about the key)
+pub fn key_fingerprint(algorithm: &KeyAlgorithm, key_bytes: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new_derive_key("pipe-cli key fingerprint v1");
+    hasher.update(algorithm.to_string().as_bytes());
+    hasher.update(key_bytes);
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Number of words in the spoken form of a fingerprint (11 bits each)
+const FINGERPRINT_WORDS: usize = 8;
+
+/// Fingerprint as hex in groups of four, e.g. `12a0 8e26 4bce ...`
+pub fn fingerprint_grouped(fingerprint: &str) -> String {
+    fingerprint
+        .as_bytes()
+        .chunks(4)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Fingerprint as words from the BIP39 English list, short enough to read
+/// out over the phone. Covers the first 88 bits of the fingerprint.
+pub fn fingerprint_words(fingerprint: &str) -> Result<String> {
+    let bytes = hex::decode(fingerprint).map_err(|_| anyhow!("Invalid fingerprint '{}'", fingerprint))?;
+    if bytes.len() * 8 < FINGERPRINT_WORDS * 11 {
+        return Err(anyhow!("Fingerprint '{}' is too short", fingerprint));
+    }
+
+    let word_list = bip39::Language::English.word_list();
+    let words: Vec<&str> = (0..FINGERPRINT_WORDS)
+        .map(|i| {
+            // Read 11 bits starting at bit i * 11
+            let index = (0..11).fold(0usize, |acc, bit| {
+                let pos = i * 11 + bit;
+                let set = bytes[pos / 8] >> (7 - pos % 8) & 1;
+                (acc << 1) | set as usize
+            });
+            word_list[index]
+        })
+        .collect();
+    Ok(words.join(" "))
 }
 
 // Default nonce for backward compatibility (existing keys won't have this field)
@@ -70,6 +233,122 @@ pub struct KeyMaterial {
     pub public_key: Option<Vec<u8>>,
 }
 
+/// Post-quantum keypair with both secret keys encrypted under the keyring password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredQuantumKeyPair {
+    pub created_at: DateTime<Utc>,
+    pub kyber_public: Vec<u8>,
+    pub dilithium_public: Vec<u8>,
+    /// Kyber and Dilithium secret keys (JSON), AES-GCM encrypted
+    encrypted_secrets: Vec<u8>,
+    salt: [u8; 32],
+    nonce: [u8; 12],
+}
+
+/// Secret halves of a quantum keypair, as sealed in `StoredQuantumKeyPair`
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct QuantumSecrets {
+    kyber_secret: Vec<u8>,
+    dilithium_secret: Vec<u8>,
+}
+
+/// Quantum keys used for one uploaded file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum QuantumFileKeys {
+    /// Keypair generated for this file only
+    Own(StoredQuantumKeyPair),
+    /// Long-lived identity keypair, by name
+    Identity { name: String },
+}
+
+/// Someone else's public keys, for encrypting to them and verifying their signatures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contact {
+    pub name: String,
+    /// ML-KEM (Kyber1024) public key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kem_public: Option<Vec<u8>>,
+    /// ML-DSA (Dilithium5) or hybrid Ed25519+Dilithium5 public key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sign_public: Option<Vec<u8>>,
+    /// Set once the fingerprints were compared out of band
+    pub verified: bool,
+    pub added_at: DateTime<Utc>,
+}
+
+impl Contact {
+    /// Create an unverified contact, checking the key sizes
+    pub fn new(name: &str, kem_public: Option<Vec<u8>>, sign_public: Option<Vec<u8>>) -> Result<Self> {
+        use pqcrypto_mlkem::mlkem1024 as kyber1024;
+
+        if kem_public.is_none() && sign_public.is_none() {
+            return Err(anyhow!("Contact '{}' needs at least one public key", name));
+        }
+        if let Some(ref key) = kem_public {
+            if key.len() != kyber1024::public_key_bytes() {
+                return Err(anyhow!("Not a Kyber1024 public key ({} bytes)", key.len()));
+            }
+        }
+        if let Some(ref key) = sign_public {
+            if !crate::quantum::is_signing_public_key(key) {
+                return Err(anyhow!("Not a Dilithium5 or Ed25519+Dilithium5 public key ({} bytes)", key.len()));
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            kem_public,
+            sign_public,
+            verified: false,
+            added_at: Utc::now(),
+        })
+    }
+
+    /// Fingerprint of the encryption key
+    pub fn kem_fingerprint(&self) -> Option<String> {
+        self.kem_public
+            .as_ref()
+            .map(|key| key_fingerprint(&KeyAlgorithm::Kyber1024, key))
+    }
+
+    /// Fingerprint of the signing key
+    pub fn sign_fingerprint(&self) -> Option<String> {
+        self.sign_public
+            .as_ref()
+            .map(|key| key_fingerprint(&KeyAlgorithm::for_signing_key(key), key))
+    }
+
+    /// Load a contact card written by `save_card`. Cards are always imported unverified.
+    pub fn load_card(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let card: ContactCard =
+            serde_json::from_str(&contents).map_err(|e| anyhow!("Not a pipe-cli contact card: {}", e))?;
+        Self::new(&card.name, card.kem_public, card.sign_public)
+    }
+
+    /// Write the public keys to a shareable contact card
+    pub fn save_card(&self, path: &Path) -> Result<()> {
+        let card = ContactCard {
+            name: self.name.clone(),
+            kem_public: self.kem_public.clone(),
+            sign_public: self.sign_public.clone(),
+        };
+        fs::write(path, serde_json::to_string_pretty(&card)?)?;
+        Ok(())
+    }
+}
+
+/// Shareable form of a contact: name and public keys only
+#[derive(Serialize, Deserialize)]
+struct ContactCard {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kem_public: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sign_public: Option<Vec<u8>>,
+}
+
 /// Password verification data
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PasswordVerification {
@@ -92,6 +371,15 @@ pub struct Keyring {
     /// Migration flag to detect legacy keyrings
     #[serde(default = "default_legacy_mode")]
     legacy_mode: bool,
+    /// Quantum keys of uploaded files, by file ID
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    quantum_files: HashMap<String, QuantumFileKeys>,
+    /// Long-lived quantum identity keypairs, by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    quantum_identities: HashMap<String, StoredQuantumKeyPair>,
+    /// Public keys of other people, by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    contacts: HashMap<String, Contact>,
 }
 
 fn default_legacy_mode() -> bool {
@@ -110,6 +398,9 @@ impl Keyring {
             keys: HashMap::new(),
             password_verification: None,
             legacy_mode: false,
+            quantum_files: HashMap::new(),
+            quantum_identities: HashMap::new(),
+            contacts: HashMap::new(),
         }
     }
 
@@ -155,18 +446,109 @@ impl Keyring {
             fs::create_dir_all(parent)?;
         }
 
+        // Write to a temporary file first so a crash never leaves a truncated keyring
+        let temp_path = path.with_extension("json.tmp");
         let contents = serde_json::to_string_pretty(self)?;
-        fs::write(path, contents)?;
+        {
+            let mut file = fs::File::create(&temp_path)?;
+            std::io::Write::write_all(&mut file, contents.as_bytes())?;
+            file.sync_all()?;
+        }
 
         // Set restrictive permissions on Unix
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(path)?.permissions();
+            let mut perms = fs::metadata(&temp_path)?.permissions();
             perms.set_mode(0o600); // Read/write for owner only
-            fs::set_permissions(path, perms)?;
+            fs::set_permissions(&temp_path, perms)?;
+        }
+
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Save after a password change. The previous file is kept as
+    /// `keyring.json.bak` until the new file has been read back and unlocked
+    /// with the new password; on failure the previous file is restored.
+    pub fn save_after_password_change(&self, path: &Path, new_password: &str) -> Result<()> {
+        let backup_path = path.with_extension("json.bak");
+        if path.exists() {
+            fs::copy(path, &backup_path)?;
+        }
+
+        let confirmed = self.save_to_file(path).and_then(|_| {
+            let reloaded = Self::load_from_file(path)?;
+            if !reloaded.verify_password(new_password)? {
+                return Err(anyhow!("Rewritten keyring does not accept the new password"));
+            }
+            Ok(())
+        });
+
+        match confirmed {
+            Ok(()) => {
+                if backup_path.exists() {
+                    fs::remove_file(&backup_path)?;
+                }
+                Ok(())
+            }
+            Err(e) => {
+                if backup_path.exists() {
+                    fs::rename(&backup_path, path)?;
+                }
+                Err(anyhow!("Failed to save keyring, previous keyring restored: {}", e))
+            }
+        }
+    }
+
+    /// Change the master password: every stored key, quantum keypair and the
+    /// password verifier are re-encrypted. The keyring is left unchanged if
+    /// anything fails.
+    pub fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<()> {
+        if self.legacy_mode {
+            return Err(anyhow!("Legacy keyring has no master password; run 'pipe keyring-migrate' instead"));
+        }
+        if !self.verify_password(old_password)? {
+            return Err(anyhow!("Invalid keyring password"));
+        }
+
+        let mut updated = Self {
+            version: self.version,
+            keys: HashMap::new(),
+            password_verification: None,
+            legacy_mode: false,
+            quantum_files: self.quantum_files.clone(),
+            quantum_identities: self.quantum_identities.clone(),
+            contacts: self.contacts.clone(),
+        };
+
+        for (name, stored_key) in &self.keys {
+            let mut new_stored_key = stored_key.clone();
+            if !stored_key.is_public_only() {
+                let old_protection_key = derive_key_from_password(old_password, &stored_key.salt)?;
+                let mut decrypted = crate::encryption::decrypt_data(
+                    &stored_key.encrypted_key,
+                    &old_protection_key,
+                    &stored_key.nonce,
+                )
+                .map_err(|_| anyhow!("Failed to decrypt key '{}'", name))?;
+
+                let new_salt = generate_salt();
+                let new_protection_key = derive_key_from_password(new_password, &new_salt)?;
+                let (encrypted_key, nonce) = crate::encryption::encrypt_data(&decrypted, &new_protection_key)?;
+                decrypted.zeroize();
+
+                new_stored_key.encrypted_key = encrypted_key;
+                new_stored_key.salt = new_salt;
+                new_stored_key.nonce = nonce;
+            }
+            updated.keys.insert(name.clone(), new_stored_key);
         }
 
+        updated.reseal_quantum_keypairs(old_password, new_password)?;
+        updated.initialize_password(new_password)?;
+
+        *self = updated;
         Ok(())
     }
 
@@ -238,6 +620,12 @@ impl Keyring {
         let mut updated_keys = HashMap::new();
         
         for (name, stored_key) in self.keys.clone() {
+            // Imported public keys have no secret to re-encrypt
+            if stored_key.is_public_only() {
+                updated_keys.insert(name, stored_key);
+                continue;
+            }
+
             // Decrypt with old password
             let old_protection_key = derive_key_from_password(old_password, &stored_key.salt)?;
             let decrypted = crate::encryption::decrypt_data(
@@ -261,6 +649,7 @@ impl Keyring {
 
         // Update keyring
         self.keys = updated_keys;
+        self.reseal_quantum_keypairs(old_password, new_password)?;
         self.initialize_password(new_password)?;
         
         Ok(())
@@ -299,13 +688,9 @@ impl Keyring {
             encrypted_key,
             salt,
             nonce,
-            metadata: KeyMetadata {
-                created_at: Utc::now(),
-                last_used: None,
-                description,
-                usage_count: 0,
-            },
+            metadata: KeyMetadata::new(description),
             public_key: None,
+            fingerprint: Some(key_fingerprint(&KeyAlgorithm::Aes256, &key)),
         };
 
         let key_name = name.unwrap_or_else(|| key_id.clone());
@@ -348,13 +733,9 @@ impl Keyring {
             encrypted_key,
             salt,
             nonce,
-            metadata: KeyMetadata {
-                created_at: Utc::now(),
-                last_used: None,
-                description,
-                usage_count: 0,
-            },
+            metadata: KeyMetadata::new(description),
             public_key: Some(public_key.as_bytes().to_vec()),
+            fingerprint: None,
         };
 
         let key_name = name.unwrap_or_else(|| key_id.clone());
@@ -369,38 +750,72 @@ impl Keyring {
         name: Option<String>,
         description: Option<String>,
         password: &str,
+    ) -> Result<String> {
+        use pqcrypto_mldsa::mldsa87 as dilithium5;
+
+        let (public_key, secret_key) = dilithium5::keypair();
+        self.store_signing_keypair(
+            name,
+            description,
+            password,
+            KeyAlgorithm::Dilithium5,
+            public_key.as_bytes().to_vec(),
+            secret_key.as_bytes(),
+        )
+    }
+
+    /// Generate a new hybrid Ed25519 + Dilithium5 signing keypair
+    pub fn generate_hybrid_signing_keypair(
+        &mut self,
+        name: Option<String>,
+        description: Option<String>,
+        password: &str,
+    ) -> Result<String> {
+        let (public_key, mut secret_key) = crate::quantum::generate_hybrid_signing_keypair();
+        let result = self.store_signing_keypair(
+            name,
+            description,
+            password,
+            KeyAlgorithm::Ed25519Dilithium5,
+            public_key,
+            &secret_key,
+        );
+        secret_key.zeroize();
+        result
+    }
+
+    fn store_signing_keypair(
+        &mut self,
+        name: Option<String>,
+        description: Option<String>,
+        password: &str,
+        algorithm: KeyAlgorithm,
+        public_key: Vec<u8>,
+        secret_key: &[u8],
     ) -> Result<String> {
         // Verify password if not in legacy mode
         if !self.legacy_mode && !self.verify_password(password)? {
             return Err(anyhow!("Invalid keyring password"));
         }
 
-        use pqcrypto_mldsa::mldsa87 as dilithium5;
-
-        let (public_key, secret_key) = dilithium5::keypair();
-
         let key_id = Uuid::new_v4().to_string();
         let salt = generate_salt();
 
         // Use the appropriate password based on mode
         let key_password = self.get_key_password(password);
         let protection_key = derive_key_from_password(&key_password, &salt)?;
-        let (encrypted_key, nonce) = crate::encryption::encrypt_data(secret_key.as_bytes(), &protection_key)?;
+        let (encrypted_key, nonce) = crate::encryption::encrypt_data(secret_key, &protection_key)?;
 
         let stored_key = StoredKey {
             id: key_id.clone(),
             name: name.clone(),
-            algorithm: KeyAlgorithm::Dilithium5,
+            algorithm,
             encrypted_key,
             salt,
             nonce,
-            metadata: KeyMetadata {
-                created_at: Utc::now(),
-                last_used: None,
-                description,
-                usage_count: 0,
-            },
-            public_key: Some(public_key.as_bytes().to_vec()),
+            metadata: KeyMetadata::new(description),
+            public_key: Some(public_key),
+            fingerprint: None,
         };
 
         let key_name = name.unwrap_or_else(|| key_id.clone());
@@ -427,8 +842,26 @@ impl Keyring {
         Ok(())
     }
 
-    /// Decrypt and retrieve key material (updates usage stats)
-    pub fn get_key_material(&mut self, name: &str, password: &str) -> Result<KeyMaterial> {
+    /// Set the expiry and usage limit of a key, replacing any previous policy
+    pub fn set_key_policy(
+        &mut self,
+        name: &str,
+        expires_at: Option<DateTime<Utc>>,
+        max_uses: Option<u64>,
+    ) -> Result<()> {
+        let stored_key = self
+            .keys
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Key '{}' not found", name))?;
+        stored_key.metadata.expires_at = expires_at;
+        stored_key.metadata.max_uses = max_uses;
+        Ok(())
+    }
+
+    /// Decrypt and retrieve key material (updates usage stats). Keys past
+    /// their expiry or usage limit are refused for `KeyUse::Encrypt`, and
+    /// only those uses count towards the limit.
+    pub fn get_key_material(&mut self, name: &str, password: &str, key_use: KeyUse) -> Result<KeyMaterial> {
         // Use the appropriate password based on mode
         let key_password = self.get_key_password(password);
         
@@ -437,6 +870,20 @@ impl Keyring {
             .get_mut(name)
             .ok_or_else(|| anyhow!("Key '{}' not found", name))?;
 
+        if stored_key.is_public_only() {
+            return Err(anyhow!("Key '{}' is a public key only; its secret part is not in this keyring", name));
+        }
+
+        if key_use == KeyUse::Encrypt {
+            if let Some(reason) = stored_key.metadata.retired_reason(Utc::now()) {
+                return Err(anyhow!(
+                    "Key '{}' {} and can no longer encrypt or sign new data; existing files can still be decrypted. Generate a replacement with 'pipe key-gen'",
+                    name,
+                    reason
+                ));
+            }
+        }
+
         // Decrypt the key
         let protection_key = derive_key_from_password(&key_password, &stored_key.salt)?;
         let decrypted = crate::encryption::decrypt_data(
@@ -447,7 +894,9 @@ impl Keyring {
 
         // Update usage stats
         stored_key.metadata.last_used = Some(Utc::now());
-        stored_key.metadata.usage_count += 1;
+        if key_use == KeyUse::Encrypt {
+            stored_key.metadata.usage_count += 1;
+        }
 
         let material = match stored_key.algorithm {
             KeyAlgorithm::Aes256 => {
@@ -459,7 +908,7 @@ impl Keyring {
                     public_key: None,
                 }
             }
-            KeyAlgorithm::Kyber1024 | KeyAlgorithm::Dilithium5 => KeyMaterial {
+            KeyAlgorithm::Kyber1024 | KeyAlgorithm::Dilithium5 | KeyAlgorithm::Ed25519Dilithium5 => KeyMaterial {
                 symmetric_key: None,
                 private_key: Some(decrypted),
                 public_key: stored_key.public_key.clone(),
@@ -491,6 +940,293 @@ impl Keyring {
     pub fn is_legacy(&self) -> bool {
         self.legacy_mode
     }
+
+    /// Store the keypair generated for an uploaded file
+    pub fn store_quantum_keypair(&mut self, keypair: &QuantumKeyPair, password: &str) -> Result<()> {
+        if !self.legacy_mode && !self.verify_password(password)? {
+            return Err(anyhow!("Invalid keyring password"));
+        }
+
+        let stored = seal_quantum_keypair(keypair, &self.get_key_password(password))?;
+        self.quantum_files
+            .insert(keypair.file_id.clone(), QuantumFileKeys::Own(stored));
+        Ok(())
+    }
+
+    /// Generate a long-lived quantum identity keypair that uploads can reuse
+    pub fn generate_quantum_identity(&mut self, name: &str, password: &str) -> Result<()> {
+        if !self.legacy_mode && !self.verify_password(password)? {
+            return Err(anyhow!("Invalid keyring password"));
+        }
+        if self.quantum_identities.contains_key(name) {
+            return Err(anyhow!("Quantum identity '{}' already exists", name));
+        }
+
+        let keypair = crate::quantum_keyring::generate_quantum_keypair(name)?;
+        let stored = seal_quantum_keypair(&keypair, &self.get_key_password(password))?;
+        self.quantum_identities.insert(name.to_string(), stored);
+        Ok(())
+    }
+
+    /// Decrypt a quantum identity keypair
+    pub fn quantum_identity(&self, name: &str, password: &str) -> Result<QuantumKeyPair> {
+        let stored = self
+            .quantum_identities
+            .get(name)
+            .ok_or_else(|| anyhow!("Quantum identity '{}' not found", name))?;
+        open_quantum_keypair(stored, name, &self.get_key_password(password))
+    }
+
+    /// Record that a file was encrypted with an identity keypair
+    pub fn link_quantum_identity(&mut self, file_id: &str, name: &str) -> Result<()> {
+        if !self.quantum_identities.contains_key(name) {
+            return Err(anyhow!("Quantum identity '{}' not found", name));
+        }
+        self.quantum_files.insert(
+            file_id.to_string(),
+            QuantumFileKeys::Identity {
+                name: name.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Check if the keyring holds quantum keys for a file (no password needed)
+    pub fn has_quantum_keys(&self, file_id: &str) -> bool {
+        self.quantum_files.contains_key(file_id)
+    }
+
+    /// Decrypt the quantum keypair used for a file
+    pub fn quantum_keypair(&self, file_id: &str, password: &str) -> Result<QuantumKeyPair> {
+        match self.quantum_files.get(file_id) {
+            Some(QuantumFileKeys::Own(stored)) => {
+                open_quantum_keypair(stored, file_id, &self.get_key_password(password))
+            }
+            Some(QuantumFileKeys::Identity { name }) => self.quantum_identity(name, password),
+            None => Err(anyhow!("Quantum key not found for file: {}", file_id)),
+        }
+    }
+
+    /// Number of files with quantum keys in the keyring
+    pub fn quantum_file_count(&self) -> usize {
+        self.quantum_files.len()
+    }
+
+    /// Quantum identities with their creation time
+    pub fn quantum_identities(&self) -> Vec<(&String, &StoredQuantumKeyPair)> {
+        self.quantum_identities.iter().collect()
+    }
+
+    /// Decrypt every quantum identity and per-file keypair for a backup
+    pub fn export_quantum_keys(&self, password: &str) -> Result<PlainQuantumKeys> {
+        if !self.legacy_mode && !self.verify_password(password)? {
+            return Err(anyhow!("Invalid keyring password"));
+        }
+        let key_password = self.get_key_password(password);
+
+        let mut keys = PlainQuantumKeys::default();
+        for (name, stored) in &self.quantum_identities {
+            keys.identities.push(open_quantum_keypair(stored, name, &key_password)?);
+        }
+        for (file_id, file_keys) in &self.quantum_files {
+            match file_keys {
+                QuantumFileKeys::Own(stored) => {
+                    keys.files.push(open_quantum_keypair(stored, file_id, &key_password)?)
+                }
+                QuantumFileKeys::Identity { name } => {
+                    keys.identity_files.push((file_id.clone(), name.clone()))
+                }
+            }
+        }
+        keys.identities.sort_by(|a, b| a.file_id.cmp(&b.file_id));
+        keys.files.sort_by(|a, b| a.file_id.cmp(&b.file_id));
+        keys.identity_files.sort();
+        Ok(keys)
+    }
+
+    /// Restore quantum keys from a backup. Entries already in the keyring are
+    /// skipped; an identity whose name is taken by a different keypair is
+    /// stored as `<name>-recovered` and its files are linked to that name.
+    pub fn insert_quantum_keys(&mut self, keys: &PlainQuantumKeys, password: &str) -> Result<QuantumRestore> {
+        if !self.legacy_mode && !self.verify_password(password)? {
+            return Err(anyhow!("Invalid keyring password"));
+        }
+        let key_password = self.get_key_password(password);
+
+        let mut restore = QuantumRestore::default();
+        let mut renamed: HashMap<&str, String> = HashMap::new();
+        for identity in &keys.identities {
+            let present = self
+                .quantum_identities
+                .iter()
+                .find(|(_, stored)| stored.kyber_public == identity.kyber_public);
+            if let Some((existing, _)) = present {
+                restore.notes.push(if existing == &identity.file_id {
+                    format!("Quantum identity '{}' is already in the keyring", existing)
+                } else {
+                    format!(
+                        "Quantum identity '{}' is already in the keyring as '{}'",
+                        identity.file_id, existing
+                    )
+                });
+                renamed.insert(&identity.file_id, existing.clone());
+                continue;
+            }
+
+            let mut name = identity.file_id.clone();
+            if self.quantum_identities.contains_key(&name) {
+                name = format!("{}-recovered", identity.file_id);
+                let mut suffix = 2;
+                while self.quantum_identities.contains_key(&name) {
+                    name = format!("{}-recovered-{}", identity.file_id, suffix);
+                    suffix += 1;
+                }
+                restore.notes.push(format!(
+                    "Quantum identity '{}' is taken by another keypair; restored as '{}'",
+                    identity.file_id, name
+                ));
+                renamed.insert(&identity.file_id, name.clone());
+            }
+            self.quantum_identities
+                .insert(name, seal_quantum_keypair(identity, &key_password)?);
+            restore.restored += 1;
+        }
+
+        for keypair in &keys.files {
+            if self.quantum_files.contains_key(&keypair.file_id) {
+                restore.notes.push(format!(
+                    "Quantum keys for file '{}' are already in the keyring; skipped",
+                    keypair.file_id
+                ));
+                continue;
+            }
+            self.quantum_files.insert(
+                keypair.file_id.clone(),
+                QuantumFileKeys::Own(seal_quantum_keypair(keypair, &key_password)?),
+            );
+            restore.restored += 1;
+        }
+
+        for (file_id, name) in &keys.identity_files {
+            if self.quantum_files.contains_key(file_id) {
+                restore.notes.push(format!(
+                    "Quantum keys for file '{}' are already in the keyring; skipped",
+                    file_id
+                ));
+                continue;
+            }
+            let name = renamed.get(name.as_str()).unwrap_or(name);
+            self.link_quantum_identity(file_id, name)?;
+            restore.restored += 1;
+        }
+
+        Ok(restore)
+    }
+
+    /// Add a contact. Names must be unique, and a public key already held by
+    /// another contact is rejected.
+    pub fn add_contact(&mut self, contact: Contact) -> Result<()> {
+        if self.contacts.contains_key(&contact.name) {
+            return Err(anyhow!("Contact '{}' already exists", contact.name));
+        }
+        for existing in self.contacts.values() {
+            let same_kem = contact.kem_public.is_some() && existing.kem_public == contact.kem_public;
+            let same_sign = contact.sign_public.is_some() && existing.sign_public == contact.sign_public;
+            if same_kem || same_sign {
+                return Err(anyhow!("Contact '{}' already has this public key", existing.name));
+            }
+        }
+        self.contacts.insert(contact.name.clone(), contact);
+        Ok(())
+    }
+
+    /// Get a contact by name
+    pub fn get_contact(&self, name: &str) -> Option<&Contact> {
+        self.contacts.get(name)
+    }
+
+    /// Mark a contact's keys as verified out of band
+    pub fn verify_contact(&mut self, name: &str) -> Result<()> {
+        self.contacts
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Contact '{}' not found", name))?
+            .verified = true;
+        Ok(())
+    }
+
+    /// Remove a contact
+    pub fn remove_contact(&mut self, name: &str) -> Result<()> {
+        self.contacts
+            .remove(name)
+            .ok_or_else(|| anyhow!("Contact '{}' not found", name))?;
+        Ok(())
+    }
+
+    /// All contacts, sorted by name
+    pub fn contacts(&self) -> Vec<&Contact> {
+        let mut contacts: Vec<&Contact> = self.contacts.values().collect();
+        contacts.sort_by(|a, b| a.name.cmp(&b.name));
+        contacts
+    }
+
+    /// Re-encrypt all quantum keypairs from one key password to another
+    fn reseal_quantum_keypairs(&mut self, old_password: &str, new_password: &str) -> Result<()> {
+        let own_keypairs = self.quantum_files.iter_mut().filter_map(|(id, keys)| match keys {
+            QuantumFileKeys::Own(stored) => Some((id, stored)),
+            QuantumFileKeys::Identity { .. } => None,
+        });
+        for (id, stored) in own_keypairs.chain(self.quantum_identities.iter_mut()) {
+            let keypair = open_quantum_keypair(stored, id, old_password)?;
+            *stored = seal_quantum_keypair(&keypair, new_password)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encrypt the secret halves of a quantum keypair with a key password
+fn seal_quantum_keypair(keypair: &QuantumKeyPair, key_password: &str) -> Result<StoredQuantumKeyPair> {
+    let secrets = QuantumSecrets {
+        kyber_secret: keypair.kyber_secret.clone(),
+        dilithium_secret: keypair.dilithium_secret.clone(),
+    };
+    let mut plaintext = serde_json::to_vec(&secrets)?;
+
+    let salt = generate_salt();
+    let protection_key = derive_key_from_password(key_password, &salt)?;
+    let (encrypted_secrets, nonce) = crate::encryption::encrypt_data(&plaintext, &protection_key)?;
+    plaintext.zeroize();
+
+    Ok(StoredQuantumKeyPair {
+        created_at: keypair.created_at,
+        kyber_public: keypair.kyber_public.clone(),
+        dilithium_public: keypair.dilithium_public.clone(),
+        encrypted_secrets,
+        salt,
+        nonce,
+    })
+}
+
+/// Decrypt a stored quantum keypair with a key password
+fn open_quantum_keypair(
+    stored: &StoredQuantumKeyPair,
+    file_id: &str,
+    key_password: &str,
+) -> Result<QuantumKeyPair> {
+    let protection_key = derive_key_from_password(key_password, &stored.salt)?;
+    let mut plaintext =
+        crate::encryption::decrypt_data(&stored.encrypted_secrets, &protection_key, &stored.nonce)?;
+    let secrets: Result<QuantumSecrets, _> = serde_json::from_slice(&plaintext);
+    plaintext.zeroize();
+    let secrets = secrets.map_err(|e| anyhow!("Quantum keypair for '{}' is corrupted: {}", file_id, e))?;
+
+    Ok(QuantumKeyPair {
+        kyber_public: stored.kyber_public.clone(),
+        kyber_secret: secrets.kyber_secret.clone(),
+        dilithium_public: stored.dilithium_public.clone(),
+        dilithium_secret: secrets.dilithium_secret.clone(),
+        file_id: file_id.to_string(),
+        created_at: stored.created_at,
+    })
 }
 
 /// Export a key to a standalone file
@@ -518,40 +1254,324 @@ pub fn export_key(
     // Then re-encrypt with export password
     let (encrypted, nonce) = crate::encryption::encrypt_data(&decrypted, &export_protection_key)?;
 
-    #[derive(Serialize)]
-    struct ExportedKey {
-        version: u8,
-        algorithm: KeyAlgorithm,
-        encrypted_key: Vec<u8>,
-        salt: [u8; 32],
-        nonce: [u8; 12],
-        public_key: Option<Vec<u8>>,
-        metadata: KeyMetadata,
-    }
-
     let exported = ExportedKey {
-        version: 1,
+        version: ExportedKey::VERSION,
+        name: Some(key_name.to_string()),
         algorithm: key.algorithm.clone(),
-        encrypted_key: encrypted,
-        salt: export_salt,
-        nonce,
+        encrypted_key: Some(encrypted),
+        salt: Some(export_salt),
+        nonce: Some(nonce),
         public_key: key.public_key.clone(),
         metadata: key.metadata.clone(),
     };
 
-    let contents = serde_json::to_string_pretty(&exported)?;
-    fs::write(output_path, contents)?;
+    exported.save_to_file(output_path)
+}
+
+/// Export only the public part of a post-quantum key, e.g. to hand to a colleague
+pub fn export_public_key(keyring: &Keyring, key_name: &str, output_path: &Path) -> Result<()> {
+    let key = keyring
+        .get_key(key_name)
+        .ok_or_else(|| anyhow!("Key '{}' not found", key_name))?;
+    let public_key = key
+        .public_key
+        .clone()
+        .ok_or_else(|| anyhow!("Key '{}' ({}) has no public key", key_name, key.algorithm))?;
+
+    let exported = ExportedKey {
+        version: ExportedKey::VERSION,
+        name: Some(key_name.to_string()),
+        algorithm: key.algorithm.clone(),
+        encrypted_key: None,
+        salt: None,
+        nonce: None,
+        public_key: Some(public_key),
+        metadata: key.metadata.clone(),
+    };
+
+    exported.save_to_file(output_path)
+}
+
+/// A key with its secret in the clear, as carried by backups and paper keys.
+/// The secret is zeroized on drop.
+#[derive(Serialize, Deserialize)]
+pub struct PlainKey {
+    pub name: String,
+    pub algorithm: KeyAlgorithm,
+    /// Absent for public keys
+    pub secret: Option<Vec<u8>>,
+    pub public_key: Option<Vec<u8>>,
+    pub metadata: KeyMetadata,
+}
+
+impl Drop for PlainKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// Quantum keys with their secrets in the clear, as carried by backups
+#[derive(Serialize, Deserialize, Default)]
+pub struct PlainQuantumKeys {
+    /// Identity keypairs; `file_id` holds the identity name
+    #[serde(default)]
+    pub identities: Vec<QuantumKeyPair>,
+    /// Keypairs generated for a single file
+    #[serde(default)]
+    pub files: Vec<QuantumKeyPair>,
+    /// Files encrypted with an identity, as (file ID, identity name)
+    #[serde(default)]
+    pub identity_files: Vec<(String, String)>,
+}
+
+impl PlainQuantumKeys {
+    pub fn is_empty(&self) -> bool {
+        self.identities.is_empty() && self.files.is_empty() && self.identity_files.is_empty()
+    }
+}
+
+impl Drop for PlainQuantumKeys {
+    fn drop(&mut self) {
+        for keypair in self.identities.iter_mut().chain(self.files.iter_mut()) {
+            keypair.kyber_secret.zeroize();
+            keypair.dilithium_secret.zeroize();
+        }
+    }
+}
+
+/// Outcome of `Keyring::insert_quantum_keys`
+#[derive(Debug, Default)]
+pub struct QuantumRestore {
+    /// Identities, keypairs and identity links added to the keyring
+    pub restored: usize,
+    /// Entries that were skipped or renamed, and why
+    pub notes: Vec<String>,
+}
+
+/// Standalone key file written by `export_key` and `export_public_key`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedKey {
+    version: u8,
+    /// Keyring name at export time (absent in older exports)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    algorithm: KeyAlgorithm,
+    /// Secret key encrypted with the export password; absent in public-key-only exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_key: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<[u8; 32]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<[u8; 12]>,
+    public_key: Option<Vec<u8>>,
+    metadata: KeyMetadata,
+}
+
+impl ExportedKey {
+    const VERSION: u8 = 1;
+
+    /// Load an exported key file
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let exported: Self = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Not a pipe-cli key export: {}", e))?;
+
+        if exported.version > Self::VERSION {
+            return Err(anyhow!(
+                "Key export version {} is newer than supported version {}",
+                exported.version,
+                Self::VERSION
+            ));
+        }
+
+        Ok(exported)
+    }
+
+    fn save_to_file(&self, output_path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(output_path, contents)?;
+
+        // Set restrictive permissions
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = fs::metadata(output_path)?.permissions();
+            perms.set_mode(0o600);
+            fs::set_permissions(output_path, perms)?;
+        }
+
+        Ok(())
+    }
+
+    /// Name the key had in the exporting keyring
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn algorithm(&self) -> &KeyAlgorithm {
+        &self.algorithm
+    }
+
+    pub fn public_key(&self) -> Option<&[u8]> {
+        self.public_key.as_deref()
+    }
+
+    /// True when the file holds only a public key
+    pub fn is_public_only(&self) -> bool {
+        self.encrypted_key.is_none()
+    }
+}
+
+impl Keyring {
+    /// Import an exported key, re-encrypting its secret under the local keyring
+    /// password. Algorithm, public key and metadata are kept. Returns the name
+    /// the key was stored under.
+    pub fn import_key(
+        &mut self,
+        exported: &ExportedKey,
+        name: String,
+        keyring_password: &str,
+        export_password: Option<&str>,
+    ) -> Result<String> {
+        // Recover the secret with the export password
+        let secret = match (&exported.encrypted_key, &exported.salt, &exported.nonce) {
+            (Some(encrypted_key), Some(salt), Some(nonce)) => {
+                let export_password = export_password
+                    .ok_or_else(|| anyhow!("Export password required to import a secret key"))?;
+                let export_protection_key = derive_key_from_password(export_password, salt)?;
+                let secret = crate::encryption::decrypt_data(encrypted_key, &export_protection_key, nonce)
+                    .map_err(|_| anyhow!("Failed to decrypt exported key. Wrong export password?"))?;
+                Some(secret)
+            }
+            (None, None, None) => None,
+            _ => return Err(anyhow!("Key export is incomplete (missing salt or nonce)")),
+        };
+
+        let key = PlainKey {
+            name,
+            algorithm: exported.algorithm.clone(),
+            secret,
+            public_key: exported.public_key.clone(),
+            metadata: exported.metadata.clone(),
+        };
+        self.insert_plain_key(&key, keyring_password)
+    }
+
+    /// Decrypt a key for backup or export in another format
+    pub fn export_plain_key(&mut self, name: &str, password: &str) -> Result<PlainKey> {
+        let material = self.get_key_material(name, password, KeyUse::Decrypt)?;
+        let stored_key = &self.keys[name];
+        let secret = match stored_key.algorithm {
+            KeyAlgorithm::Aes256 => material.symmetric_key.map(|key| key.to_vec()),
+            _ => material.private_key.clone(),
+        };
+
+        Ok(PlainKey {
+            name: name.to_string(),
+            algorithm: stored_key.algorithm.clone(),
+            secret,
+            public_key: stored_key.public_key.clone(),
+            metadata: stored_key.metadata.clone(),
+        })
+    }
+
+    /// Add a decrypted key (from an export, backup or paper key), encrypting
+    /// its secret under the keyring password. Keys already present are
+    /// detected by fingerprint. Returns the name the key was stored under.
+    pub fn insert_plain_key(&mut self, key: &PlainKey, keyring_password: &str) -> Result<String> {
+        if !self.legacy_mode && !self.verify_password(keyring_password)? {
+            return Err(anyhow!("Invalid keyring password"));
+        }
+        if self.keys.contains_key(&key.name) {
+            return Err(anyhow!("A key named '{}' already exists; choose another name", key.name));
+        }
+
+        let key_password = self.get_key_password(keyring_password);
+
+        let fingerprint = match key.algorithm {
+            KeyAlgorithm::Aes256 => {
+                let secret = key
+                    .secret
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("AES-256 keys have no public part to import"))?;
+                if secret.len() != 32 {
+                    return Err(anyhow!("AES-256 key has invalid length {}", secret.len()));
+                }
+                key_fingerprint(&key.algorithm, secret)
+            }
+            _ => {
+                let public_key = key
+                    .public_key
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("{} key has no public key", key.algorithm))?;
+                key_fingerprint(&key.algorithm, public_key)
+            }
+        };
+
+        if let Some(existing) = self.find_by_fingerprint(&key.algorithm, &fingerprint, &key_password)? {
+            return Err(anyhow!(
+                "Key is already in the keyring as '{}' (fingerprint {})",
+                existing,
+                fingerprint
+            ));
+        }
+
+        let (encrypted_key, salt, nonce) = match &key.secret {
+            Some(secret) => {
+                let salt = generate_salt();
+                let protection_key = derive_key_from_password(&key_password, &salt)?;
+                let (encrypted_key, nonce) = crate::encryption::encrypt_data(secret, &protection_key)?;
+                (encrypted_key, salt, nonce)
+            }
+            None => (Vec::new(), [0u8; 32], [0u8; 12]),
+        };
+
+        let stored_key = StoredKey {
+            id: Uuid::new_v4().to_string(),
+            name: Some(key.name.clone()),
+            algorithm: key.algorithm.clone(),
+            encrypted_key,
+            salt,
+            nonce,
+            metadata: key.metadata.clone(),
+            public_key: key.public_key.clone(),
+            fingerprint: Some(fingerprint),
+        };
+        self.keys.insert(key.name.clone(), stored_key);
 
-    // Set restrictive permissions
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(output_path)?.permissions();
-        perms.set_mode(0o600);
-        fs::set_permissions(output_path, perms)?;
+        Ok(key.name.clone())
     }
 
-    Ok(())
+    /// Find a key of the same algorithm with the given fingerprint. AES keys
+    /// created before fingerprints were stored are decrypted to compare.
+    fn find_by_fingerprint(
+        &self,
+        algorithm: &KeyAlgorithm,
+        fingerprint: &str,
+        key_password: &str,
+    ) -> Result<Option<String>> {
+        for (name, key) in &self.keys {
+            if &key.algorithm != algorithm {
+                continue;
+            }
+            let existing = match key.fingerprint_hex() {
+                Some(existing) => existing,
+                None if !key.is_public_only() => {
+                    let protection_key = derive_key_from_password(key_password, &key.salt)?;
+                    let mut decrypted =
+                        crate::encryption::decrypt_data(&key.encrypted_key, &protection_key, &key.nonce)?;
+                    let existing = key_fingerprint(&key.algorithm, &decrypted);
+                    decrypted.zeroize();
+                    existing
+                }
+                None => continue,
+            };
+            if existing == fingerprint {
+                return Ok(Some(name.clone()));
+            }
+        }
+        Ok(None)
+    }
 }
 
 #[cfg(test)]
@@ -708,6 +1728,240 @@ mod tests {
         assert!(exported_json["public_key"].is_array());
     }
 
+    #[test]
+    fn test_export_import_between_keyrings() {
+        let temp_dir = TempDir::new().unwrap();
+        let export_path = temp_dir.path().join("laptop_key.json");
+
+        let (mut source, source_password) = create_test_keyring(false);
+        let key_name = source
+            .generate_aes_key(Some("laptop".to_string()), Some("Backups".to_string()), &source_password)
+            .unwrap();
+        let original = source.get_key_material(&key_name, &source_password, KeyUse::Decrypt).unwrap();
+        export_key(&source, &key_name, &export_path, &source_password, "export pw").unwrap();
+
+        // The target keyring has its own password
+        let mut target = Keyring::new();
+        target.initialize_password("target password").unwrap();
+        let exported = ExportedKey::load_from_file(&export_path).unwrap();
+        assert_eq!(exported.name(), Some("laptop"));
+        assert!(target
+            .import_key(&exported, "laptop".to_string(), "target password", Some("wrong"))
+            .is_err());
+
+        let imported = target
+            .import_key(&exported, "laptop".to_string(), "target password", Some("export pw"))
+            .unwrap();
+        let stored = target.get_key(&imported).unwrap();
+        assert_eq!(stored.algorithm, KeyAlgorithm::Aes256);
+        assert_eq!(stored.metadata.description.as_deref(), Some("Backups"));
+        assert_eq!(stored.fingerprint_hex(), source.get_key(&key_name).unwrap().fingerprint_hex());
+
+        let material = target.get_key_material(&imported, "target password", KeyUse::Decrypt).unwrap();
+        assert_eq!(material.symmetric_key, original.symmetric_key);
+
+        // Same key under another name is detected by fingerprint
+        let err = target
+            .import_key(&exported, "copy".to_string(), "target password", Some("export pw"))
+            .unwrap_err();
+        assert!(err.to_string().contains("already in the keyring as 'laptop'"));
+    }
+
+    #[test]
+    fn test_import_public_key_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let export_path = temp_dir.path().join("colleague.pub.json");
+
+        let (mut source, source_password) = create_test_keyring(false);
+        let key_name = source
+            .generate_kyber_keypair(Some("colleague".to_string()), None, &source_password)
+            .unwrap();
+        export_public_key(&source, &key_name, &export_path).unwrap();
+
+        let exported_json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&export_path).unwrap()).unwrap();
+        assert!(exported_json.get("encrypted_key").is_none());
+
+        let (mut target, target_password) = create_test_keyring(false);
+        let exported = ExportedKey::load_from_file(&export_path).unwrap();
+        assert!(exported.is_public_only());
+        let imported = target
+            .import_key(&exported, "colleague".to_string(), &target_password, None)
+            .unwrap();
+
+        let stored = target.get_key(&imported).unwrap();
+        assert!(stored.is_public_only());
+        assert_eq!(stored.public_key, source.get_key(&key_name).unwrap().public_key);
+        assert!(target
+            .get_key_material(&imported, &target_password, KeyUse::Decrypt)
+            .unwrap_err()
+            .to_string()
+            .contains("public key only"));
+    }
+
+    #[test]
+    fn test_quantum_keypairs_encrypted_in_keyring() {
+        let temp_dir = TempDir::new().unwrap();
+        let keyring_path = temp_dir.path().join("keyring.json");
+        let (mut keyring, password) = create_test_keyring(false);
+
+        let keypair = crate::quantum_keyring::generate_quantum_keypair("report.pdf").unwrap();
+        keyring.store_quantum_keypair(&keypair, &password).unwrap();
+        keyring.generate_quantum_identity("me", &password).unwrap();
+        keyring.link_quantum_identity("notes.txt", "me").unwrap();
+        assert!(keyring.link_quantum_identity("other.txt", "nobody").is_err());
+        keyring.save_to_file(&keyring_path).unwrap();
+
+        // Secret keys never appear in plaintext on disk
+        let contents = fs::read_to_string(&keyring_path).unwrap();
+        let secret_json = serde_json::to_string(&keypair.kyber_secret).unwrap();
+        assert!(!contents.contains(&secret_json[1..secret_json.len() - 1]));
+
+        let loaded = Keyring::load_from_file(&keyring_path).unwrap();
+        assert!(loaded.has_quantum_keys("report.pdf"));
+        assert!(!loaded.has_quantum_keys("missing.pdf"));
+        assert!(loaded.quantum_keypair("report.pdf", "wrong password").is_err());
+
+        let restored = loaded.quantum_keypair("report.pdf", &password).unwrap();
+        assert_eq!(restored.kyber_secret, keypair.kyber_secret);
+        assert_eq!(restored.dilithium_secret, keypair.dilithium_secret);
+        assert_eq!(restored.kyber_public, keypair.kyber_public);
+
+        // Files encrypted with an identity resolve to the identity keypair
+        let identity = loaded.quantum_identity("me", &password).unwrap();
+        let linked = loaded.quantum_keypair("notes.txt", &password).unwrap();
+        assert_eq!(linked.kyber_secret, identity.kyber_secret);
+        assert_eq!(loaded.quantum_file_count(), 2);
+    }
+
+    #[test]
+    fn test_quantum_keys_backup_restore() {
+        let (mut keyring, password) = create_test_keyring(false);
+        let keypair = crate::quantum_keyring::generate_quantum_keypair("report.pdf").unwrap();
+        keyring.store_quantum_keypair(&keypair, &password).unwrap();
+        keyring.generate_quantum_identity("me", &password).unwrap();
+        keyring.link_quantum_identity("notes.txt", "me").unwrap();
+        let identity = keyring.quantum_identity("me", &password).unwrap();
+
+        assert!(keyring.export_quantum_keys("wrong password").is_err());
+        let exported = keyring.export_quantum_keys(&password).unwrap();
+
+        // A fresh keyring with another identity of the same name and a password of its own
+        let mut restored = Keyring::new();
+        restored.initialize_password("other password").unwrap();
+        restored.generate_quantum_identity("me", "other password").unwrap();
+        let outcome = restored.insert_quantum_keys(&exported, "other password").unwrap();
+        assert_eq!(outcome.restored, 3);
+        assert_eq!(outcome.notes.len(), 1);
+        assert!(outcome.notes[0].contains("'me-recovered'"), "{}", outcome.notes[0]);
+
+        let file_keys = restored.quantum_keypair("report.pdf", "other password").unwrap();
+        assert_eq!(file_keys.kyber_secret, keypair.kyber_secret);
+        let linked = restored.quantum_keypair("notes.txt", "other password").unwrap();
+        assert_eq!(linked.kyber_secret, identity.kyber_secret);
+
+        // Restoring again adds nothing
+        let again = restored.insert_quantum_keys(&exported, "other password").unwrap();
+        assert_eq!(again.restored, 0);
+        assert_eq!(again.notes.len(), 3);
+        assert_eq!(restored.quantum_identities().len(), 2);
+    }
+
+    #[test]
+    fn test_contacts_store() {
+        use pqcrypto_mldsa::mldsa87 as dilithium5;
+        use pqcrypto_mlkem::mlkem1024 as kyber1024;
+
+        let temp_dir = TempDir::new().unwrap();
+        let keyring_path = temp_dir.path().join("keyring.json");
+        let card_path = temp_dir.path().join("alice.contact.json");
+
+        let (kem_public, _) = kyber1024::keypair();
+        let (sign_public, _) = dilithium5::keypair();
+        assert!(Contact::new("alice", Some(vec![0u8; 32]), None).is_err());
+        assert!(Contact::new("alice", None, None).is_err());
+
+        let alice = Contact::new(
+            "alice",
+            Some(kem_public.as_bytes().to_vec()),
+            Some(sign_public.as_bytes().to_vec()),
+        )
+        .unwrap();
+        alice.save_card(&card_path).unwrap();
+
+        let mut keyring = Keyring::new();
+        keyring.add_contact(Contact::load_card(&card_path).unwrap()).unwrap();
+        assert!(!keyring.get_contact("alice").unwrap().verified);
+
+        // The same keys under another name are rejected
+        let mut copy = Contact::load_card(&card_path).unwrap();
+        copy.name = "alice2".to_string();
+        assert!(keyring.add_contact(copy).is_err());
+
+        keyring.verify_contact("alice").unwrap();
+        keyring.save_to_file(&keyring_path).unwrap();
+
+        let mut loaded = Keyring::load_from_file(&keyring_path).unwrap();
+        let contact = loaded.get_contact("alice").unwrap();
+        assert!(contact.verified);
+        assert_eq!(contact.sign_fingerprint(), alice.sign_fingerprint());
+        assert_ne!(contact.kem_fingerprint(), contact.sign_fingerprint());
+
+        loaded.remove_contact("alice").unwrap();
+        assert!(loaded.contacts().is_empty());
+        assert!(loaded.remove_contact("alice").is_err());
+    }
+
+    #[test]
+    fn test_change_password() {
+        let temp_dir = TempDir::new().unwrap();
+        let keyring_path = temp_dir.path().join("keyring.json");
+        let (mut keyring, password) = create_test_keyring(false);
+
+        let key_name = keyring.generate_aes_key(Some("data".to_string()), None, &password).unwrap();
+        let original = keyring.get_key_material(&key_name, &password, KeyUse::Decrypt).unwrap();
+        keyring.generate_quantum_identity("me", &password).unwrap();
+        keyring.save_to_file(&keyring_path).unwrap();
+
+        assert!(keyring.change_password("wrong", "new password").is_err());
+        keyring.change_password(&password, "new password").unwrap();
+        keyring.save_after_password_change(&keyring_path, "new password").unwrap();
+        assert!(!keyring_path.with_extension("json.bak").exists());
+
+        let mut loaded = Keyring::load_from_file(&keyring_path).unwrap();
+        assert!(loaded.verify_password("new password").unwrap());
+        assert!(!loaded.verify_password(&password).unwrap());
+        let material = loaded.get_key_material(&key_name, "new password", KeyUse::Decrypt).unwrap();
+        assert_eq!(material.symmetric_key, original.symmetric_key);
+        assert!(loaded.get_key_material(&key_name, &password, KeyUse::Decrypt).is_err());
+        assert!(loaded.quantum_identity("me", "new password").is_ok());
+
+        // A failed confirmation restores the previous file
+        let before = fs::read_to_string(&keyring_path).unwrap();
+        assert!(loaded.save_after_password_change(&keyring_path, "not the password").is_err());
+        assert_eq!(fs::read_to_string(&keyring_path).unwrap(), before);
+        assert!(!keyring_path.with_extension("json.bak").exists());
+    }
+
+    #[test]
+    fn test_fingerprint_representations() {
+        let fingerprint = key_fingerprint(&KeyAlgorithm::Dilithium5, b"public key bytes");
+        assert_eq!(fingerprint.len(), 64);
+        assert_eq!(fingerprint, key_fingerprint(&KeyAlgorithm::Dilithium5, b"public key bytes"));
+        assert_ne!(fingerprint, key_fingerprint(&KeyAlgorithm::Kyber1024, b"public key bytes"));
+
+        let grouped = fingerprint_grouped(&fingerprint);
+        assert_eq!(grouped.split(' ').count(), 16);
+        assert_eq!(grouped.replace(' ', ""), fingerprint);
+
+        // All-zero bits map to the first word, all-one bits to the last
+        assert_eq!(fingerprint_words(&"00".repeat(32)).unwrap(), ["abandon"; 8].join(" "));
+        assert_eq!(fingerprint_words(&"ff".repeat(32)).unwrap(), ["zoo"; 8].join(" "));
+        let words = fingerprint_words(&fingerprint).unwrap();
+        assert_eq!(words.split(' ').count(), 8);
+        assert!(fingerprint_words("abcd").is_err());
+    }
+
     #[test]
     fn test_password_verification() {
         let (keyring, password) = create_test_keyring(false);
@@ -735,6 +1989,16 @@ mod tests {
             None,
             &old_password
         ).unwrap();
+        let quantum_keypair = crate::quantum_keyring::generate_quantum_keypair("legacy.bin").unwrap();
+        keyring.store_quantum_keypair(&quantum_keypair, &old_password).unwrap();
+        let public_key = PlainKey {
+            name: "colleague".to_string(),
+            algorithm: KeyAlgorithm::Kyber1024,
+            secret: None,
+            public_key: Some(quantum_keypair.kyber_public.clone()),
+            metadata: KeyMetadata::new(None),
+        };
+        keyring.insert_plain_key(&public_key, &old_password).unwrap();
         
         // Save as legacy
         keyring.save_to_file(&keyring_path).unwrap();
@@ -752,8 +2016,17 @@ mod tests {
         assert!(!loaded.verify_password(&old_password).unwrap());
         
         // Verify we can still access the key with new password
-        let key_material = loaded.get_key_material(&key_name, new_password).unwrap();
+        let key_material = loaded.get_key_material(&key_name, new_password, KeyUse::Decrypt).unwrap();
         assert!(key_material.symmetric_key.is_some());
+
+        // Quantum keypairs are re-encrypted too
+        let restored = loaded.quantum_keypair("legacy.bin", new_password).unwrap();
+        assert_eq!(restored.kyber_secret, quantum_keypair.kyber_secret);
+
+        // Public keys are carried over unchanged
+        let colleague = loaded.get_key("colleague").unwrap();
+        assert!(colleague.is_public_only());
+        assert_eq!(colleague.public_key.as_ref(), Some(&quantum_keypair.kyber_public));
     }
 
     #[test]
@@ -799,16 +2072,58 @@ mod tests {
         ).unwrap();
         
         // Get key material with correct password
-        let material = keyring.get_key_material(&key_name, &password).unwrap();
+        let material = keyring.get_key_material(&key_name, &password, KeyUse::Decrypt).unwrap();
         assert!(material.symmetric_key.is_some());
         assert_eq!(material.symmetric_key.unwrap().len(), 32);
         
-        // Check usage stats were updated
+        // Check usage stats were updated; decryption does not count as a use
         let key = keyring.get_key(&key_name).unwrap();
-        assert_eq!(key.metadata.usage_count, 1);
+        assert_eq!(key.metadata.usage_count, 0);
         assert!(key.metadata.last_used.is_some());
     }
 
+    #[test]
+    fn test_key_lifecycle_policy() {
+        let (mut keyring, password) = create_test_keyring(false);
+        let key_name = keyring
+            .generate_aes_key(Some("limited".to_string()), None, &password)
+            .unwrap();
+
+        // Usage limit: encryptions stop at the limit, decryption keeps working
+        keyring.set_key_policy(&key_name, None, Some(2)).unwrap();
+        keyring.get_key_material(&key_name, &password, KeyUse::Encrypt).unwrap();
+        keyring.get_key_material(&key_name, &password, KeyUse::Encrypt).unwrap();
+        let err = keyring
+            .get_key_material(&key_name, &password, KeyUse::Encrypt)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("limit of 2 uses"), "{}", err);
+        keyring.get_key_material(&key_name, &password, KeyUse::Decrypt).unwrap();
+        assert_eq!(keyring.get_key(&key_name).unwrap().metadata.usage_count, 2);
+
+        // Expiry
+        let now = Utc::now();
+        keyring
+            .set_key_policy(&key_name, Some(now - chrono::Duration::days(1)), None)
+            .unwrap();
+        assert!(keyring.get_key_material(&key_name, &password, KeyUse::Encrypt).is_err());
+        assert!(keyring.get_key_material(&key_name, &password, KeyUse::Decrypt).is_ok());
+
+        // Rotation reminders
+        let max_age = chrono::Duration::days(365);
+        let mut metadata = KeyMetadata::new(None);
+        assert!(metadata.rotation_reason(now, max_age).is_none());
+        metadata.created_at = now - chrono::Duration::days(400);
+        assert!(metadata.rotation_reason(now, max_age).unwrap().contains("no expiry"));
+        metadata.expires_at = Some(now + chrono::Duration::days(10));
+        assert!(metadata.rotation_reason(now, max_age).unwrap().contains("expires on"));
+        metadata.expires_at = Some(now + chrono::Duration::days(100));
+        metadata.max_uses = Some(10);
+        metadata.usage_count = 9;
+        assert!(metadata.rotation_reason(now, max_age).unwrap().contains("used 9 of 10"));
+        assert!(metadata.retired_reason(now).is_none());
+    }
+
     #[test]
     fn test_wrong_password_error() {
         let (mut keyring, password) = create_test_keyring(false);
@@ -820,7 +2135,7 @@ mod tests {
         ).unwrap();
         
         // Try to get key material with wrong password (should fail in non-legacy mode)
-        let result = keyring.get_key_material(&key_name, "wrong_password");
+        let result = keyring.get_key_material(&key_name, "wrong_password", KeyUse::Decrypt);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Decryption failed"));
     }
@@ -830,7 +2145,7 @@ mod tests {
         let (mut keyring, password) = create_test_keyring(false);
         
         // Try to get non-existent key
-        let result = keyring.get_key_material("non_existent_key", &password);
+        let result = keyring.get_key_material("non_existent_key", &password, KeyUse::Decrypt);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("not found"));
     }
@@ -845,7 +2160,7 @@ mod tests {
             &password
         ).unwrap();
         
-        let material = keyring.get_key_material(&key_name, &password).unwrap();
+        let material = keyring.get_key_material(&key_name, &password, KeyUse::Decrypt).unwrap();
         
         // Dilithium keys should have private and public keys
         assert!(material.private_key.is_some());
@@ -863,7 +2178,7 @@ mod tests {
             &password
         ).unwrap();
         
-        let material = keyring.get_key_material(&key_name, &password).unwrap();
+        let material = keyring.get_key_material(&key_name, &password, KeyUse::Decrypt).unwrap();
         
         // Kyber keys should have private and public keys
         assert!(material.private_key.is_some());
@@ -885,7 +2200,7 @@ mod tests {
         ).unwrap();
         
         // Get key material
-        let material = keyring.get_key_material(&key_name, &password).unwrap();
+        let material = keyring.get_key_material(&key_name, &password, KeyUse::Decrypt).unwrap();
         
         // Sign some data
         let test_data = b"Hello, quantum world!";
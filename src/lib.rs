@@ -11,7 +11,7 @@ use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use reqwest::{Body, Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::fs::{self, OpenOptions};
+use std::fs;
 use std::io::Write as IoWrite; // For writeln!
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -25,11 +25,21 @@ use tokio::sync::Mutex as TokioMutex;
 use tokio::sync::Semaphore;
 use walkdir::WalkDir;
 
+mod backup;
+pub mod catalog;
+mod compression;
 mod encryption;
+mod envelope;
+mod integrity;
 mod keyring;
+mod listing;
+mod manifest;
+mod paperkey;
 mod quantum;
 mod quantum_keyring;
 mod password_utils;
+mod reconcile;
+mod signature;
 pub mod sync;
 
 #[cfg(test)]
@@ -201,12 +211,36 @@ pub enum Commands {
             help = "Upload tier: normal, priority, premium, ultra, enterprise"
         )]
         tier: Option<String>,
-        #[arg(long, help = "Encrypt file with password before upload")]
+        #[arg(long, help = "Encrypt file before upload with a per-file key wrapped by your master key (see rekey)")]
         encrypt: bool,
-        #[arg(long, help = "Password for encryption (will prompt if not provided)")]
+        #[arg(long, help = "Encrypt with a key derived from this password instead of the master key")]
         password: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "envelope",
+            help = "With --encrypt, prompt for a password to derive the key from instead of using the master key"
+        )]
+        use_password: bool,
+        #[arg(long, help = "Encrypt with a per-file key wrapped by your master key (the default for --encrypt)")]
+        envelope: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["encrypt", "envelope", "password", "use_password"],
+            help = "Encrypt for this contact's Kyber1024 key (see contact add); uploads <file_name>.enc"
+        )]
+        recipient: Option<String>,
         #[arg(long, help = "Show cost estimate without uploading")]
         dry_run: bool,
+        #[arg(long, help = "Compress before upload: zstd or zstd:<level> (1-22)")]
+        compress: Option<String>,
+        #[arg(long, help = "Sign with this Dilithium5 or hybrid keyring key and upload the signature as <file_name>.sig")]
+        sign: Option<String>,
+        #[arg(
+            long,
+            conflicts_with_all = ["encrypt", "envelope", "recipient", "compress", "sign"],
+            help = "Sign and encrypt with this quantum identity (see quantum-identity-gen); uploads <file_name>.qenc"
+        )]
+        quantum_identity: Option<String>,
         #[arg(long, help = "Print progress info for GUI integration")]
         gui_style: bool,
     },
@@ -230,16 +264,26 @@ pub enum Commands {
         #[arg(long, help = "Treat file_name as Blake3 hash ID instead of filename")]
         file_id: bool,
 
-        #[arg(long, help = "Decrypt file with password after download")]
+        #[arg(long, help = "Fetch <file_name>.enc and expect it to be encrypted (format is auto-detected)")]
         decrypt: bool,
         #[arg(long, help = "Password for decryption (will prompt if not provided)")]
         password: Option<String>,
-        #[arg(long, help = "Use key from keyring or key file")]
+        #[arg(long, help = "Use key from keyring for key-encrypted or Kyber files")]
         key: Option<String>,
-        #[arg(long, help = "Use post-quantum decryption (kyber)")]
+        #[arg(long, help = "Use post-quantum decryption (kyber); now auto-detected")]
         quantum: bool,
+        #[arg(long, help = "Save the file as downloaded, without auto-detecting encryption")]
+        raw: bool,
         #[arg(long, help = "Use legacy download endpoint (base64 encoded)")]
         legacy: bool,
+        #[arg(
+            long,
+            conflicts_with = "raw",
+            help = "Check the <file_name>.sig signature from upload-file --sign against the decrypted file; fails if it is missing or invalid"
+        )]
+        verify: bool,
+        #[arg(long, help = "Expected signer for --verify: contact name, public key file or keyring key name")]
+        signer: Option<String>,
         #[arg(long, help = "Print progress info for GUI integration")]
         gui_style: bool,
     },
@@ -255,7 +299,7 @@ pub enum Commands {
         file_id: bool,
     },
 
-    /// Get information about a file (size, encryption status, etc.)
+    /// Show a remote file's size, tier, upload history, public links and encryption header
     FileInfo {
         #[arg(long)]
         user_id: Option<String>,
@@ -270,21 +314,56 @@ pub enum Commands {
         output_file: String,
         #[arg(long, help = "Password for encryption (will prompt if not provided)")]
         password: Option<String>,
+        #[arg(long, help = "Encrypt with a per-file key wrapped by your master key (see rekey)")]
+        envelope: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["envelope", "password"],
+            help = "Encrypt for this contact's Kyber1024 key instead of a password (see contact add)"
+        )]
+        recipient: Option<String>,
+        #[arg(long, help = "Compress before encryption: zstd or zstd:<level> (1-22)")]
+        compress: Option<String>,
     },
 
     /// Decrypt a local file (without downloading)
     DecryptLocal {
         input_file: String,
-        output_file: String,
+        /// Output file or directory (default: original name next to the input)
+        output_file: Option<String>,
         #[arg(long, help = "Password for decryption (will prompt if not provided)")]
         password: Option<String>,
+        #[arg(long, help = "Use key from keyring for key-encrypted or Kyber files")]
+        key: Option<String>,
+        #[arg(long, help = "Overwrite an existing file with the restored original name")]
+        force: bool,
+    },
+
+    /// Show header information for an encrypted or compressed file
+    Inspect {
+        /// File to inspect
+        file: String,
+        #[arg(long, help = "Password to also reveal the encrypted metadata block")]
+        password: Option<String>,
+    },
+
+    /// Change the master key password, or rotate the master key and rewrap envelope-encrypted files
+    Rekey {
+        /// Local envelope-encrypted files to rewrap to the active master key
+        files: Vec<String>,
+        #[arg(long, help = "Remote envelope-encrypted files to rewrap (only the header is read; the new key block is uploaded as <name>.key)")]
+        remote: Vec<String>,
+        #[arg(long, help = "Generate a new master key for future uploads")]
+        rotate: bool,
+        #[arg(long, help = "Protect the master key with this keyring AES-256 key instead of a password")]
+        keyring_key: Option<String>,
     },
 
     /// Generate a new encryption key
     KeyGen {
         #[arg(long, help = "Name for the key")]
         name: Option<String>,
-        #[arg(long, help = "Algorithm: aes256, kyber1024, dilithium5")]
+        #[arg(long, help = "Algorithm: aes256, kyber1024, dilithium5, hybrid (Ed25519 + Dilithium5 signing)")]
         algorithm: Option<String>,
         #[arg(long, help = "Description of the key")]
         description: Option<String>,
@@ -295,6 +374,26 @@ pub enum Commands {
     /// List all keys in the keyring
     KeyList,
 
+    /// List keys that have expired or are due for rotation
+    KeyStatus {
+        #[arg(long, default_value = "365", help = "Report keys without an expiry once they are this many days old")]
+        max_age_days: i64,
+    },
+
+    /// Set when a key expires or how often it may be used for new data
+    KeyPolicy {
+        /// Name of the key
+        key_name: String,
+        #[arg(long, help = "Expire the key this many days from now")]
+        expires_in_days: Option<i64>,
+        #[arg(long, help = "Expiry date (YYYY-MM-DD)")]
+        expires: Option<String>,
+        #[arg(long, help = "Refuse new encryptions and signatures after this many uses")]
+        max_uses: Option<u64>,
+        #[arg(long, help = "Remove the expiry and usage limit")]
+        clear: bool,
+    },
+
     /// Delete a key from the keyring
     KeyDelete {
         /// Name or ID of the key to delete
@@ -307,6 +406,48 @@ pub enum Commands {
         key_name: String,
         /// Output file path
         output: String,
+        #[arg(long, help = "Export only the public key (Kyber/Dilithium keys); no password needed")]
+        public: bool,
+    },
+
+    /// Import a key exported with key-export into the keyring
+    KeyImport {
+        /// Exported key file
+        file: String,
+        #[arg(long, help = "Name to store the key under (default: name from the export file)")]
+        name: Option<String>,
+    },
+
+    /// Split keyring keys into Shamir shares for backup
+    KeyBackup {
+        /// Name of the key to back up
+        key_name: Option<String>,
+        #[arg(long, help = "Back up every key with a secret part, plus quantum identities and per-file quantum keys, instead of a single key")]
+        all: bool,
+        #[arg(long, default_value = "5", help = "Number of shares to create")]
+        shares: u8,
+        #[arg(long, default_value = "3", help = "Number of shares needed to recover")]
+        threshold: u8,
+        #[arg(long, help = "Write each share to its own file in this directory instead of printing")]
+        output_dir: Option<String>,
+    },
+
+    /// Rebuild keys from backup shares and import them into the keyring
+    KeyRecover {
+        /// Share files or share text (reads shares from stdin, one per line, if omitted)
+        shares: Vec<String>,
+    },
+
+    /// Print an AES-256 key as a paper key: rows of words with check words
+    KeyPaperExport {
+        /// Name or ID of the key to export
+        key_name: String,
+    },
+
+    /// Import an AES-256 key from a paper key into the keyring
+    KeyPaperImport {
+        /// Name to store the key under
+        name: String,
     },
 
     /// Migrate legacy keyring to use custom master password
@@ -315,7 +456,22 @@ pub enum Commands {
         force: bool,
     },
 
-    /// Sign a file with Dilithium
+    /// Change the keyring master password
+    KeyringPasswd,
+
+    /// Move plaintext per-file quantum keypairs (~/.pipe-cli/quantum-keys) into the keyring
+    QuantumKeysMigrate {
+        #[arg(long, help = "Keep the plaintext .quantum files after importing them")]
+        keep: bool,
+    },
+
+    /// Generate a long-lived quantum identity keypair (Kyber1024 + Ed25519/Dilithium5) in the keyring
+    QuantumIdentityGen {
+        /// Name of the identity
+        name: String,
+    },
+
+    /// Sign a file with Dilithium, or Ed25519 + Dilithium for hybrid keys
     SignFile {
         /// File to sign
         input_file: String,
@@ -323,18 +479,24 @@ pub enum Commands {
         signature_file: String,
         #[arg(long, help = "Signing key name or path")]
         key: String,
+        #[arg(long, help = "Comment stored in the signature, e.g. what was approved")]
+        comment: Option<String>,
     },
 
-    /// Verify a file signature
+    /// Verify a file signature, or every <file>.sig in a directory of signatures
     VerifySignature {
-        /// File to verify
+        /// File (or directory of files) to verify
         input_file: String,
-        /// Signature file
+        /// Signature file (or directory of .sig files)
         signature_file: String,
-        #[arg(long, help = "Public key file or name")]
-        public_key: String,
+        #[arg(long, help = "Require this signer: public key file, contact name or keyring key name (needed for raw signatures)")]
+        public_key: Option<String>,
     },
 
+    /// Manage contacts (other people's public keys)
+    #[command(subcommand)]
+    Contact(ContactCommands),
+
     /// Check SOL balance
     CheckSol {
         #[arg(long)]
@@ -435,9 +597,37 @@ pub enum Commands {
         tier: Option<String>,
         #[arg(long, help = "Skip files that were already uploaded successfully")]
         skip_uploaded: bool,
-        #[arg(long, help = "Encrypt all files with password before upload")]
+        #[arg(long, help = "Encrypt all files before upload with per-file keys wrapped by your master key (see rekey)")]
         encrypt: bool,
-        #[arg(long, help = "Password for encryption (will prompt if not provided)")]
+        #[arg(long, help = "Encrypt with a key derived from this password instead of the master key")]
+        password: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "envelope",
+            help = "With --encrypt, prompt for a password to derive the keys from instead of using the master key"
+        )]
+        use_password: bool,
+        #[arg(long, help = "Encrypt with per-file keys wrapped by your master key (the default for --encrypt)")]
+        envelope: bool,
+        #[arg(long, help = "Compress before upload: zstd or zstd:<level> (1-22)")]
+        compress: Option<String>,
+        #[arg(long, help = "Sign a manifest of the uploaded files with this Dilithium5 or hybrid keyring key and upload it next to them")]
+        sign_manifest: Option<String>,
+    },
+
+    /// Check a signed upload manifest and the files it lists
+    VerifyManifest {
+        /// Manifest file (or remote name with --remote)
+        manifest: String,
+        #[arg(long, help = "Download the manifest from storage instead of reading a local file")]
+        remote: bool,
+        #[arg(long, help = "Check the files in this local directory against the manifest")]
+        dir: Option<String>,
+        #[arg(long, help = "Download and decrypt each listed file and check it against the manifest")]
+        download: bool,
+        #[arg(long, help = "Expected signer: public key file, contact name or keyring key name")]
+        signer: Option<String>,
+        #[arg(long, help = "Password for password-encrypted files (with --download)")]
         password: Option<String>,
     },
 
@@ -476,7 +666,7 @@ pub enum Commands {
         #[arg(long, help = "Filter files by regex pattern")]
         filter: Option<String>,
         
-        #[arg(long, help = "Path to upload log file (default: ~/.pipe-cli-uploads.json)")]
+        #[arg(long, help = "Read this JSON-lines upload log instead of the upload catalog (~/.pipe-cli-uploads.db)")]
         upload_log: Option<String>,
     },
 
@@ -511,7 +701,45 @@ pub enum Commands {
         output_path: String,
     },
 
-    ListUploads,
+    /// List files in remote storage
+    Ls {
+        /// Only list paths starting with this prefix, e.g. photos/
+        prefix: Option<String>,
+        #[arg(short, long, help = "Long format: size and upload date")]
+        long: bool,
+        #[arg(short = 'R', long, help = "List every file under the prefix instead of one level")]
+        recursive: bool,
+        #[arg(long, help = "Show files under the prefix as a tree")]
+        tree: bool,
+        #[arg(long, conflicts_with = "tree", help = "Print the listing as JSON")]
+        json: bool,
+        #[arg(long, help = "Fetch a fresh listing instead of one cached in the last minute")]
+        refresh: bool,
+    },
+
+    /// List recorded uploads with filters, sorting and a totals footer
+    ListUploads {
+        #[arg(long, help = "Only uploads with this status, e.g. SUCCESS or FAIL")]
+        status: Option<String>,
+        #[arg(long, help = "Only remote paths starting with this prefix")]
+        prefix: Option<String>,
+        #[arg(long, help = "Only uploads on or after this date (YYYY-MM-DD)")]
+        since: Option<String>,
+        #[arg(long, help = "Only uploads on or before this date (YYYY-MM-DD)")]
+        until: Option<String>,
+        #[arg(long, help = "Only files at least this large, e.g. 500MB or 1GB")]
+        min_size: Option<String>,
+        #[arg(long, help = "Only files whose Blake3 hash starts with this prefix")]
+        hash: Option<String>,
+        #[arg(long, help = "Sort by size (largest first), date (newest first) or name")]
+        sort: Option<String>,
+        #[arg(long, help = "Show at most this many uploads")]
+        limit: Option<usize>,
+        #[arg(long, default_value = "0", help = "Skip this many uploads")]
+        offset: usize,
+        #[arg(long, default_value = "id,date,status,size,remote,local", help = "Columns to show: id, date, status, size, hash, local, remote, message")]
+        columns: String,
+    },
 
     ExtendStorage {
         #[arg(long)]
@@ -522,13 +750,23 @@ pub enum Commands {
         additional_months: u64,
     },
 
-    /// Verify file integrity using Blake3 hash
+    /// Verify remote files against the Blake3 hashes recorded at upload
     VerifyFile {
         /// File name or Blake3 hash ID
-        file_name: String,
+        #[arg(required_unless_present_any = ["prefix", "all"], conflicts_with_all = ["prefix", "all"])]
+        file_name: Option<String>,
         
         #[arg(long, help = "Treat file_name as Blake3 hash ID")]
         file_id: bool,
+
+        #[arg(long, help = "Verify every catalogued upload under this remote prefix")]
+        prefix: Option<String>,
+
+        #[arg(long, conflicts_with = "prefix", help = "Verify every catalogued upload")]
+        all: bool,
+
+        #[arg(long, default_value = "4", help = "Number of files to verify at once")]
+        parallel: usize,
         
         #[arg(long)]
         user_id: Option<String>,
@@ -536,6 +774,43 @@ pub enum Commands {
         user_app_key: Option<String>,
     },
     
+    /// Verify a random sample of catalogued uploads and record the results
+    Audit {
+        #[arg(long, default_value = "1%", help = "Files to check: a count like 50 or a share like 2%")]
+        sample: String,
+
+        #[arg(long, help = "Only audit uploads under this remote prefix")]
+        prefix: Option<String>,
+
+        #[arg(long, default_value = "4", help = "Number of files to verify at once")]
+        parallel: usize,
+    },
+
+    /// Compare the upload catalog with remote storage and optionally repair either
+    Reconcile {
+        #[arg(long, help = "Only reconcile files under this remote prefix")]
+        prefix: Option<String>,
+
+        #[arg(long, help = "Update the catalog to match remote storage")]
+        fix_catalog: bool,
+
+        #[arg(long, help = "Re-upload missing or mismatched files from their local copies")]
+        reupload: bool,
+
+        #[arg(
+            long,
+            requires = "prefix",
+            help = "Delete remote files with no upload record in this machine's catalog (needs --prefix)"
+        )]
+        delete_orphans: bool,
+
+        #[arg(long, help = "Show what would be fixed without changing anything")]
+        dry_run: bool,
+
+        #[arg(long, short = 'y', help = "Delete orphans without asking")]
+        yes: bool,
+    },
+    
     /// Find uploaded file by local path or hash
     FindUpload {
         /// Local file path or Blake3 hash to search for
@@ -553,10 +828,10 @@ pub enum Commands {
     
     /// Sync files between local and remote storage
     Sync {
-        /// Path to sync (local directory or remote prefix)
+        /// Path to sync (local directory, or remote prefix such as remote:photos)
         path: String,
         
-        /// Optional second path for explicit direction (e.g., remote path for download)
+        /// Optional second path for explicit direction (e.g., remote:backup for upload)
         #[arg(value_name = "DEST_PATH")]
         destination: Option<String>,
         
@@ -587,6 +862,43 @@ pub enum Commands {
         /// Number of parallel operations
         #[arg(long, default_value = "5")]
         parallel: usize,
+
+        /// Compress files before upload: zstd or zstd:<level> (1-22)
+        #[arg(long)]
+        compress: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ContactCommands {
+    /// Add a contact from a contact card or public key files
+    Add {
+        /// Name to refer to the contact by
+        name: String,
+        #[arg(long, help = "Contact card written by 'pipe contact export'")]
+        card: Option<String>,
+        #[arg(long, help = "Kyber1024 (ML-KEM) public key file (raw or 'key-export --public')")]
+        kem_key: Option<String>,
+        #[arg(long, help = "Dilithium5 or hybrid signing public key file (raw, .pubkey or 'key-export --public')")]
+        sign_key: Option<String>,
+        #[arg(long, help = "Mark as verified (fingerprints already compared out of band)")]
+        verified: bool,
+    },
+    /// List contacts with their fingerprints
+    List,
+    /// Mark a contact as verified after comparing fingerprints out of band
+    Verify {
+        name: String,
+    },
+    /// Remove a contact
+    Remove {
+        name: String,
+    },
+    /// Write a contact card with the contact's public keys
+    Export {
+        name: String,
+        /// Output file path
+        output: String,
     },
 }
 
@@ -821,7 +1133,7 @@ impl ServiceDiscoveryCache {
     pub fn new(fallback_endpoint: String) -> Self {
         // Initialize with a time that's definitely in the past to force refresh
         let past_time = Instant::now().checked_sub(Duration::from_secs(3600))
-            .unwrap_or_else(|| Instant::now());
+            .unwrap_or_else(Instant::now);
         
         Self {
             instances: RwLock::new(Vec::new()),
@@ -1191,6 +1503,9 @@ pub struct UploadLogEntry {
     pub file_size: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// PIPE tokens charged for the upload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
 }
 
 /// Calculate Blake3 hash of a file
@@ -1224,7 +1539,7 @@ pub fn append_to_upload_log(
     status: &str,
     message: &str,
 ) -> Result<()> {
-    append_to_upload_log_with_hash(local_path, remote_path, status, message, None, None)
+    append_to_upload_log_with_hash(local_path, remote_path, status, message, None, None, None)
 }
 
 pub fn append_to_upload_log_with_hash(
@@ -1234,13 +1549,8 @@ pub fn append_to_upload_log_with_hash(
     message: &str,
     blake3_hash: Option<String>,
     file_size: Option<u64>,
+    cost: Option<f64>,
 ) -> Result<()> {
-    let log_path = get_upload_log_path();
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)?;
-
     let entry = UploadLogEntry {
         local_path: local_path.to_string(),
         remote_path: remote_path.to_string(),
@@ -1249,18 +1559,25 @@ pub fn append_to_upload_log_with_hash(
         blake3_hash,
         file_size,
         timestamp: Some(chrono::Utc::now()),
+        cost,
     };
 
-    let json_line = serde_json::to_string(&entry)?;
-    writeln!(file, "{}", json_line)?;
+    catalog::Catalog::open_default()?.record(&entry)?;
     Ok(())
 }
 
-/// Read and parse the upload log
+/// Read upload records: from a JSON-lines upload log when a path is given,
+/// otherwise from the upload catalog
 pub fn read_upload_log_entries(log_path: Option<&str>) -> Result<Vec<UploadLogEntry>> {
     let path = match log_path {
         Some(p) => PathBuf::from(p),
-        None => get_upload_log_path(),
+        None => {
+            return Ok(catalog::Catalog::open_default()?
+                .entries()?
+                .into_iter()
+                .map(|e| e.entry)
+                .collect())
+        }
     };
     
     if !path.exists() {
@@ -1279,6 +1596,165 @@ pub fn read_upload_log_entries(log_path: Option<&str>) -> Result<Vec<UploadLogEn
     Ok(entries)
 }
 
+// One line per verified file: verdict, then the remote path
+fn print_verification(verification: &integrity::Verification) {
+    match &verification.result {
+        Ok(integrity::Verdict::Match) => println!("   ✅ match     {}", verification.remote_path),
+        Ok(integrity::Verdict::Mismatch { actual }) => {
+            println!("   ❌ MISMATCH  {}", verification.remote_path);
+            println!("      recorded {}", verification.expected.as_deref().unwrap_or("-"));
+            println!("      remote   {}", actual);
+        }
+        Ok(integrity::Verdict::Unknown(reason)) => {
+            println!("   ❔ unknown   {} ({})", verification.remote_path, reason)
+        }
+        Err(e) => println!("   ⚠️  error     {}: {}", verification.remote_path, e),
+    }
+}
+
+// Summary of a verification run; an error if any file mismatched or could not be read
+fn report_verifications(results: &[integrity::Verification]) -> Result<()> {
+    let count = |f: fn(&integrity::Verification) -> bool| results.iter().filter(|v| f(v)).count();
+    let matched = count(|v| matches!(v.result, Ok(integrity::Verdict::Match)));
+    let mismatched = count(|v| matches!(v.result, Ok(ref verdict) if verdict.is_mismatch()));
+    let unknown = count(|v| matches!(v.result, Ok(integrity::Verdict::Unknown(_))));
+    let errors = count(|v| v.result.is_err());
+    let failed = count(integrity::Verification::failed);
+
+    if results.len() > 1 {
+        println!(
+            "\n📊 {} checked: {} match, {} mismatch, {} unknown, {} error(s)",
+            results.len(),
+            matched,
+            mismatched,
+            unknown,
+            errors
+        );
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} file(s) failed verification", failed));
+    }
+    Ok(())
+}
+
+// One line of the audit history: date, result, path and detail
+fn print_audit_record(record: &catalog::AuditRecord) {
+    println!(
+        "      {}  {}  {}{}",
+        record
+            .checked_at
+            .map_or("-".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string()),
+        record.result,
+        record.remote_path,
+        record.detail.as_deref().map_or(String::new(), |d| format!(" ({})", d))
+    );
+}
+
+// One line of 'pipe ls --long': size, upload date and name
+fn format_listing_line(
+    size: u64,
+    uploaded_at: Option<chrono::DateTime<chrono::Utc>>,
+    name: &str,
+) -> String {
+    format!(
+        "{:>10}  {:16}  {}",
+        sync::format_file_size(size),
+        uploaded_at.map_or("-".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string()),
+        name
+    )
+}
+
+// Columns of 'list-uploads'
+#[derive(Clone, Copy)]
+enum UploadColumn {
+    Id,
+    Date,
+    Status,
+    Size,
+    Hash,
+    Local,
+    Remote,
+    Message,
+}
+
+impl UploadColumn {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "id" => Ok(UploadColumn::Id),
+            "date" => Ok(UploadColumn::Date),
+            "status" => Ok(UploadColumn::Status),
+            "size" => Ok(UploadColumn::Size),
+            "hash" => Ok(UploadColumn::Hash),
+            "local" => Ok(UploadColumn::Local),
+            "remote" => Ok(UploadColumn::Remote),
+            "message" => Ok(UploadColumn::Message),
+            _ => Err(anyhow!(
+                "Unknown column '{}'. Use: id, date, status, size, hash, local, remote, message",
+                name
+            )),
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            UploadColumn::Id => "ID",
+            UploadColumn::Date => "DATE",
+            UploadColumn::Status => "STATUS",
+            UploadColumn::Size => "SIZE",
+            UploadColumn::Hash => "BLAKE3",
+            UploadColumn::Local => "LOCAL",
+            UploadColumn::Remote => "REMOTE",
+            UploadColumn::Message => "MESSAGE",
+        }
+    }
+
+    fn value(self, record: &catalog::CatalogEntry) -> String {
+        let entry = &record.entry;
+        match self {
+            UploadColumn::Id => record.id.to_string(),
+            UploadColumn::Date => entry
+                .timestamp
+                .map_or("-".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string()),
+            UploadColumn::Status => entry.status.clone(),
+            UploadColumn::Size => entry.file_size.map_or("-".to_string(), sync::format_file_size),
+            UploadColumn::Hash => entry.blake3_hash.clone().unwrap_or_else(|| "-".to_string()),
+            UploadColumn::Local => entry.local_path.clone(),
+            UploadColumn::Remote => entry.remote_path.clone(),
+            UploadColumn::Message => entry.message.clone(),
+        }
+    }
+}
+
+// Parse a size such as 1048576, 500KB, 1.5GB (binary units, like the sizes printed)
+fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return Err(anyhow!("Invalid size '{}', expected e.g. 500MB or 1GB", value)),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size '{}', expected e.g. 500MB or 1GB", value))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+// Parse a YYYY-MM-DD date as midnight UTC
+fn parse_date_arg(date: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    Ok(chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid date '{}', expected YYYY-MM-DD", date))?
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc())
+}
+
 /// Filter upload log entries by prefix and status
 pub fn filter_entries_for_download<'a>(
     entries: &'a [UploadLogEntry],
@@ -1368,6 +1844,72 @@ async fn improved_download_file(
     improved_download_file_with_auth(client, base_url, &creds, file_name, output_path).await
 }
 
+// First `len` bytes of a remote file. Asks for a byte range; if the server
+// ignores it and streams the whole file, the stream is dropped after `len` bytes.
+async fn fetch_file_head(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+    file_name: &str,
+    len: usize,
+) -> Result<Vec<u8>> {
+    let request = client
+        .get(format!("{}/download-stream", base_url))
+        .query(&[("file_name", file_name)])
+        .header(reqwest::header::RANGE, format!("bytes=0-{}", len - 1));
+    let mut resp = add_auth_headers(request, creds, false).send().await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("Failed to read '{}': {}", file_name, resp.status()));
+    }
+
+    let mut head = Vec::with_capacity(len);
+    while head.len() < len {
+        match resp.chunk().await? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    head.truncate(len);
+    Ok(head)
+}
+
+// Delete a remote file; returns the server's confirmation message
+async fn delete_remote_file(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+    file_name: &str,
+) -> Result<String> {
+    // Add auth headers including CSRF token for this state-changing operation
+    let mut request = add_auth_headers(client.post(format!("{}/deleteFile", base_url)), creds, true);
+
+    // Use JWT auth if available, otherwise fall back to legacy
+    if creds.auth_tokens.is_some() {
+        // With JWT, send only file name - server will get user info from token
+        request = request.json(&serde_json::json!({ "file_name": file_name }));
+    } else {
+        // Legacy auth via request body
+        request = request.json(&DeleteFileRequest {
+            user_id: creds.user_id.clone(),
+            user_app_key: creds.user_app_key.clone(),
+            file_name: file_name.to_string(),
+        });
+    }
+
+    let resp = request.send().await?;
+    listing::invalidate_cache();
+    let status = resp.status();
+    let text_body = resp.text().await?;
+    if !status.is_success() {
+        return Err(anyhow!(
+            "Delete file failed. Status = {}, Body = {}",
+            status,
+            text_body
+        ));
+    }
+    Ok(serde_json::from_str::<DeleteFileResponse>(&text_body)?.message)
+}
+
 async fn improved_download_file_with_auth(
     client: &Client,
     base_url: &str,
@@ -1375,7 +1917,40 @@ async fn improved_download_file_with_auth(
     file_name: &str,
     output_path: &str,
 ) -> Result<()> {
-    improved_download_file_with_auth_and_options(client, base_url, creds, file_name, output_path, false).await
+    improved_download_file_with_auth_and_options(client, base_url, creds, file_name, output_path, false).await?;
+    let output_path = if Path::new(output_path).is_dir() {
+        Path::new(output_path).join(file_name)
+    } else {
+        PathBuf::from(output_path)
+    };
+    decompress_download(file_name, &output_path)
+}
+
+// Files uploaded with --compress (and no encryption) carry a PIPE-ZST header.
+// A plain upload that merely starts with the magic is recognised by the catalog
+// recording the hash of the stored bytes, and is left as downloaded.
+fn decompress_download(remote_name: &str, path: &Path) -> Result<()> {
+    if !compression::is_compressed_file(std::fs::File::open(path)?)? {
+        return Ok(());
+    }
+    let stored_hash = signature::hash_file(path)?;
+    let uploaded_as_is = catalog::Catalog::open_default()
+        .and_then(|catalog| catalog.find_by_remote_path(remote_name))
+        .map(|records| {
+            records.iter().any(|record| {
+                record.entry.status == "SUCCESS"
+                    && record.entry.blake3_hash.as_deref() == Some(stored_hash.as_str())
+            })
+        })
+        .unwrap_or(false);
+    if uploaded_as_is {
+        return Ok(());
+    }
+
+    if compression::decompress_file_in_place(path)? {
+        println!("Decompressed '{}'", remote_name);
+    }
+    Ok(())
 }
 
 async fn improved_download_file_with_auth_and_options(
@@ -1752,6 +2327,7 @@ async fn upload_file_with_auth(
     }
 
     let resp = request.body(body).send().await?;
+    listing::invalidate_cache();
 
     let status = resp.status();
     let text_body = resp.text().await?;
@@ -1854,6 +2430,7 @@ async fn upload_file_priority(
         .body(body)
         .send()
         .await?;
+    listing::invalidate_cache();
 
     let status = resp.status();
     let text_body = resp.text().await?;
@@ -1958,6 +2535,7 @@ async fn upload_file_priority_with_auth(
     }
 
     let resp = request.body(body).send().await?;
+    listing::invalidate_cache();
 
     let status = resp.status();
     let text_body = resp.text().await?;
@@ -2079,106 +2657,6 @@ struct DirectoryUploadProgress {
     progress_bar: Arc<ProgressBar>,
 }
 
-// Helper function to handle quantum-encrypted file download
-#[allow(dead_code)]
-async fn download_file_with_quantum_decryption(
-    client: &Client,
-    base_url: &str,
-    creds: &SavedCredentials,
-    file_name: &str,
-    output_path: &str,
-    decrypt_password: bool,
-    password: Option<String>,
-) -> Result<()> {
-    download_file_with_quantum_decryption_and_options(client, base_url, creds, file_name, output_path, decrypt_password, password, false).await
-}
-
-#[allow(clippy::too_many_arguments)]
-async fn download_file_with_quantum_decryption_and_options(
-    client: &Client,
-    base_url: &str,
-    creds: &SavedCredentials,
-    file_name: &str,
-    output_path: &str,
-    decrypt_password: bool,
-    password: Option<String>,
-    use_legacy: bool,
-) -> Result<()> {
-    use crate::quantum::decrypt_and_verify;
-    use crate::quantum_keyring::load_quantum_keypair;
-    
-    println!("🔐 Downloading quantum-encrypted file...");
-    
-    // Download the quantum-encrypted file
-    let temp_path = format!("{}.qenc.tmp", output_path);
-    improved_download_file_with_auth_and_options(client, base_url, creds, file_name, &temp_path, use_legacy).await?;
-    
-    // Read the downloaded file
-    let quantum_encrypted_data = std::fs::read(&temp_path)?;
-    println!("  Downloaded size: {} bytes", quantum_encrypted_data.len());
-    
-    // Determine the original filename (remove .qenc extension if present)
-    let original_filename = if let Some(stripped) = file_name.strip_suffix(".qenc") {
-        stripped
-    } else {
-        file_name
-    };
-    
-    // Load quantum keys
-    let quantum_keys = match load_quantum_keypair(original_filename) {
-        Ok(keys) => keys,
-        Err(e) => {
-            eprintln!("⚠️  Could not load quantum keys for {}: {}", original_filename, e);
-            eprintln!("    Make sure you have the quantum keys from when this file was uploaded.");
-            let _ = std::fs::remove_file(&temp_path);
-            return Err(anyhow!("Quantum keys not found"));
-        }
-    };
-    
-    // Decrypt and verify using quantum crypto
-    println!("  Decrypting with quantum-resistant algorithms...");
-    let signed_data = decrypt_and_verify(
-        &quantum_encrypted_data,
-        &quantum_keys.kyber_secret,
-    )?;
-    
-    println!("  ✅ Signature verified");
-    println!("  Decrypted size: {} bytes", signed_data.data.len());
-    
-    // If password decryption is also needed
-    let final_data = if decrypt_password {
-        let password = match password {
-            Some(p) => p,
-            None => rpassword::prompt_password("Enter decryption password: ")?,
-        };
-        
-        // Extract nonce and encrypted data
-        if signed_data.data.len() < 12 {
-            return Err(anyhow!("Invalid encrypted data: too short"));
-        }
-        let (nonce_bytes, encrypted_data) = signed_data.data.split_at(12);
-        let mut nonce = [0u8; 12];
-        nonce.copy_from_slice(nonce_bytes);
-        
-        // Decrypt with password
-        // Use a fixed salt for quantum context
-        let quantum_salt = b"pipe-quantum-v1-salt-2024";
-        let decryption_key = crate::encryption::derive_key_from_password(&password, quantum_salt)?;
-        crate::encryption::decrypt_data(encrypted_data, &decryption_key, &nonce)?
-    } else {
-        signed_data.data
-    };
-    
-    // Write the final decrypted file
-    std::fs::write(output_path, &final_data)?;
-    
-    // Clean up temp file
-    let _ = std::fs::remove_file(&temp_path);
-    
-    println!("✅ Quantum-encrypted file downloaded and decrypted to: {}", output_path);
-    Ok(())
-}
-
 // Helper function to handle file download with optional decryption
 async fn download_file_with_decryption(
     client: &Client,
@@ -2189,9 +2667,19 @@ async fn download_file_with_decryption(
     decrypt: bool,
     password: Option<String>,
 ) -> Result<()> {
-    download_file_with_decryption_and_options(client, base_url, creds, file_name, output_path, decrypt, password, false).await
+    let options = DecryptOptions {
+        password,
+        key: None,
+        quantum_password: decrypt,
+        quantum: false,
+    };
+    download_file_with_decryption_and_options(client, base_url, creds, file_name, output_path, decrypt, options, false)
+        .await
+        .map(|_| ())
 }
 
+// Download a file and decrypt it according to its detected format. Files that
+// are not encrypted are saved as-is. Returns the path the file was saved to.
 #[allow(clippy::too_many_arguments)]
 async fn download_file_with_decryption_and_options(
     client: &Client,
@@ -2200,56 +2688,432 @@ async fn download_file_with_decryption_and_options(
     file_name: &str,
     output_path: &str,
     decrypt: bool,
-    password: Option<String>,
+    options: DecryptOptions,
     use_legacy: bool,
-) -> Result<()> {
-    let actual_file_name = if decrypt && !file_name.ends_with(".enc") {
+) -> Result<PathBuf> {
+    let has_encrypted_extension = [".enc", ".qenc", ".pq"]
+        .iter()
+        .any(|ext| file_name.ends_with(ext));
+    let actual_file_name = if decrypt && !has_encrypted_extension {
         format!("{}.enc", file_name)
     } else {
         file_name.to_string()
     };
 
-    if decrypt {
-        // Download to temporary file first
-        let temp_path = format!("{}.tmp", output_path);
-        improved_download_file_with_auth_and_options(client, base_url, creds, &actual_file_name, &temp_path, use_legacy)
-            .await?;
+    // Download to temporary file first (inside the directory if one was given)
+    let output_is_dir = Path::new(output_path).is_dir();
+    let temp_path = if output_is_dir {
+        Path::new(output_path)
+            .join(format!(".pipe-download-{}.tmp", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .to_string()
+    } else {
+        format!("{}.tmp", output_path)
+    };
+    improved_download_file_with_auth_and_options(client, base_url, creds, &actual_file_name, &temp_path, use_legacy)
+        .await?;
+    decompress_download(&actual_file_name, Path::new(&temp_path))?;
+    if crate::encryption::detect_format(std::fs::File::open(&temp_path)?)?
+        == crate::encryption::EncryptionFormat::Envelope
+    {
+        apply_remote_key_block(client, base_url, creds, &actual_file_name, Path::new(&temp_path)).await?;
+    }
 
-        // Get password if not provided
-        let password = match password {
-            Some(p) => p,
-            None => rpassword::prompt_password("Enter decryption password: ")?,
-        };
+    match decrypt_detected_file(
+        Path::new(&temp_path),
+        Path::new(output_path),
+        &actual_file_name,
+        options,
+        true,
+        None,
+    )
+    .await
+    {
+        Ok(Some((final_path, metadata))) => {
+            // Clean up temporary file
+            let _ = std::fs::remove_file(&temp_path);
+            println!("Decrypted to {}", final_path.display());
+            print_restored_metadata(&final_path, metadata.as_ref());
+            Ok(final_path)
+        }
+        Ok(None) => {
+            if decrypt {
+                println!("⚠️  '{}' is not encrypted; saved as-is", actual_file_name);
+            }
+            let final_path = if output_is_dir {
+                let name = Path::new(&actual_file_name)
+                    .file_name()
+                    .map(|n| n.to_os_string())
+                    .unwrap_or_else(|| "download".into());
+                Path::new(output_path).join(name)
+            } else {
+                PathBuf::from(output_path)
+            };
+            std::fs::rename(&temp_path, &final_path)?;
+            Ok(final_path)
+        }
+        Err(e) => {
+            // Clean up temporary file
+            let _ = std::fs::remove_file(&temp_path);
+            Err(anyhow!("Decryption failed: {}", e))
+        }
+    }
+}
 
-        // Decrypt the file
-        let input_file = std::fs::File::open(&temp_path)?;
-        let output_file = std::fs::File::create(output_path)?;
+// Key sources supplied on the command line for auto-detected decryption
+struct DecryptOptions {
+    password: Option<String>,
+    key: Option<String>,
+    // Whether .qenc payloads carry the inner password layer from `--encrypt`;
+    // the container itself has no marker for it
+    quantum_password: bool,
+    // Whether --quantum was given, so a sign-and-encrypt container is expected
+    // even without a .qenc name
+    quantum: bool,
+}
 
-        println!("Decrypting to {}...", output_path);
+// Key material for a detected format
+enum DecryptKey {
+    Password(String),
+    Aes(crate::encryption::EncryptionKey),
+    Kyber(Vec<u8>),
+    Envelope(envelope::UnlockedMasterKeys),
+    QuantumSigned {
+        kyber_secret: Vec<u8>,
+        password: Option<String>,
+    },
+}
 
-        match crate::encryption::decrypt_file_with_password(
-            input_file,
-            output_file,
-            &password,
-            None,
-        )
+// Detect the format of a local file and decrypt it with the matching key source.
+// Returns None, leaving the file untouched, when it is not encrypted. Unless
+// `overwrite` is set, a restored name must not clash with an existing file.
+async fn decrypt_detected_file(
+    input: &Path,
+    output: &Path,
+    file_id: &str,
+    options: DecryptOptions,
+    overwrite: bool,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<Option<(PathBuf, Option<crate::encryption::FileMetadata>)>> {
+    use crate::encryption::EncryptionFormat;
+
+    let expect_quantum = options.quantum || file_id.ends_with(".qenc");
+    let format = crate::encryption::detect_format_expecting(std::fs::File::open(input)?, expect_quantum)?;
+    if matches!(format, EncryptionFormat::Compressed | EncryptionFormat::Plain) {
+        return Ok(None);
+    }
+
+    println!("Detected format: {}", format);
+    let key = resolve_decrypt_key(format, file_id, options)?;
+    decrypt_file_to_path(input, output, file_id, &key, overwrite, progress_callback)
         .await
-        {
-            Ok(_) => {
-                // Clean up temporary file
-                let _ = std::fs::remove_file(&temp_path);
-                Ok(())
+        .map(Some)
+}
+
+// Pick the key source for a detected format: prompt for a password, load a key
+// from the keyring, or load the quantum keypair saved when the file was uploaded
+fn resolve_decrypt_key(
+    format: crate::encryption::EncryptionFormat,
+    file_id: &str,
+    options: DecryptOptions,
+) -> Result<DecryptKey> {
+    use crate::encryption::EncryptionFormat;
+
+    let DecryptOptions {
+        password,
+        key,
+        quantum_password,
+        ..
+    } = options;
+
+    match format {
+        EncryptionFormat::Password => {
+            let password = match password {
+                Some(p) => p,
+                None => rpassword::prompt_password("Enter decryption password: ")?,
+            };
+            Ok(DecryptKey::Password(password))
+        }
+        EncryptionFormat::Key => {
+            let name = key.ok_or_else(|| {
+                anyhow!("File is encrypted with a key; pass --key <name> to use one from the keyring")
+            })?;
+            let material = load_keyring_key_material(
+                &name,
+                &[keyring::KeyAlgorithm::Aes256],
+                keyring::KeyUse::Decrypt,
+            )?;
+            let key = material
+                .symmetric_key
+                .ok_or_else(|| anyhow!("Key '{}' has no symmetric key material", name))?;
+            Ok(DecryptKey::Aes(crate::encryption::EncryptionKey { key }))
+        }
+        EncryptionFormat::Envelope => {
+            let path = envelope::MasterKeyStore::default_path()?;
+            if !path.exists() {
+                return Err(anyhow!(
+                    "File is envelope-encrypted but no master key store exists at {}",
+                    path.display()
+                ));
             }
-            Err(e) => {
-                // Clean up temporary file
-                let _ = std::fs::remove_file(&temp_path);
-                Err(anyhow!("Decryption failed: {}. Wrong password?", e))
+            let store = envelope::MasterKeyStore::load_from_file(&path)?;
+            let (_, unlocked) = unlock_master_keys(&store)?;
+            Ok(DecryptKey::Envelope(unlocked))
+        }
+        EncryptionFormat::Kyber => Ok(DecryptKey::Kyber(load_kyber_secret(key.as_deref(), file_id)?)),
+        EncryptionFormat::QuantumSigned => {
+            let kyber_secret = load_kyber_secret(key.as_deref(), file_id)?;
+            let password = match password {
+                Some(p) => Some(p),
+                None if quantum_password => {
+                    Some(rpassword::prompt_password("Enter decryption password: ")?)
+                }
+                None => None,
+            };
+            Ok(DecryptKey::QuantumSigned {
+                kyber_secret,
+                password,
+            })
+        }
+        EncryptionFormat::Compressed | EncryptionFormat::Plain => {
+            Err(anyhow!("File is not encrypted ({})", format))
+        }
+    }
+}
+
+// Load a key from the keyring after checking its algorithm, prompting for the
+// keyring password. Expired keys are refused for new encryptions.
+fn load_keyring_key_material(
+    name: &str,
+    allowed: &[keyring::KeyAlgorithm],
+    key_use: keyring::KeyUse,
+) -> Result<keyring::KeyMaterial> {
+    let keyring_path = keyring::Keyring::default_path()?;
+    let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+
+    let algorithm = keyring
+        .get_key(name)
+        .ok_or_else(|| anyhow!("Key '{}' not found in keyring", name))?
+        .algorithm
+        .clone();
+    if !allowed.contains(&algorithm) {
+        return Err(anyhow!(
+            "Key '{}' is a {} key and cannot be used here (need {})",
+            name,
+            algorithm,
+            allowed.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(" or ")
+        ));
+    }
+
+    let password = rpassword::prompt_password("Enter keyring password: ")?;
+    let material = keyring.get_key_material(name, &password, key_use)?;
+
+    // Update keyring with usage stats
+    keyring.save_to_file(&keyring_path)?;
+    Ok(material)
+}
+
+// Kyber secret key from the keyring if --key was given, otherwise from the
+// quantum keypair saved for this file
+fn load_kyber_secret(key_name: Option<&str>, file_id: &str) -> Result<Vec<u8>> {
+    if let Some(name) = key_name {
+        let material = load_keyring_key_material(
+            name,
+            &[keyring::KeyAlgorithm::Kyber1024, keyring::KeyAlgorithm::KyberAes],
+            keyring::KeyUse::Decrypt,
+        )?;
+        return material
+            .private_key
+            .clone()
+            .ok_or_else(|| anyhow!("Key '{}' has no secret key material", name));
+    }
+
+    let file_id = strip_encrypted_extension(file_id);
+    let keyring_path = keyring::Keyring::default_path()?;
+    let keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+    if keyring.has_quantum_keys(file_id) {
+        let password = rpassword::prompt_password("Enter keyring password: ")?;
+        return Ok(keyring.quantum_keypair(file_id, &password)?.kyber_secret.clone());
+    }
+
+    // Plaintext keypairs written by older versions
+    let keys = crate::quantum_keyring::load_quantum_keypair(file_id).map_err(|_| {
+        anyhow!(
+            "No quantum keypair found for '{}'. Pass --key <name> to use a Kyber key from the keyring",
+            file_id
+        )
+    })?;
+    eprintln!("⚠️  Using an unencrypted quantum keypair. Run 'pipe quantum-keys-migrate' to move it into the keyring.");
+    Ok(keys.kyber_secret.clone())
+}
+
+// Strip the extension added by encrypted uploads (.enc, .qenc, .pq)
+fn strip_encrypted_extension(name: &str) -> &str {
+    [".enc", ".qenc", ".pq"]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(name)
+}
+
+// Decrypt a sign-and-encrypt (.qenc) container, verifying its Dilithium signature.
+// The optional password removes the inner layer added by `--encrypt`.
+fn decrypt_quantum_container<W: std::io::Write>(
+    input: &Path,
+    mut output: W,
+    kyber_secret: &[u8],
+    password: Option<&str>,
+) -> Result<()> {
+    let data = std::fs::read(input)?;
+    let signed_data = crate::quantum::decrypt_and_verify(&data, kyber_secret)?;
+    println!("  ✅ Signature verified");
+
+    let plaintext = match password {
+        Some(password) => {
+            // Extract nonce and encrypted data
+            if signed_data.data.len() < 12 {
+                return Err(anyhow!("Invalid encrypted data: too short"));
             }
+            let (nonce_bytes, encrypted_data) = signed_data.data.split_at(12);
+            let mut nonce = [0u8; 12];
+            nonce.copy_from_slice(nonce_bytes);
+
+            // Quantum uploads use a fixed salt for the password layer
+            let quantum_salt = b"pipe-quantum-v1-salt-2024";
+            let decryption_key = crate::encryption::derive_key_from_password(password, quantum_salt)?;
+            crate::encryption::decrypt_data(encrypted_data, &decryption_key, &nonce)?
         }
+        None => signed_data.data,
+    };
+
+    output.write_all(&plaintext)?;
+    Ok(())
+}
+
+// Decrypt a file into a temp file next to its destination, then move it into
+// place. If `output` is a directory, the original file name from the header is
+// used, falling back to `file_id` without its encrypted extension. Original
+// timestamps and permissions are restored when known.
+async fn decrypt_file_to_path(
+    input: &Path,
+    output: &Path,
+    file_id: &str,
+    key: &DecryptKey,
+    overwrite: bool,
+    progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+) -> Result<(PathBuf, Option<crate::encryption::FileMetadata>)> {
+    let output_is_dir = output.is_dir();
+    let dest_dir = if output_is_dir {
+        output.to_path_buf()
     } else {
-        // Regular download without decryption
-        improved_download_file_with_auth_and_options(client, base_url, creds, &actual_file_name, output_path, use_legacy)
+        output
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+    };
+    let temp_path = dest_dir.join(format!(".pipe-decrypt-{}.tmp", uuid::Uuid::new_v4()));
+
+    let input_file = std::fs::File::open(input)?;
+    let output_file = std::fs::File::create(&temp_path)?;
+    let result = match key {
+        DecryptKey::Password(password) => {
+            crate::encryption::decrypt_file_with_password(
+                input_file,
+                output_file,
+                password,
+                progress_callback,
+            )
+            .await
+        }
+        DecryptKey::Aes(key) => {
+            crate::encryption::decrypt_file_with_key(input_file, output_file, key, progress_callback)
+                .await
+        }
+        DecryptKey::Envelope(unlocked) => {
+            crate::encryption::decrypt_file_with_master_keys(
+                input_file,
+                output_file,
+                unlocked.keys(),
+                progress_callback,
+            )
+            .await
+        }
+        DecryptKey::Kyber(secret) => {
+            crate::quantum::decrypt_file_with_kyber(
+                input_file,
+                output_file,
+                secret,
+                progress_callback,
+            )
             .await
+        }
+        DecryptKey::QuantumSigned {
+            kyber_secret,
+            password,
+        } => decrypt_quantum_container(input, output_file, kyber_secret, password.as_deref())
+            .map(|_| None),
+    };
+    let metadata = match result {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+
+    let final_path = if output_is_dir {
+        let name = metadata
+            .as_ref()
+            .and_then(|m| m.safe_name())
+            .unwrap_or_else(|| {
+                let name = Path::new(file_id)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "decrypted".to_string());
+                strip_encrypted_extension(&name).to_string()
+            });
+        output.join(name)
+    } else {
+        output.to_path_buf()
+    };
+
+    if input.canonicalize().ok() == final_path.canonicalize().ok() {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(anyhow!(
+            "Refusing to overwrite the encrypted input '{}'; specify an output path",
+            input.display()
+        ));
+    }
+
+    // A restored name may collide with an unrelated file in the directory
+    if output_is_dir && !overwrite && final_path.exists() {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(anyhow!(
+            "'{}' already exists; pass --force to overwrite it or specify an output path",
+            final_path.display()
+        ));
+    }
+
+    if let Err(e) = std::fs::rename(&temp_path, &final_path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+
+    if let Some(ref m) = metadata {
+        m.apply_to(&final_path)?;
+    }
+
+    Ok((final_path, metadata))
+}
+
+// Report what was verified and restored from the encrypted metadata block
+fn print_restored_metadata(path: &Path, metadata: Option<&crate::encryption::FileMetadata>) {
+    if let Some(m) = metadata {
+        println!("✅ Integrity verified (Blake3 {})", m.blake3);
+        println!("   Restored '{}' ({} bytes)", path.display(), m.size);
+        if let Some(modified) = m.modified.and_then(|secs| DateTime::from_timestamp(secs, 0)) {
+            println!("   Modified: {}", modified.format("%Y-%m-%d %H:%M:%S UTC"));
+        }
     }
 }
 
@@ -2268,9 +3132,16 @@ pub async fn download_directory(
     filter: Option<String>,
     upload_log_path: Option<&str>,
 ) -> Result<()> {
-    // 1. Read upload log
-    let entries = read_upload_log_entries(upload_log_path)?;
-    if entries.is_empty() {
+    // 1. Read upload log, or query the catalog
+    let entries = match upload_log_path {
+        Some(path) => read_upload_log_entries(Some(path))?,
+        None => catalog::Catalog::open_default()?
+            .successful_with_prefix(remote_prefix)?
+            .into_iter()
+            .map(|e| e.entry)
+            .collect(),
+    };
+    if entries.is_empty() && upload_log_path.is_some() {
         return Err(anyhow!("No upload log found. Have you uploaded any files?"));
     }
     
@@ -2422,6 +3293,7 @@ mod download_directory_tests {
                 blake3_hash: None,
                 file_size: None,
                 timestamp: None,
+                cost: None,
             },
             UploadLogEntry {
                 local_path: "/home/user/photos/vacation/sunset.jpg".to_string(),
@@ -2431,6 +3303,7 @@ mod download_directory_tests {
                 blake3_hash: None,
                 file_size: None,
                 timestamp: None,
+                cost: None,
             },
             UploadLogEntry {
                 local_path: "/home/user/photos/family/portrait.jpg".to_string(),
@@ -2440,6 +3313,7 @@ mod download_directory_tests {
                 blake3_hash: None,
                 file_size: None,
                 timestamp: None,
+                cost: None,
             },
             UploadLogEntry {
                 local_path: "/home/user/docs/report.pdf".to_string(),
@@ -2449,6 +3323,7 @@ mod download_directory_tests {
                 blake3_hash: None,
                 file_size: None,
                 timestamp: None,
+                cost: None,
             },
             UploadLogEntry {
                 local_path: "/home/user/docs/summary.pdf".to_string(),
@@ -2458,6 +3333,7 @@ mod download_directory_tests {
                 blake3_hash: None,
                 file_size: None,
                 timestamp: None,
+                cost: None,
             },
         ];
 
@@ -2616,6 +3492,7 @@ mod download_directory_tests {
                 blake3_hash: None,
                 file_size: None,
                 timestamp: None,
+                cost: None,
             },
             UploadLogEntry {
                 local_path: "TEST.TXT".to_string(),
@@ -2625,6 +3502,7 @@ mod download_directory_tests {
                 blake3_hash: None,
                 file_size: None,
                 timestamp: None,
+                cost: None,
             },
         ];
         
@@ -2641,48 +3519,92 @@ mod download_directory_tests {
     }
 }
 
+// Keys and passwords for a quantum upload, resolved once before the retry loop
+// so a retry neither prompts again nor generates another keypair
+struct QuantumUploadKeys {
+    keyring_path: PathBuf,
+    keyring: keyring::Keyring,
+    keyring_password: String,
+    identity: Option<String>,
+    keys: crate::quantum_keyring::QuantumKeyPair,
+    // Inner password layer from --encrypt
+    password: Option<String>,
+}
+
+impl QuantumUploadKeys {
+    fn resolve(
+        file_name_in_bucket: &str,
+        encrypt: bool,
+        password: Option<String>,
+        identity: Option<String>,
+    ) -> Result<Self> {
+        use crate::quantum_keyring::generate_quantum_keypair;
+
+        // Keypairs are kept encrypted in the keyring
+        let keyring_path = keyring::Keyring::default_path()?;
+        let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+        let keyring_password = keyring_password_for_new_key(&mut keyring)?;
+
+        // Reuse the identity keypair if one was given, otherwise generate one for this file
+        let keys = match identity.as_deref() {
+            Some(name) => keyring.quantum_identity(name, &keyring_password)?,
+            None => generate_quantum_keypair(file_name_in_bucket)?,
+        };
+
+        let password = if encrypt {
+            Some(match password {
+                Some(p) => p,
+                None => prompt_new_password("Enter encryption password: ", "Confirm encryption password: ")?,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            keyring_path,
+            keyring,
+            keyring_password,
+            identity,
+            keys,
+            password,
+        })
+    }
+
+    // Record the keypair, or the identity it came from, once the file is uploaded
+    fn save(mut self, file_name_in_bucket: &str) -> Result<()> {
+        match self.identity.as_deref() {
+            Some(name) => self.keyring.link_quantum_identity(file_name_in_bucket, name)?,
+            None => self.keyring.store_quantum_keypair(&self.keys, &self.keyring_password)?,
+        }
+        self.keyring.save_to_file(&self.keyring_path)?;
+        println!("🔑 Quantum keys saved for file: {}", file_name_in_bucket);
+        Ok(())
+    }
+}
+
 // Helper function to handle quantum encrypted file upload
-#[allow(clippy::too_many_arguments)]
 async fn upload_file_with_quantum_encryption(
     client: &Client,
     file_path: &Path,
     full_url: &str,
     file_name_in_bucket: &str,
     creds: &SavedCredentials,
-    encrypt: bool,
-    password: Option<String>,
-    _key: Option<String>,
+    quantum_keys: &QuantumUploadKeys,
 ) -> Result<(String, f64)> {
     use crate::quantum::sign_and_encrypt;
-    use crate::quantum_keyring::{generate_quantum_keypair, save_quantum_keypair};
     
     println!("🔐 Using quantum-resistant encryption (Kyber + Dilithium)...");
     
-    // Generate quantum keypair
-    let quantum_keys = generate_quantum_keypair(file_name_in_bucket)?;
-    
     // Read the file
     let file_data = std::fs::read(file_path)?;
     println!("  Original file size: {} bytes", file_data.len());
     
     // If password encryption is also requested, encrypt with password first
-    let data_to_quantum_encrypt = if encrypt {
-        let password = match password {
-            Some(p) => p,
-            None => {
-                let password = rpassword::prompt_password("Enter encryption password: ")?;
-                let confirm = rpassword::prompt_password("Confirm encryption password: ")?;
-                if password != confirm {
-                    return Err(anyhow!("Passwords do not match"));
-                }
-                password
-            }
-        };
-        
+    let data_to_quantum_encrypt = if let Some(ref password) = quantum_keys.password {
         // Encrypt with password first
         // Use a fixed salt for quantum context
         let quantum_salt = b"pipe-quantum-v1-salt-2024";
-        let encryption_key = crate::encryption::derive_key_from_password(&password, quantum_salt)?;
+        let encryption_key = crate::encryption::derive_key_from_password(password, quantum_salt)?;
         let (encrypted, nonce) = crate::encryption::encrypt_data(&file_data, &encryption_key)?;
         
         // Combine nonce and encrypted data
@@ -2694,25 +3616,27 @@ async fn upload_file_with_quantum_encryption(
     };
     
     // Apply quantum encryption (sign-then-encrypt)
+    let keys = &quantum_keys.keys;
     let quantum_encrypted = sign_and_encrypt(
         &data_to_quantum_encrypt,
-        &quantum_keys.dilithium_secret,
-        &quantum_keys.dilithium_public,
-        &quantum_keys.kyber_public,
+        &keys.dilithium_secret,
+        &keys.dilithium_public,
+        &keys.kyber_public,
     )?;
     
     println!("  Quantum encrypted size: {} bytes", quantum_encrypted.len());
     
-    // Save the quantum keys
-    save_quantum_keypair(&quantum_keys)?;
-    
     // Create temporary file for upload
     let temp_path = file_path.with_extension("qenc.tmp");
     std::fs::write(&temp_path, &quantum_encrypted)?;
     
     // Update filename to indicate quantum encryption
     let quantum_filename = format!("{}.qenc", file_name_in_bucket);
-    let full_url_quantum = full_url.replace(file_name_in_bucket, &quantum_filename);
+    let full_url_quantum = full_url.replacen(
+        &format!("file_name={}", utf8_percent_encode(file_name_in_bucket, QUERY_ENCODE_SET)),
+        &format!("file_name={}", utf8_percent_encode(&quantum_filename, QUERY_ENCODE_SET)),
+        1,
+    );
     
     // Upload the quantum-encrypted file
     let result = upload_file_with_shared_progress(
@@ -2731,113 +3655,670 @@ async fn upload_file_with_quantum_encryption(
     match result {
         Ok((filename, cost)) => {
             println!("✅ Quantum-encrypted file uploaded: {}", filename);
-            println!("🔑 Quantum keys saved for file: {}", file_name_in_bucket);
             Ok((filename, cost))
         }
         Err(e) => Err(e),
     }
 }
 
-// Helper function to handle encrypted file upload
-#[allow(clippy::too_many_arguments)]
-async fn upload_file_with_encryption(
-    client: &Client,
-    file_path: &Path,
-    full_url: &str,
-    file_name_in_bucket: &str,
-    creds: &SavedCredentials,
-    encrypt: bool,
-    password: Option<String>,
-    shared_progress: Option<DirectoryUploadProgress>,
-) -> Result<(String, f64)> {
-    if encrypt {
-        // Get password if not provided
-        let password = match password {
-            Some(p) => p,
-            None => {
-                let password = rpassword::prompt_password("Enter encryption password: ")?;
-                let confirm = rpassword::prompt_password("Confirm encryption password: ")?;
-                if password != confirm {
-                    return Err(anyhow!("Passwords do not match"));
-                }
-                password
+// Content key source for encrypted uploads
+#[derive(Clone)]
+enum UploadEncryption {
+    // Key derived from the password with a fresh salt per file
+    Password(String),
+    // Password key derived once for a batch; each file gets an HKDF subkey
+    Batch(crate::encryption::BatchPasswordKey),
+    // Random per-file data key wrapped by the active master key
+    Envelope {
+        master_key_id: String,
+        master_key: crate::encryption::EncryptionKey,
+    },
+    // Stream key encapsulated to a contact's Kyber public key
+    Recipient(Vec<u8>),
+}
+
+impl UploadEncryption {
+    // Encryption mode recorded in upload manifests
+    fn manifest_mode(&self) -> &'static str {
+        match self {
+            UploadEncryption::Password(_) | UploadEncryption::Batch(_) => "password",
+            UploadEncryption::Envelope { .. } => "envelope",
+            UploadEncryption::Recipient(_) => "recipient",
+        }
+    }
+
+    // Encrypt a stream (compression happens before encryption)
+    async fn encrypt_file<R: std::io::Read, W: std::io::Write>(
+        &self,
+        input: R,
+        output: W,
+        compression: Option<compression::CompressionSpec>,
+        metadata: Option<&crate::encryption::FileMetadata>,
+        progress_callback: Option<Box<dyn Fn(usize) + Send>>,
+    ) -> Result<()> {
+        match self {
+            UploadEncryption::Password(password) => {
+                crate::encryption::encrypt_file_with_password_and_options(
+                    input,
+                    output,
+                    password,
+                    compression,
+                    metadata,
+                    progress_callback,
+                )
+                .await
             }
-        };
+            UploadEncryption::Batch(batch_key) => {
+                crate::encryption::encrypt_file_with_batch_key(
+                    input,
+                    output,
+                    batch_key,
+                    compression,
+                    metadata,
+                    progress_callback,
+                )
+                .await
+            }
+            UploadEncryption::Envelope {
+                master_key_id,
+                master_key,
+            } => {
+                crate::encryption::encrypt_file_with_envelope(
+                    input,
+                    output,
+                    master_key_id,
+                    master_key,
+                    compression,
+                    metadata,
+                    progress_callback,
+                )
+                .await
+            }
+            UploadEncryption::Recipient(kem_public) => {
+                crate::quantum::encrypt_file_with_kyber(
+                    input,
+                    output,
+                    kem_public,
+                    compression,
+                    metadata,
+                    progress_callback,
+                )
+                .await
+            }
+        }
+    }
+}
 
-        // Create a temporary encrypted file
-        let temp_path = file_path.with_extension("enc.tmp");
+// Encryption to a contact's Kyber public key, for --recipient
+fn recipient_encryption(keyring: &keyring::Keyring, name: &str) -> Result<UploadEncryption> {
+    let contact = keyring
+        .get_contact(name)
+        .ok_or_else(|| anyhow!("Contact '{}' not found; add it with 'pipe contact add'", name))?;
+    let kem_public = contact
+        .kem_public
+        .clone()
+        .ok_or_else(|| anyhow!("Contact '{}' has no Kyber1024 encryption key", name))?;
+    if !contact.verified {
+        eprintln!(
+            "⚠️  Contact '{}' is unverified; compare fingerprints and run 'pipe contact verify {}'",
+            name, name
+        );
+    }
+    Ok(UploadEncryption::Recipient(kem_public))
+}
 
-        // Encrypt the file
-        let input_file = std::fs::File::open(file_path)?;
-        let output_file = std::fs::File::create(&temp_path)?;
+// Resolve how an upload is encrypted: with --recipient the content key is
+// encapsulated to the contact's Kyber key. With --envelope, or --encrypt
+// without a password, the active master key is unlocked (created on first
+// use); otherwise the key is derived from the password, prompted for if needed.
+// `use_password` makes a password the default when none is given.
+fn resolve_upload_encryption(
+    encrypt: bool,
+    envelope: bool,
+    use_password: bool,
+    password: Option<String>,
+    recipient: Option<&str>,
+) -> Result<Option<UploadEncryption>> {
+    if let Some(name) = recipient {
+        let keyring = keyring::Keyring::load_from_file(&keyring::Keyring::default_path()?)?;
+        return recipient_encryption(&keyring, name).map(Some);
+    }
+    if envelope || (encrypt && !use_password && password.is_none()) {
+        let (_, unlocked) = unlock_or_create_master_keys()?;
+        let (id, key) = unlocked.active();
+        return Ok(Some(UploadEncryption::Envelope {
+            master_key_id: id.to_string(),
+            master_key: key.clone(),
+        }));
+    }
+    if !encrypt {
+        return Ok(None);
+    }
 
-        println!("Encrypting {}...", file_path.display());
+    let password = match password {
+        Some(p) => p,
+        None => prompt_new_password("Enter encryption password: ", "Confirm encryption password: ")?,
+    };
+    Ok(Some(UploadEncryption::Password(password)))
+}
 
-        crate::encryption::encrypt_file_with_password(input_file, output_file, &password, None)
-            .await?;
+// Prompt for a new password twice and make sure both entries match
+fn prompt_new_password(prompt: &str, confirm_prompt: &str) -> Result<String> {
+    let password = rpassword::prompt_password(prompt)?;
+    let confirm = rpassword::prompt_password(confirm_prompt)?;
+    if password != confirm {
+        return Err(anyhow!("Passwords do not match"));
+    }
+    Ok(password)
+}
 
-        // Upload the encrypted file
-        let remote_name = format!("{}.enc", file_name_in_bucket);
-        let result = upload_file_with_shared_progress(
-            client,
-            &temp_path,
-            &full_url.replace(file_name_in_bucket, &remote_name),
-            &remote_name,
-            creds,
-            shared_progress,
-        )
-        .await;
+// Unlock the master key store, prompting for its password or loading the
+// keyring key that protects it
+fn unlock_master_keys(
+    store: &envelope::MasterKeyStore,
+) -> Result<(crate::encryption::EncryptionKey, envelope::UnlockedMasterKeys)> {
+    let kek = match store.protection() {
+        envelope::Protection::Password { salt } => {
+            let password = rpassword::prompt_password("Enter master key password: ")?;
+            crate::encryption::derive_key_from_password(&password, salt)?
+        }
+        envelope::Protection::Keyring { key_name } => {
+            keyring_protection_key(key_name, keyring::KeyUse::Decrypt)?
+        }
+    };
+    let unlocked = store.unlock(&kek)?;
+    Ok((kek, unlocked))
+}
 
-        // Clean up temporary file
-        let _ = std::fs::remove_file(&temp_path);
+// Unlock the master key store, creating it with a new password on first use
+fn unlock_or_create_master_keys() -> Result<(envelope::MasterKeyStore, envelope::UnlockedMasterKeys)> {
+    let path = envelope::MasterKeyStore::default_path()?;
+    if path.exists() {
+        let store = envelope::MasterKeyStore::load_from_file(&path)?;
+        let (_, unlocked) = unlock_master_keys(&store)?;
+        return Ok((store, unlocked));
+    }
 
-        result
+    println!("No master key found; creating one at {}", path.display());
+    println!("The master key protects the per-file keys of --envelope uploads.");
+    let password = prompt_new_password(
+        "Choose master key password: ",
+        "Confirm master key password: ",
+    )?;
+    let (kek, protection) = envelope::new_password_protection(&password)?;
+    let (store, unlocked) = envelope::MasterKeyStore::create(&kek, protection)?;
+    store.save_to_file(&path)?;
+    Ok((store, unlocked))
+}
+
+// Keyring password for adding a key. An empty keyring gets its master
+// password set up first; legacy keyrings use the hardcoded password.
+fn keyring_password_for_new_key(keyring: &mut keyring::Keyring) -> Result<String> {
+    if keyring.keys().is_empty() && !keyring.has_password() {
+        // First time setup - initialize keyring password
+        println!("🔐 Setting up keyring master password...");
+        let password = rpassword::prompt_password("Enter new keyring password: ")?;
+        let confirm = rpassword::prompt_password("Confirm keyring password: ")?;
+        if password != confirm {
+            return Err(anyhow!("Passwords do not match"));
+        }
+        keyring.initialize_password(&password)?;
+        Ok(password)
+    } else if keyring.is_legacy() {
+        // Legacy keyring - use hardcoded password
+        eprintln!("⚠️  Using legacy keyring password. Run 'pipe keyring-migrate' to set a custom password.");
+        Ok("keyring-protection".to_string())
     } else {
-        // Regular upload without encryption
-        upload_file_with_shared_progress(
-            client,
-            file_path,
-            full_url,
-            file_name_in_bucket,
-            creds,
-            shared_progress,
-        )
-        .await
+        // Normal operation - prompt for password
+        Ok(rpassword::prompt_password("Enter keyring password: ")?)
     }
 }
 
-// Upload file with shared progress bar for directory uploads
-async fn upload_file_with_shared_progress(
-    client: &Client,
-    file_path: &Path,
-    full_url: &str,
-    file_name_in_bucket: &str,
-    creds: &SavedCredentials,
-    shared_progress: Option<DirectoryUploadProgress>,
-) -> Result<(String, f64)> {
-    let f = TokioFile::open(file_path)
-        .await
-        .map_err(|e| anyhow!("Failed to open local file: {}", e))?;
-    let meta = f.metadata().await?;
-    let file_size = meta.len();
-
-    // Use individual progress bar if no shared progress provided
-    let (progress, is_shared) = match shared_progress {
-        Some(ref sp) => (sp.progress_bar.clone(), true),
-        None => {
-            let pb = ProgressBar::new(file_size);
-            let gui_style = std::env::args().any(|arg| arg == "--gui-style");
-            if gui_style {
-                pb.set_draw_target(indicatif::ProgressDrawTarget::hidden());
-            } else {
-                pb.set_style(
-                    ProgressStyle::default_bar()
-                        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-                        .unwrap()
-                        .progress_chars("#>-"),
-                );
-            }
-            (Arc::new(pb), false)
+// Read a public key from a 'key-export --public' file, or raw key bytes
+fn read_public_key_file(path: &str, algorithm: keyring::KeyAlgorithm) -> Result<Vec<u8>> {
+    if let Ok(exported) = keyring::ExportedKey::load_from_file(Path::new(path)) {
+        if exported.algorithm() != &algorithm {
+            return Err(anyhow!(
+                "'{}' holds a {} key, expected {}",
+                path,
+                exported.algorithm(),
+                algorithm
+            ));
+        }
+        return exported
+            .public_key()
+            .map(|key| key.to_vec())
+            .ok_or_else(|| anyhow!("'{}' has no public key", path));
+    }
+    Ok(std::fs::read(path)?)
+}
+
+// Read a signing public key (Dilithium5 or hybrid) from a 'key-export --public'
+// file, or raw key bytes
+fn read_signing_public_key_file(path: &str) -> Result<Vec<u8>> {
+    if let Ok(exported) = keyring::ExportedKey::load_from_file(Path::new(path)) {
+        let algorithm = exported.algorithm().clone();
+        if !algorithm.is_signing() {
+            return Err(anyhow!("'{}' holds a {} key, expected a signing key", path, algorithm));
+        }
+        return read_public_key_file(path, algorithm);
+    }
+    Ok(std::fs::read(path)?)
+}
+
+// Signing public key named on the command line: a key file, a contact or a
+// keyring signing key. The contact is returned when the key came from one.
+fn resolve_signer_public_key<'a>(
+    keyring: &'a keyring::Keyring,
+    signer: &str,
+) -> Result<Option<(Vec<u8>, Option<&'a keyring::Contact>)>> {
+    if Path::new(signer).exists() {
+        return Ok(Some((
+            read_signing_public_key_file(signer)?,
+            None,
+        )));
+    }
+    if let Some(contact) = keyring.get_contact(signer) {
+        if let Some(ref sign_public) = contact.sign_public {
+            return Ok(Some((sign_public.clone(), Some(contact))));
+        }
+    }
+    if let Some(key) = keyring
+        .get_key(signer)
+        .filter(|k| k.algorithm.is_signing())
+    {
+        let public_key = key
+            .public_key
+            .clone()
+            .ok_or_else(|| anyhow!("Key '{}' has no public key", signer))?;
+        return Ok(Some((public_key, None)));
+    }
+    Ok(None)
+}
+
+// Verify one file against a signature envelope, or raw signature bytes from
+// older versions (which need the expected key or a .pubkey file next to the
+// signature). Prints the outcome and the signed fields.
+fn verify_signature_file(
+    keyring: &keyring::Keyring,
+    input_path: &Path,
+    signature_path: &Path,
+    expected_key: Option<&[u8]>,
+) -> Result<bool> {
+    let signature_bytes = std::fs::read(signature_path)?;
+    // Anything that parses as JSON is an envelope, so a rejected one reports why
+    let envelope = match std::str::from_utf8(&signature_bytes) {
+        Ok(json) if serde_json::from_str::<serde_json::Value>(json).is_ok() => {
+            Some(signature::SignatureSidecar::from_json(json)?)
+        }
+        _ => None,
+    };
+
+    let Some(sidecar) = envelope else {
+        let pubkey_bytes = match expected_key {
+            Some(key) => key.to_vec(),
+            None => {
+                let pubkey_file = format!("{}.pubkey", signature_path.display());
+                if !Path::new(&pubkey_file).exists() {
+                    return Err(anyhow!(
+                        "'{}' is a raw signature; pass --public-key to verify it",
+                        signature_path.display()
+                    ));
+                }
+                std::fs::read(&pubkey_file)?
+            }
+        };
+        let data = std::fs::read(input_path)?;
+        if !quantum::verify_signature(&data, &signature_bytes, &pubkey_bytes)? {
+            println!("❌ Signature verification FAILED for '{}'", input_path.display());
+            return Ok(false);
+        }
+        println!("✅ Signature verification PASSED for '{}'", input_path.display());
+        println!("   Raw signature without signer, time or hash");
+        print_signer(keyring, &pubkey_bytes);
+        return Ok(true);
+    };
+
+    let signer_key = sidecar.signer_public_key()?;
+    let failure = if let Err(e) = sidecar.verify_signature() {
+        Some(e.to_string())
+    } else if expected_key.is_some_and(|key| key != signer_key.as_slice()) {
+        Some("Signed by a different key than the one required".to_string())
+    } else if signature::hash_file(input_path)? != sidecar.blake3 {
+        Some("File does not match the signed hash; it was modified".to_string())
+    } else {
+        None
+    };
+
+    match &failure {
+        Some(reason) => println!(
+            "❌ Signature verification FAILED for '{}': {}",
+            input_path.display(),
+            reason
+        ),
+        None => println!("✅ Signature verification PASSED for '{}'", input_path.display()),
+    }
+    println!("   Format version: {}", sidecar.version);
+    if let Some(signed_at) = sidecar.signed_at {
+        println!("   Signed at: {}", signed_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    }
+    println!("   Blake3: {}", sidecar.blake3);
+    if let Some(ref comment) = sidecar.comment {
+        println!("   Comment: {}", comment);
+    }
+    if failure.is_some() {
+        print_fingerprint("   ", "Signer fingerprint", &sidecar.signer_fingerprint);
+        return Ok(false);
+    }
+    print_signer(keyring, &signer_key);
+    Ok(true)
+}
+
+// Print who made a valid signature: a contact (warning while unverified), one
+// of our own keys, or an unknown key whose fingerprint has to be checked
+fn print_signer(keyring: &keyring::Keyring, public_key: &[u8]) {
+    let algorithm = keyring::KeyAlgorithm::for_signing_key(public_key);
+    let fingerprint = keyring::key_fingerprint(&algorithm, public_key);
+    print_fingerprint("   ", "Signer fingerprint", &fingerprint);
+    println!("   Signature: {}", algorithm);
+
+    let contact = keyring
+        .contacts()
+        .into_iter()
+        .find(|c| c.sign_fingerprint().as_deref() == Some(fingerprint.as_str()));
+    let own_key = keyring.list_keys().into_iter().find(|(_, key)| {
+        key.algorithm.is_signing()
+            && key.fingerprint_hex().as_deref() == Some(fingerprint.as_str())
+    });
+    match (contact, own_key) {
+        (Some(contact), _) => {
+            println!("   Signed by contact '{}'", contact.name);
+            if !contact.verified {
+                println!(
+                    "   ⚠️  Contact is unverified; compare fingerprints and run 'pipe contact verify {}'",
+                    contact.name
+                );
+            }
+        }
+        (None, Some((name, _))) => println!("   Signed with your key '{}'", name),
+        (None, None) => {
+            println!("   ⚠️  Signer is not in your contacts; compare the fingerprint with the sender")
+        }
+    }
+}
+
+fn print_contact_fingerprints(contact: &keyring::Contact) {
+    if let Some(fingerprint) = contact.kem_fingerprint() {
+        print_fingerprint("  ", "Encryption key (Kyber1024)", &fingerprint);
+    }
+    if let (Some(fingerprint), Some(key)) = (contact.sign_fingerprint(), &contact.sign_public) {
+        let label = format!("Signing key ({})", keyring::KeyAlgorithm::for_signing_key(key));
+        print_fingerprint("  ", &label, &fingerprint);
+    }
+}
+
+// Print a key fingerprint as grouped hex plus the word form for comparing out of band
+fn print_fingerprint(indent: &str, label: &str, fingerprint: &str) {
+    println!("{}{}: {}", indent, label, keyring::fingerprint_grouped(fingerprint));
+    if let Ok(words) = keyring::fingerprint_words(fingerprint) {
+        println!("{}{:width$}  {}", indent, "", words, width = label.len());
+    }
+}
+
+// AES-256 key from the keyring used to protect the master key store
+fn keyring_protection_key(
+    key_name: &str,
+    key_use: keyring::KeyUse,
+) -> Result<crate::encryption::EncryptionKey> {
+    let material = load_keyring_key_material(key_name, &[keyring::KeyAlgorithm::Aes256], key_use)?;
+    let key = material
+        .symmetric_key
+        .ok_or_else(|| anyhow!("Key '{}' has no symmetric key material", key_name))?;
+    Ok(crate::encryption::EncryptionKey { key })
+}
+
+// Rewrap a local envelope-encrypted file to the active master key in place.
+// Returns false if it already uses the active key.
+fn rewrap_local_file(path: &Path, unlocked: &envelope::UnlockedMasterKeys) -> Result<bool> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?;
+    let temp_path = path.with_file_name(format!(".{}.rekey.tmp", file_name.to_string_lossy()));
+
+    let input = std::io::BufReader::new(std::fs::File::open(path)?);
+    let output = std::io::BufWriter::new(std::fs::File::create(&temp_path)?);
+    match crate::encryption::rewrap_envelope(input, output, unlocked.keys(), unlocked.active().0) {
+        Ok(true) => {
+            std::fs::rename(&temp_path, path)?;
+            Ok(true)
+        }
+        Ok(false) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Ok(false)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+// Rewrap a remote envelope-encrypted file. Only the start of the file is
+// downloaded; the data key rewrapped to the active master key is uploaded as
+// `<name>.key` next to it and replaces the header's key block on download.
+async fn rewrap_remote_file(
+    client: &Client,
+    service_cache: &Arc<ServiceDiscoveryCache>,
+    base_url: &str,
+    creds: &SavedCredentials,
+    remote_name: &str,
+    unlocked: &envelope::UnlockedMasterKeys,
+) -> Result<bool> {
+    let download_endpoint = get_endpoint_for_operation(
+        service_cache,
+        client,
+        base_url,
+        "download",
+        &creds.user_id,
+        Some(remote_name),
+    )
+    .await;
+    let head = fetch_file_head(client, &download_endpoint, creds, remote_name, 8192).await?;
+
+    // A file rewrapped before is rewrapped again from its current key block
+    let key_name = format!("{}.key", remote_name);
+    let key_block_len = crate::encryption::MAX_KEY_BLOCK_SIZE + 1;
+    let head = match fetch_file_head(client, &download_endpoint, creds, &key_name, key_block_len).await {
+        Ok(key_block) => {
+            let mut rewrapped = Vec::new();
+            crate::encryption::apply_envelope_key_block(std::io::Cursor::new(&head), &mut rewrapped, &key_block)?;
+            rewrapped
+        }
+        Err(_) => head,
+    };
+
+    let Some(key_block) =
+        crate::encryption::rewrap_envelope_key_block(&head, unlocked.keys(), unlocked.active().0)?
+    else {
+        return Ok(false);
+    };
+
+    let upload_endpoint = get_endpoint_for_operation(
+        service_cache,
+        client,
+        base_url,
+        "upload",
+        &creds.user_id,
+        Some(&key_name),
+    )
+    .await;
+    let url = format!(
+        "{}/upload?file_name={}",
+        upload_endpoint,
+        utf8_percent_encode(&key_name, QUERY_ENCODE_SET)
+    );
+    let key_file = tempfile::NamedTempFile::new()?;
+    std::fs::write(key_file.path(), &key_block)?;
+    upload_file_with_shared_progress(client, key_file.path(), &url, &key_name, creds, None).await?;
+    Ok(true)
+}
+
+// Replace the key block of a downloaded envelope file with the one uploaded by
+// `rekey --remote` as `<name>.key`, if there is one
+async fn apply_remote_key_block(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+    remote_name: &str,
+    path: &Path,
+) -> Result<()> {
+    let key_name = format!("{}.key", remote_name);
+    let key_block_len = crate::encryption::MAX_KEY_BLOCK_SIZE + 1;
+    let Ok(key_block) = fetch_file_head(client, base_url, creds, &key_name, key_block_len).await else {
+        return Ok(());
+    };
+
+    let temp_path = path.with_extension("rekey.tmp");
+    let input = std::io::BufReader::new(std::fs::File::open(path)?);
+    let output = std::io::BufWriter::new(std::fs::File::create(&temp_path)?);
+    if let Err(e) = crate::encryption::apply_envelope_key_block(input, output, &key_block) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(anyhow!("Invalid rewrapped key '{}': {}", key_name, e));
+    }
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+// Helper function to handle encrypted and/or compressed file upload
+#[allow(clippy::too_many_arguments)]
+async fn upload_file_with_encryption(
+    client: &Client,
+    file_path: &Path,
+    full_url: &str,
+    file_name_in_bucket: &str,
+    creds: &SavedCredentials,
+    encryption: Option<UploadEncryption>,
+    compressed: Option<&compression::CompressedFile>,
+    shared_progress: Option<DirectoryUploadProgress>,
+) -> Result<(String, f64)> {
+    if let Some(encryption) = encryption {
+        // Create a temporary encrypted file
+        let temp_path = file_path.with_extension("enc.tmp");
+
+        // Encrypt the file; compressed files are encrypted from their
+        // already compressed frame
+        let metadata = crate::encryption::FileMetadata::from_path(file_path)?;
+        let output_file = std::fs::File::create(&temp_path)?;
+
+        println!("Encrypting {}...", file_path.display());
+
+        match compressed {
+            Some(compressed) => {
+                let (frame, spec) = compressed.frame()?;
+                encryption
+                    .encrypt_file(frame, output_file, Some(spec), Some(&metadata), None)
+                    .await?
+            }
+            None => {
+                let input_file = std::fs::File::open(file_path)?;
+                encryption
+                    .encrypt_file(input_file, output_file, None, Some(&metadata), None)
+                    .await?
+            }
+        }
+
+        // Upload the encrypted file
+        let remote_name = format!("{}.enc", file_name_in_bucket);
+        let result = upload_file_with_shared_progress(
+            client,
+            &temp_path,
+            &full_url.replace(file_name_in_bucket, &remote_name),
+            &remote_name,
+            creds,
+            shared_progress,
+        )
+        .await;
+
+        // Clean up temporary file
+        let _ = std::fs::remove_file(&temp_path);
+
+        result
+    } else if let Some(compressed) = compressed {
+        // Compressed upload without encryption; the remote name is unchanged and
+        // the PIPE-ZST header triggers decompression on download
+        upload_file_with_shared_progress(
+            client,
+            compressed.path(),
+            full_url,
+            file_name_in_bucket,
+            creds,
+            shared_progress,
+        )
+        .await
+    } else {
+        // Regular upload without encryption
+        upload_file_with_shared_progress(
+            client,
+            file_path,
+            full_url,
+            file_name_in_bucket,
+            creds,
+            shared_progress,
+        )
+        .await
+    }
+}
+
+// Manifest entry for a file uploaded from a directory, hashing the local plaintext
+async fn manifest_entry(
+    path: &Path,
+    rel_path: &str,
+    remote_path: &str,
+    encryption: &str,
+    compression: Option<compression::CompressionSpec>,
+) -> Result<manifest::ManifestEntry> {
+    Ok(manifest::ManifestEntry {
+        path: rel_path.to_string(),
+        remote_path: remote_path.to_string(),
+        size: tokio::fs::metadata(path).await?.len(),
+        blake3: calculate_blake3(path).await?,
+        encryption: encryption.to_string(),
+        compression: compression.map(|spec| format!("zstd:{}", spec.level)),
+    })
+}
+
+// Upload file with shared progress bar for directory uploads
+async fn upload_file_with_shared_progress(
+    client: &Client,
+    file_path: &Path,
+    full_url: &str,
+    file_name_in_bucket: &str,
+    creds: &SavedCredentials,
+    shared_progress: Option<DirectoryUploadProgress>,
+) -> Result<(String, f64)> {
+    let f = TokioFile::open(file_path)
+        .await
+        .map_err(|e| anyhow!("Failed to open local file: {}", e))?;
+    let meta = f.metadata().await?;
+    let file_size = meta.len();
+
+    // Use individual progress bar if no shared progress provided
+    let (progress, is_shared) = match shared_progress {
+        Some(ref sp) => (sp.progress_bar.clone(), true),
+        None => {
+            let pb = ProgressBar::new(file_size);
+            let gui_style = std::env::args().any(|arg| arg == "--gui-style");
+            if gui_style {
+                pb.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+            } else {
+                pb.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+                        .unwrap()
+                        .progress_chars("#>-"),
+                );
+            }
+            (Arc::new(pb), false)
         }
     };
 
@@ -2932,6 +4413,7 @@ async fn upload_file_with_shared_progress(
     }
 
     let resp = request.body(body).send().await?;
+    listing::invalidate_cache();
 
     let status = resp.status();
 
@@ -3123,6 +4605,7 @@ async fn upload_file_priority_with_shared_progress(
     }
 
     let resp = request.body(body).send().await?;
+    listing::invalidate_cache();
 
     let status = resp.status();
 
@@ -3245,6 +4728,16 @@ mod tests {
     use super::*;
     use base64::engine::general_purpose;
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576").unwrap(), 1 << 20);
+        assert_eq!(parse_size("500KB").unwrap(), 500 * 1024);
+        assert_eq!(parse_size("1.5gb").unwrap(), 3 << 29);
+        assert_eq!(parse_size("2 MB").unwrap(), 2 << 20);
+        assert!(parse_size("10XB").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+
     #[test]
     fn test_base64_decode_valid_text() {
         // Test that valid base64 encoded text is properly decoded
@@ -3392,6 +4885,52 @@ mod tests {
             Err(_) => panic!("Empty should be valid UTF-8"),
         }
     }
+
+    #[tokio::test]
+    async fn test_recipient_encryption_roundtrip() {
+        use pqcrypto_mldsa::mldsa87 as dilithium5;
+        use pqcrypto_mlkem::mlkem1024 as kyber1024;
+        use pqcrypto_traits::kem::{PublicKey, SecretKey};
+        use pqcrypto_traits::sign::PublicKey as SignPublicKey;
+        use std::io::Cursor;
+
+        let (kem_public, kem_secret) = kyber1024::keypair();
+        let (sign_public, _) = dilithium5::keypair();
+        let mut keyring = keyring::Keyring::new();
+        keyring
+            .add_contact(keyring::Contact::new("bob", Some(kem_public.as_bytes().to_vec()), None).unwrap())
+            .unwrap();
+        keyring
+            .add_contact(keyring::Contact::new("carol", None, Some(sign_public.as_bytes().to_vec())).unwrap())
+            .unwrap();
+        assert!(recipient_encryption(&keyring, "dave").is_err());
+        assert!(recipient_encryption(&keyring, "carol").is_err());
+
+        let data = b"for bob's eyes only ".repeat(1000);
+        let mut encrypted = Vec::new();
+        recipient_encryption(&keyring, "bob")
+            .unwrap()
+            .encrypt_file(
+                Cursor::new(&data),
+                &mut encrypted,
+                Some(compression::CompressionSpec::parse("zstd").unwrap()),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            crate::encryption::detect_format(Cursor::new(&encrypted)).unwrap(),
+            crate::encryption::EncryptionFormat::Kyber
+        );
+
+        // Bob opens it with his Kyber secret key
+        let mut decrypted = Vec::new();
+        crate::quantum::decrypt_file_with_kyber(Cursor::new(&encrypted), &mut decrypted, kem_secret.as_bytes(), None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted, data);
+    }
 }
 
 #[cfg(test)]
@@ -3566,7 +5105,7 @@ pub async fn run_cli() -> Result<()> {
             | Commands::GetTierPricing
             | Commands::PriorityUpload { .. }
             | Commands::PriorityDownload { .. }
-            | Commands::ListUploads
+            | Commands::ListUploads { .. }
             | Commands::ExtendStorage { .. }
     );
     */
@@ -4043,7 +5582,13 @@ pub async fn run_cli() -> Result<()> {
             tier,
             encrypt,
             password,
+            use_password,
+            envelope,
+            recipient,
             dry_run,
+            compress,
+            sign,
+            quantum_identity,
             ..
         } => {
             // Load credentials and check for JWT
@@ -4067,11 +5612,21 @@ pub async fn run_cli() -> Result<()> {
                 return Err(anyhow!("Local file not found: {}", file_path));
             }
 
+            let compression = compress
+                .as_deref()
+                .map(compression::CompressionSpec::parse)
+                .transpose()?;
+
             let epochs_final = epochs.unwrap_or(1); // default 1 month
 
             // Handle dry-run: calculate and show cost estimate
             if dry_run {
-                let file_size = std::fs::metadata(local_path)?.len();
+                let original_size = std::fs::metadata(local_path)?.len();
+                // Storage is charged on the bytes actually uploaded
+                let file_size = match compression {
+                    Some(spec) => compression::compressed_size(local_path, spec)?,
+                    None => original_size,
+                };
                 let file_size_gb = file_size as f64 / 1_000_000_000.0;
                 
                 // Get tier pricing
@@ -4106,6 +5661,13 @@ pub async fn run_cli() -> Result<()> {
                 println!("\n📊 Upload Cost Estimate:");
                 println!("  📁 File: {}", file_name);
                 println!("  📏 Size: {:.2} MB ({:.4} GB)", file_size as f64 / 1_048_576.0, file_size_gb);
+                if compression.is_some() {
+                    println!(
+                        "  🗜️  Compressed from {:.2} MB ({:.1}x)",
+                        original_size as f64 / 1_048_576.0,
+                        original_size as f64 / file_size.max(1) as f64
+                    );
+                }
                 println!("  📈 Tier: {}", tier_name);
                 println!("  💵 Rate: {} PIPE tokens/GB", cost_per_gb);
                 println!("  💰 Estimated cost: {:.4} PIPE tokens", estimated_cost);
@@ -4187,22 +5749,60 @@ pub async fn run_cli() -> Result<()> {
             println!("Blake3 hash: {}", &blake3_hash[..16]); // Show first 16 chars
             let file_size = std::fs::metadata(local_path)?.len();
 
+            // Resolve the password or master key once, outside the retry loop
+            let encryption =
+                resolve_upload_encryption(encrypt, envelope, use_password, password.clone(), recipient.as_deref())?;
+
+            // Compress once, outside the retry loop
+            let compressed = compression
+                .map(|spec| compression::CompressedFile::new(local_path, spec))
+                .transpose()?;
+
+            // Sign before uploading so a locked or retired key fails early
+            let sidecar = match sign {
+                Some(ref key_name) => {
+                    let material = load_keyring_key_material(
+                        key_name,
+                        &[keyring::KeyAlgorithm::Dilithium5, keyring::KeyAlgorithm::Ed25519Dilithium5],
+                        keyring::KeyUse::Encrypt,
+                    )?;
+                    Some(signature::SignatureSidecar::sign(
+                        &blake3_hash,
+                        None,
+                        material
+                            .private_key
+                            .as_deref()
+                            .ok_or_else(|| anyhow!("Key '{}' has no secret key material", key_name))?,
+                        material
+                            .public_key
+                            .as_deref()
+                            .ok_or_else(|| anyhow!("Key '{}' has no public key", key_name))?,
+                    )?)
+                }
+                None => None,
+            };
+
             // Use retry wrapper for single file upload
-            let upload_result = if false { // quantum feature was removed
-                // Quantum encryption upload
-                upload_with_retry(&format!("quantum upload of {}", file_path), || {
+            let upload_result = if let Some(ref identity) = quantum_identity {
+                // Sign-and-encrypt with a long-lived quantum identity, unlocked
+                // once for all attempts
+                let quantum_keys =
+                    QuantumUploadKeys::resolve(&file_name, encrypt, password.clone(), Some(identity.clone()))?;
+                let result = upload_with_retry(&format!("quantum upload of {}", file_path), || {
                     upload_file_with_quantum_encryption(
                         &client,
                         local_path,
                         &url,
                         &file_name,
                         &creds,
-                        encrypt,
-                        password.clone(),
-                        None,
+                        &quantum_keys,
                     )
                 })
-                .await
+                .await;
+                if result.is_ok() {
+                    quantum_keys.save(&file_name)?;
+                }
+                result
             } else {
                 // Regular upload (with optional password encryption)
                 upload_with_retry(&format!("upload of {}", file_path), || {
@@ -4212,8 +5812,8 @@ pub async fn run_cli() -> Result<()> {
                         &url,
                         &file_name,
                         &creds,
-                        encrypt,
-                        password.clone(),
+                        encryption.clone(),
+                        compressed.as_ref(),
                         None,
                     )
                 })
@@ -4233,8 +5833,34 @@ pub async fn run_cli() -> Result<()> {
                         &format!("Non-priority upload ({} epochs)", epochs_final),
                         Some(blake3_hash.clone()),
                         Some(file_size),
+                        (token_cost > 0.0).then_some(token_cost),
                     )?;
                     println!("📋 File ID (Blake3): {}", blake3_hash);
+
+                    if let Some(sidecar) = sidecar {
+                        let sig_name = format!("{}.sig", file_name);
+                        let mut sig_file = tempfile::NamedTempFile::new()?;
+                        std::io::Write::write_all(&mut sig_file, sidecar.to_json()?.as_bytes())?;
+                        let sig_url = url.replacen(
+                            &format!("file_name={}", utf8_percent_encode(&file_name, QUERY_ENCODE_SET)),
+                            &format!("file_name={}", utf8_percent_encode(&sig_name, QUERY_ENCODE_SET)),
+                            1,
+                        );
+                        upload_with_retry(&format!("upload of {}", sig_name), || {
+                            upload_file_with_shared_progress(
+                                &client,
+                                sig_file.path(),
+                                &sig_url,
+                                &sig_name,
+                                &creds,
+                                None,
+                            )
+                        })
+                        .await
+                        .map_err(|e| anyhow!("File uploaded, but uploading its signature failed: {}", e))?;
+                        println!("✍️  Signature uploaded as {}", sig_name);
+                        print_fingerprint("   ", "Signer fingerprint", &sidecar.signer_fingerprint);
+                    }
                 }
                 Err(e) => {
                     eprintln!("Upload failed for {} => {}", file_path, e);
@@ -4251,8 +5877,12 @@ pub async fn run_cli() -> Result<()> {
             output_path,
             decrypt,
             password,
+            key,
             quantum,
+            raw,
             legacy,
+            verify,
+            signer,
             ..
         } => {
             // Load credentials and check for JWT
@@ -4282,34 +5912,120 @@ pub async fn run_cli() -> Result<()> {
             )
             .await;
 
-            // Check if this might be a quantum-encrypted file
-            let is_quantum_file = file_name.ends_with(".qenc") || quantum;
-            
-            if is_quantum_file {
-                download_file_with_quantum_decryption_and_options(
+            // Fetch the signature first so a missing one fails before any download
+            let sidecar = if verify {
+                let sig_name = format!("{}.sig", strip_encrypted_extension(&file_name));
+                let sig_file = tempfile::NamedTempFile::new()?;
+                improved_download_file_with_auth_and_options(
+                    &client,
+                    &selected_endpoint,
+                    &creds,
+                    &sig_name,
+                    &sig_file.path().to_string_lossy(),
+                    legacy,
+                )
+                .await
+                .map_err(|e| anyhow!("No signature found for '{}' ({}): {}", file_name, sig_name, e))?;
+                let sidecar = signature::SignatureSidecar::from_json(&std::fs::read_to_string(sig_file.path())?)?;
+                sidecar.verify_signature()?;
+
+                if let Some(ref expected) = signer {
+                    let keyring = keyring::Keyring::load_from_file(&keyring::Keyring::default_path()?)?;
+                    let (expected_key, _) = resolve_signer_public_key(&keyring, expected)?.ok_or_else(|| {
+                        anyhow!("Public key file, contact or signing key not found: {}", expected)
+                    })?;
+                    if expected_key != sidecar.signer_public_key()? {
+                        return Err(anyhow!(
+                            "'{}' is signed by {}, not by '{}'",
+                            file_name,
+                            keyring::fingerprint_grouped(&sidecar.signer_fingerprint),
+                            expected
+                        ));
+                    }
+                }
+                Some(sidecar)
+            } else if signer.is_some() {
+                return Err(anyhow!("--signer requires --verify"));
+            } else {
+                None
+            };
+
+            // With --verify the file is staged next to its destination and only
+            // moved into place once it matches the signature
+            let output_is_dir = Path::new(&output_path).is_dir();
+            let staging = if sidecar.is_some() {
+                let dest_dir = if output_is_dir {
+                    PathBuf::from(&output_path)
+                } else {
+                    Path::new(&output_path)
+                        .parent()
+                        .filter(|p| !p.as_os_str().is_empty())
+                        .map(Path::to_path_buf)
+                        .unwrap_or_else(|| PathBuf::from("."))
+                };
+                Some(tempfile::Builder::new().prefix(".pipe-verify-").tempdir_in(dest_dir)?)
+            } else {
+                None
+            };
+            let download_path = match staging {
+                Some(ref dir) => dir.path().to_string_lossy().to_string(),
+                None => output_path.clone(),
+            };
+
+            let saved_path = if raw {
+                improved_download_file_with_auth_and_options(
                     &client,
                     &selected_endpoint,
                     &creds,
                     &file_name,
                     &output_path,
-                    decrypt,
-                    password,
                     legacy,
                 )
                 .await?;
+                if Path::new(&output_path).is_dir() {
+                    Path::new(&output_path).join(&file_name)
+                } else {
+                    PathBuf::from(&output_path)
+                }
             } else {
+                // The format is detected from the downloaded bytes; --decrypt and
+                // --quantum only control the remote name and the .qenc password layer
+                let options = DecryptOptions {
+                    password,
+                    key,
+                    quantum_password: decrypt && (quantum || file_name.ends_with(".qenc")),
+                    quantum,
+                };
                 download_file_with_decryption_and_options(
                     &client,
                     &selected_endpoint,
                     &creds,
                     &file_name,
-                    &output_path,
-                    decrypt,
-                    password,
+                    &download_path,
+                    decrypt && !quantum,
+                    options,
                     legacy,
                 )
-                .await?;
-            }
+                .await?
+            };
+
+            // Fail closed: a file that does not match its signature is discarded
+            // with the staging directory and the destination is left untouched
+            if let Some(sidecar) = sidecar {
+                sidecar
+                    .verify_file(&saved_path)
+                    .map_err(|e| anyhow!("Signature check failed, download discarded: {}", e))?;
+                let final_path = if output_is_dir {
+                    Path::new(&output_path).join(saved_path.file_name().unwrap_or_default())
+                } else {
+                    PathBuf::from(&output_path)
+                };
+                std::fs::rename(&saved_path, &final_path)?;
+                println!("Saved to {}", final_path.display());
+                let keyring = keyring::Keyring::load_from_file(&keyring::Keyring::default_path()?)?;
+                println!("✅ Signature verified for '{}'", file_name);
+                print_signer(&keyring, &sidecar.signer_public_key()?);
+            }
         }
 
         Commands::DownloadDirectory {
@@ -4399,77 +6115,128 @@ pub async fn run_cli() -> Result<()> {
             )
             .await;
 
-            let mut request = client.post(format!("{}/deleteFile", selected_endpoint));
-
-            // Add auth headers including CSRF token for this state-changing operation
-            request = add_auth_headers(request, &creds, true);
+            let message = delete_remote_file(&client, &selected_endpoint, &creds, &file_name).await?;
+            println!("Delete success: {}", message);
+        }
 
-            // Use JWT auth if available, otherwise fall back to legacy
-            if let Some(ref _auth_tokens) = creds.auth_tokens {
-                // With JWT, send only file name - server will get user info from token
-                let req_body = serde_json::json!({
-                    "file_name": file_name
-                });
-                request = request.json(&req_body);
-            } else {
-                // Legacy auth via request body
-                let req_body = DeleteFileRequest {
-                    user_id: creds.user_id.clone(),
-                    user_app_key: creds.user_app_key.clone(),
-                    file_name: file_name.clone(),
-                };
-                request = request.json(&req_body);
+        Commands::FileInfo {
+            user_id,
+            user_app_key,
+            file_name,
+        } => {
+            let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
+                anyhow!("No credentials found. Please create a user or login first.")
+            })?;
+            ensure_valid_token(&client, base_url, &mut creds, config_path).await?;
+            if let Some(uid) = user_id {
+                creds.user_id = uid;
+            }
+            if let Some(key) = user_app_key {
+                creds.user_app_key = key;
             }
 
-            let resp = request.send().await?;
-            let status = resp.status();
-            let text_body = resp.text().await?;
-
-            if status.is_success() {
-                let json = serde_json::from_str::<DeleteFileResponse>(&text_body)?;
-                println!("Delete success: {}", json.message);
-            } else {
+            let entries = listing::fetch_remote_entries(&client, base_url, &creds, true).await?;
+            let remote = entries.iter().find(|e| e.name == file_name);
+            let catalog = catalog::Catalog::open_default()?;
+            let records = catalog.find_by_remote_path(&file_name)?;
+            if remote.is_none() && records.is_empty() {
                 return Err(anyhow!(
-                    "Delete file failed. Status = {}, Body = {}",
-                    status,
-                    text_body
+                    "'{}' was not found in remote storage or the upload catalog",
+                    file_name
                 ));
             }
-        }
 
-        Commands::FileInfo {
-            user_id: _,
-            user_app_key: _,
-            file_name,
-        } => {
             println!("📄 File Information for '{}':", file_name);
 
-            // Check if file is encrypted based on extension
-            let is_encrypted = file_name.ends_with(".enc");
-            println!(
-                "   Encrypted: {}",
-                if is_encrypted {
-                    "Yes (AES-256-GCM)"
-                } else {
-                    "No"
+            println!("\n☁️  Remote storage:");
+            match remote {
+                Some(entry) => {
+                    println!("   Size: {} ({} bytes)", sync::format_file_size(entry.size), entry.size);
+                    if let Some(uploaded_at) = entry.uploaded_at {
+                        println!("   Uploaded: {}", uploaded_at.format("%Y-%m-%d %H:%M:%S UTC"));
+                    }
+                    if let Some(ref tier) = entry.tier {
+                        println!("   Tier: {}", tier);
+                    }
+                    if let Some(expires_at) = entry.expires_at {
+                        println!("   Expires: {}", expires_at.format("%Y-%m-%d %H:%M:%S UTC"));
+                    }
                 }
-            );
+                None => println!("   Not in the remote listing (deleted or never uploaded)"),
+            }
 
-            if is_encrypted {
-                println!("\n💡 To download and decrypt this file:");
+            println!("\n📋 Upload catalog:");
+            if records.is_empty() {
+                println!("   No uploads recorded on this machine");
+            }
+            for record in &records {
+                let entry = &record.entry;
                 println!(
-                    "   pipe download-file {} output.file --decrypt",
-                    file_name.trim_end_matches(".enc")
+                    "   {} {} from {}",
+                    entry
+                        .timestamp
+                        .map_or("-".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string()),
+                    entry.status,
+                    entry.local_path
                 );
-            } else {
-                println!("\n💡 To check if an encrypted version exists:");
-                println!("   pipe file-info {}.enc", file_name);
+                if let Some(ref hash) = entry.blake3_hash {
+                    println!("      Blake3: {}", hash);
+                }
+                if let Some(cost) = entry.cost {
+                    println!("      Cost: {} PIPE tokens", cost);
+                }
             }
 
-            println!(
-                "\nNote: For detailed file metadata (size, upload date, etc.), the file listing"
-            );
-            println!("feature is not yet implemented in pipe-cli.");
+            let links = catalog.public_links(&file_name)?;
+            if !links.is_empty() {
+                println!("\n🔗 Public links:");
+                for link in &links {
+                    println!("   {}/publicDownload?hash={}", base_url, link.link_hash);
+                }
+            }
+
+            if remote.is_some() {
+                println!("\n🔐 Encryption:");
+                let head = fetch_file_head(&client, base_url, &creds, &file_name, 8192).await?;
+                let summary = encryption::summarize_header(&head, file_name.ends_with(".qenc"))?;
+                println!("   Format: {}", summary.format);
+                if let Some(kdf) = summary.kdf {
+                    println!("   Key derivation: {}", kdf);
+                }
+                for recipient in &summary.recipients {
+                    println!("   Recipient: {}", recipient);
+                }
+                if summary.format != encryption::EncryptionFormat::Plain {
+                    println!("   Compression: {}", if summary.compressed { "zstd" } else { "none" });
+                }
+                if summary.has_metadata {
+                    println!("   Metadata: original name, size and hash (encrypted)");
+                }
+                if summary.format == encryption::EncryptionFormat::QuantumSigned {
+                    println!("   Signer: inside the encrypted payload");
+                }
+            }
+
+            // Signatures uploaded with --sign sit next to the file
+            let sig_name = format!("{}.sig", strip_encrypted_extension(&file_name));
+            if entries.iter().any(|e| e.name == sig_name) {
+                println!("\n✍️  Signature ({}):", sig_name);
+                // A signature that cannot be read is reported, not fatal
+                let sidecar = fetch_file_head(&client, base_url, &creds, &sig_name, 64 * 1024)
+                    .await
+                    .and_then(|json| signature::SignatureSidecar::from_json(&String::from_utf8_lossy(&json)));
+                match sidecar.map(|sidecar| (sidecar.verify_signature(), sidecar)) {
+                    Ok((Ok(()), sidecar)) => {
+                        if let Some(signed_at) = sidecar.signed_at {
+                            println!("   Signed at: {}", signed_at.format("%Y-%m-%d %H:%M:%S UTC"));
+                        }
+                        let keyring = keyring::Keyring::load_from_file(&keyring::Keyring::default_path()?)?;
+                        print_signer(&keyring, &sidecar.signer_public_key()?);
+                    }
+                    Ok((Err(e), _)) => println!("   ❌ Invalid signature envelope: {}", e),
+                    Err(e) => println!("   ❌ Unreadable signature: {}", e),
+                }
+            }
         }
 
         Commands::CheckSol {
@@ -4916,6 +6683,7 @@ pub async fn run_cli() -> Result<()> {
                 creds.user_app_key = key;
             }
 
+            let remote_path = file_name.clone();
             let mut request = client.post(format!("{}/createPublicLink", base_url));
 
             // Add auth headers including CSRF token for this state-changing operation
@@ -4951,6 +6719,11 @@ pub async fn run_cli() -> Result<()> {
             let text_body = resp.text().await?;
             if status.is_success() {
                 let json: CreatePublicLinkResponse = serde_json::from_str(&text_body)?;
+                if let Err(e) = catalog::Catalog::open_default()
+                    .and_then(|c| c.record_public_link(&json.link_hash, &remote_path))
+                {
+                    eprintln!("⚠️  Could not record the link in the upload catalog: {}", e);
+                }
                 println!("✓ Public link created successfully!");
                 println!();
                 println!("Direct link (for downloads/playback):");
@@ -5019,6 +6792,11 @@ pub async fn run_cli() -> Result<()> {
             let text_body = resp.text().await?;
             if status.is_success() {
                 let json: DeletePublicLinkResponse = serde_json::from_str(&text_body)?;
+                if let Err(e) = catalog::Catalog::open_default()
+                    .and_then(|c| c.remove_public_link(&json.link_hash))
+                {
+                    eprintln!("⚠️  Could not update the upload catalog: {}", e);
+                }
                 println!("✅ {}", json.message);
                 println!("Deleted link hash: {}", json.link_hash);
             } else {
@@ -5061,6 +6839,10 @@ pub async fn run_cli() -> Result<()> {
             skip_uploaded,
             encrypt,
             password,
+            use_password,
+            envelope,
+            compress,
+            sign_manifest,
         } => {
             // Load credentials and check for JWT
             let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
@@ -5086,43 +6868,43 @@ pub async fn run_cli() -> Result<()> {
                 ));
             }
 
-            // Get password once for all files if encryption is enabled
-            let encryption_password = if encrypt {
-                let pass = match password {
-                    Some(p) => p,
-                    None => {
-                        println!(
-                            "You will use the same password to encrypt all files in the directory."
-                        );
-                        let password = rpassword::prompt_password("Enter encryption password: ")?;
-                        let confirm = rpassword::prompt_password("Confirm encryption password: ")?;
-                        if password != confirm {
-                            return Err(anyhow!("Passwords do not match"));
-                        }
-                        password
-                    }
-                };
-                Some(pass)
-            } else {
-                None
+            let compression = compress
+                .as_deref()
+                .map(compression::CompressionSpec::parse)
+                .transpose()?;
+
+            // Get password or master key once for all files if encryption is enabled
+            if encrypt && !envelope && password.is_none() {
+                println!("You will use the same password to encrypt all files in the directory.");
+            }
+            let encryption = match resolve_upload_encryption(encrypt, envelope, use_password, password, None)? {
+                // One Argon2 derivation for the whole job instead of one per file
+                Some(UploadEncryption::Password(password)) => Some(UploadEncryption::Batch(
+                    crate::encryption::BatchPasswordKey::derive(&password)?,
+                )),
+                other => other,
             };
 
+            // Unlock the manifest signing key before uploading anything
+            let manifest_signer = sign_manifest
+                .as_deref()
+                .map(|key_name| {
+                    load_keyring_key_material(
+                        key_name,
+                        &[keyring::KeyAlgorithm::Dilithium5, keyring::KeyAlgorithm::Ed25519Dilithium5],
+                        keyring::KeyUse::Encrypt,
+                    )
+                })
+                .transpose()?;
+            let manifest_entries = Arc::new(TokioMutex::new(Vec::new()));
+            let manifest_mode = encryption
+                .as_ref()
+                .map_or("none", UploadEncryption::manifest_mode);
+
             // Read upload log if skip_uploaded == true
             let mut previously_uploaded: HashSet<String> = HashSet::new();
             if skip_uploaded {
-                let log_path = get_upload_log_path();
-                if log_path.exists() {
-                    let contents = fs::read_to_string(&log_path)?;
-                    for line in contents.lines() {
-                        if let Ok(entry) = serde_json::from_str::<UploadLogEntry>(line) {
-                            if entry.status.contains("SUCCESS")
-                                || entry.status.contains("BACKGROUND")
-                            {
-                                previously_uploaded.insert(entry.local_path);
-                            }
-                        }
-                    }
-                }
+                previously_uploaded = catalog::Catalog::open_default()?.uploaded_local_paths()?;
                 println!(
                     "Found {} previously uploaded files in log",
                     previously_uploaded.len()
@@ -5175,6 +6957,31 @@ pub async fn run_cli() -> Result<()> {
                 total_size as f64 / 1_048_576.0
             );
 
+            // With compression, estimate cost and progress on the bytes actually uploaded;
+            // the compressed files are kept and uploaded as they are
+            let mut compressed_files: Vec<Option<compression::CompressedFile>> = Vec::new();
+            let upload_size = match compression {
+                Some(spec) => {
+                    println!("Compressing files...");
+                    let mut compressed_total = 0u64;
+                    for path in &file_entries {
+                        let compressed = compression::CompressedFile::new(path, spec)?;
+                        compressed_total += compressed.size()?;
+                        compressed_files.push(Some(compressed));
+                    }
+                    println!(
+                        "Compressed size: {:.2} MB ({:.1}x)",
+                        compressed_total as f64 / 1_048_576.0,
+                        total_size as f64 / compressed_total.max(1) as f64
+                    );
+                    compressed_total
+                }
+                None => {
+                    compressed_files.resize_with(file_entries.len(), || None);
+                    total_size
+                }
+            };
+
             // Check if user has enough tokens for the entire upload
             // Get tier pricing and concurrency
             let (fee_per_gb, tier_concurrency) =
@@ -5219,7 +7026,7 @@ pub async fn run_cli() -> Result<()> {
                     }
                 };
 
-            let total_cost_estimate = (total_size as f64 / 1_000_000_000.0) * fee_per_gb;
+            let total_cost_estimate = (upload_size as f64 / 1_000_000_000.0) * fee_per_gb;
 
             // Get current token balance
             let balance_url = format!("{}/checkCustomToken", base_url);
@@ -5312,7 +7119,7 @@ pub async fn run_cli() -> Result<()> {
             }
 
             // Create shared progress bar
-            let progress = Arc::new(ProgressBar::new(total_size));
+            let progress = Arc::new(ProgressBar::new(upload_size));
             progress.set_style(
                 ProgressStyle::default_bar()
                     .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta}) - {msg}")
@@ -5340,7 +7147,7 @@ pub async fn run_cli() -> Result<()> {
             let failed_count = Arc::new(TokioMutex::new(0u32));
             let total_cost = Arc::new(TokioMutex::new(0.0f64));
 
-            for path in file_entries {
+            for (path, compressed) in file_entries.into_iter().zip(compressed_files) {
                 let sem_clone = Arc::clone(&sem);
                 let client_clone = client.clone();
                 let base_url_clone = base_url.to_string();
@@ -5361,8 +7168,9 @@ pub async fn run_cli() -> Result<()> {
                         .unwrap_or_else(|| "untitled".to_string()),
                 };
                 let tier_clone = tier.clone();
-                let encrypt_clone = encrypt;
-                let password_clone = encryption_password.clone();
+                let encryption_clone = encryption.clone();
+                let record_manifest = manifest_signer.is_some();
+                let manifest_entries_clone = manifest_entries.clone();
 
                 let handle = tokio::spawn(async move {
                     let _permit = sem_clone.acquire_owned().await.unwrap();
@@ -5406,8 +7214,8 @@ pub async fn run_cli() -> Result<()> {
                                 &url,
                                 &rel_path,
                                 &creds_clone,
-                                encrypt_clone,
-                                password_clone.clone(),
+                                encryption_clone.clone(),
+                                compressed.as_ref(),
                                 Some(shared_progress_clone.clone()),
                             )
                         })
@@ -5429,12 +7237,31 @@ pub async fn run_cli() -> Result<()> {
                                 completed_val, file_count_copy
                             ));
 
-                            let _ = append_to_upload_log(
+                            // The manifest entry carries the hash and size for the catalog too
+                            let entry =
+                                manifest_entry(&path, &rel_path, &uploaded_file, manifest_mode, compression).await;
+                            let (blake3_hash, file_size) = match entry {
+                                Ok(ref entry) => (Some(entry.blake3.clone()), Some(entry.size)),
+                                Err(ref e) => {
+                                    eprintln!("Failed to hash {}: {}", rel_path, e);
+                                    (None, None)
+                                }
+                            };
+                            let _ = append_to_upload_log_with_hash(
                                 &path.display().to_string(),
                                 &uploaded_file,
                                 "SUCCESS",
                                 "Directory upload success",
+                                blake3_hash,
+                                file_size,
+                                (cost > 0.0).then_some(cost),
                             );
+
+                            if record_manifest {
+                                if let Ok(entry) = entry {
+                                    manifest_entries_clone.lock().await.push(entry);
+                                }
+                            }
                         }
                         Err(e) => {
                             let mut failed = failed_clone.lock().await;
@@ -5471,10 +7298,225 @@ pub async fn run_cli() -> Result<()> {
             if final_cost > 0.0 {
                 println!("  💰 Total cost: {:.4} PIPE tokens", final_cost);
             }
+
+            if let Some(material) = manifest_signer {
+                let entries = std::mem::take(&mut *manifest_entries.lock().await);
+                let source = dir
+                    .canonicalize()?
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "upload".to_string());
+                let entry_count = entries.len();
+                let signed = manifest::UploadManifest::new(&source, entries).sign(
+                    material
+                        .private_key
+                        .as_deref()
+                        .ok_or_else(|| anyhow!("Signing key has no secret key material"))?,
+                    material
+                        .public_key
+                        .as_deref()
+                        .ok_or_else(|| anyhow!("Signing key has no public key"))?,
+                )?;
+
+                // Keep a local copy and upload one next to the files
+                let manifest_name = format!("{}.pipe-manifest.json", source);
+                std::fs::write(&manifest_name, signed.to_json()?)?;
+                let selected_endpoint = get_endpoint_for_operation(
+                    &service_cache,
+                    &client,
+                    base_url,
+                    "upload",
+                    &creds.user_id,
+                    Some(&manifest_name),
+                )
+                .await;
+                let url = format!(
+                    "{}/upload?file_name={}",
+                    selected_endpoint,
+                    utf8_percent_encode(&manifest_name, QUERY_ENCODE_SET)
+                );
+                upload_with_retry(&format!("upload of {}", manifest_name), || {
+                    upload_file_with_shared_progress(
+                        &client,
+                        Path::new(&manifest_name),
+                        &url,
+                        &manifest_name,
+                        &creds,
+                        None,
+                    )
+                })
+                .await?;
+
+                println!("\n📜 Signed manifest of {} files uploaded as {}", entry_count, manifest_name);
+                println!("   Local copy: {}", manifest_name);
+                print_fingerprint("   ", "Signer fingerprint", &signed.signer_fingerprint()?);
+                if failed > 0 {
+                    println!("   Files that failed to upload are not in the manifest.");
+                }
+            }
+
+            println!(
+                "\nCheck the upload catalog for details ('pipe list-uploads'):\n  {}",
+                catalog::Catalog::default_path().display()
+            );
+        }
+
+        Commands::VerifyManifest {
+            manifest,
+            remote,
+            dir,
+            download,
+            signer,
+            password,
+        } => {
+            let keyring = keyring::Keyring::load_from_file(&keyring::Keyring::default_path()?)?;
+
+            // Credentials are only needed to download
+            let creds = if remote || download {
+                let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
+                    anyhow!("No credentials found. Please create a user or login first.")
+                })?;
+                ensure_valid_token(&client, base_url, &mut creds, config_path).await?;
+                Some(creds)
+            } else {
+                None
+            };
+
+            let signed = match creds {
+                Some(ref creds) if remote => {
+                    let selected_endpoint = get_endpoint_for_operation(
+                        &service_cache,
+                        &client,
+                        base_url,
+                        "download",
+                        &creds.user_id,
+                        Some(&manifest),
+                    )
+                    .await;
+                    let temp = tempfile::NamedTempFile::new()?;
+                    improved_download_file_with_auth_and_options(
+                        &client,
+                        &selected_endpoint,
+                        creds,
+                        &manifest,
+                        &temp.path().to_string_lossy(),
+                        false,
+                    )
+                    .await?;
+                    manifest::SignedManifest::load_from_file(temp.path())?
+                }
+                _ => manifest::SignedManifest::load_from_file(Path::new(&manifest))?,
+            };
+
+            signed.verify()?;
+            let signer_key = signed.signer_public_key()?;
+            if let Some(ref expected) = signer {
+                let (expected_key, _) = resolve_signer_public_key(&keyring, expected)?.ok_or_else(|| {
+                    anyhow!("Public key file, contact or signing key not found: {}", expected)
+                })?;
+                if expected_key != signer_key {
+                    return Err(anyhow!(
+                        "Manifest is signed by {}, not by '{}'",
+                        keyring::fingerprint_grouped(&signed.signer_fingerprint()?),
+                        expected
+                    ));
+                }
+            }
+
+            let listed = &signed.manifest;
+            println!("✅ Manifest signature is valid");
+            print_signer(&keyring, &signer_key);
+            println!(
+                "   Source: {} ({} files, created {})",
+                listed.source,
+                listed.entries.len(),
+                listed.created_at.format("%Y-%m-%d %H:%M:%S")
+            );
+
+            let creds = match (dir.as_deref(), download, creds) {
+                (None, false, _) => {
+                    println!("\nPass --dir or --download to check the files themselves.");
+                    return Ok(());
+                }
+                (Some(_), true, _) => return Err(anyhow!("Use either --dir or --download")),
+                (_, _, creds) => creds,
+            };
+
+            // One password prompt for all password-encrypted files
+            let password = match password {
+                None if download && listed.entries.iter().any(|e| e.encryption == "password") => {
+                    Some(rpassword::prompt_password("Enter decryption password: ")?)
+                }
+                other => other,
+            };
+
+            println!();
+            let mut mismatched = 0;
+            for entry in &listed.entries {
+                let result = match (dir.as_deref(), creds.as_ref()) {
+                    (Some(dir), _) => manifest::check_file(entry, &Path::new(dir).join(&entry.path)),
+                    (None, Some(creds)) => {
+                        // Download and decrypt into a scratch directory
+                        let scratch = tempfile::tempdir()?;
+                        let selected_endpoint = get_endpoint_for_operation(
+                            &service_cache,
+                            &client,
+                            base_url,
+                            "download",
+                            &creds.user_id,
+                            Some(&entry.remote_path),
+                        )
+                        .await;
+                        let options = DecryptOptions {
+                            password: password.clone(),
+                            key: None,
+                            quantum_password: false,
+                            quantum: false,
+                        };
+                        download_file_with_decryption_and_options(
+                            &client,
+                            &selected_endpoint,
+                            creds,
+                            &entry.remote_path,
+                            &scratch.path().to_string_lossy(),
+                            false,
+                            options,
+                            false,
+                        )
+                        .await
+                        .and_then(|downloaded| manifest::check_file(entry, &downloaded))
+                    }
+                    (None, None) => unreachable!("credentials are loaded for --download"),
+                };
+
+                match result {
+                    Ok(manifest::EntryCheck::Ok) => println!("  ✅ {}", entry.path),
+                    Ok(problem) => {
+                        mismatched += 1;
+                        match problem {
+                            manifest::EntryCheck::Missing => println!("  ❌ {}: missing", entry.path),
+                            manifest::EntryCheck::SizeMismatch { actual } => println!(
+                                "  ❌ {}: size is {} bytes, manifest says {}",
+                                entry.path, actual, entry.size
+                            ),
+                            _ => println!("  ❌ {}: content differs (Blake3 mismatch)", entry.path),
+                        }
+                    }
+                    Err(e) => {
+                        mismatched += 1;
+                        println!("  ❌ {}: {}", entry.path, e);
+                    }
+                }
+            }
+
             println!(
-                "\nCheck the log file for details:\n  {}",
-                get_upload_log_path().display()
+                "\n{} of {} files match the manifest",
+                listed.entries.len() - mismatched,
+                listed.entries.len()
             );
+            if mismatched > 0 {
+                return Err(anyhow!("{} file(s) do not match the manifest", mismatched));
+            }
         }
 
         Commands::PriorityUploadDirectory {
@@ -5521,19 +7563,7 @@ pub async fn run_cli() -> Result<()> {
             // Read upload log if skip_uploaded == true
             let mut previously_uploaded: HashSet<String> = HashSet::new();
             if skip_uploaded {
-                let log_path = get_upload_log_path();
-                if log_path.exists() {
-                    let contents = fs::read_to_string(&log_path)?;
-                    for line in contents.lines() {
-                        if let Ok(entry) = serde_json::from_str::<UploadLogEntry>(line) {
-                            if entry.status.contains("SUCCESS")
-                                || entry.status.contains("BACKGROUND")
-                            {
-                                previously_uploaded.insert(entry.local_path);
-                            }
-                        }
-                    }
-                }
+                previously_uploaded = catalog::Catalog::open_default()?.uploaded_local_paths()?;
                 println!(
                     "Found {} previously uploaded files in log",
                     previously_uploaded.len()
@@ -5768,11 +7798,14 @@ pub async fn run_cli() -> Result<()> {
                                 completed_val, file_count_copy
                             ));
 
-                            let _ = append_to_upload_log(
+                            let _ = append_to_upload_log_with_hash(
                                 &path.display().to_string(),
                                 &uploaded_file,
                                 "PRIORITY SUCCESS",
                                 "Priority directory upload success",
+                                calculate_blake3(&path).await.ok(),
+                                std::fs::metadata(&path).ok().map(|m| m.len()),
+                                (cost > 0.0).then_some(cost),
                             );
                         }
                         Err(e) => {
@@ -5811,8 +7844,8 @@ pub async fn run_cli() -> Result<()> {
                 );
             }
             println!(
-                "\nCheck the log file for details:\n  {}",
-                get_upload_log_path().display()
+                "\nCheck the upload catalog for details ('pipe list-uploads'):\n  {}",
+                catalog::Catalog::default_path().display()
             );
         }
 
@@ -6028,6 +8061,7 @@ pub async fn run_cli() -> Result<()> {
                         &format!("Priority upload ({} epochs)", epochs_final),
                         Some(blake3_hash.clone()),
                         Some(file_size),
+                        (token_cost > 0.0).then_some(token_cost),
                     )?;
                     println!("📋 File ID (Blake3): {}", blake3_hash);
                 }
@@ -6074,29 +8108,162 @@ pub async fn run_cli() -> Result<()> {
             }
         }
 
-        Commands::ListUploads => {
-            let log_path = get_upload_log_path();
-            if !log_path.exists() {
-                println!("No upload log found at {}", log_path.display());
-            } else {
-                let contents = fs::read_to_string(&log_path)?;
-                for (i, line) in contents.lines().enumerate() {
-                    if let Ok(entry) = serde_json::from_str::<UploadLogEntry>(line) {
-                        println!(
-                            "{}: local='{}', remote='{}', status='{}', msg='{}'",
-                            i + 1,
-                            entry.local_path,
-                            entry.remote_path,
-                            entry.status,
-                            entry.message
-                        );
+        Commands::Ls {
+            prefix,
+            long,
+            recursive,
+            tree,
+            json,
+            refresh,
+        } => {
+            let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
+                anyhow!("No credentials found. Please create a user or login first.")
+            })?;
+            ensure_valid_token(&client, base_url, &mut creds, config_path).await?;
+
+            let prefix = prefix.unwrap_or_default();
+            let entries = listing::fetch_remote_entries(&client, base_url, &creds, refresh).await?;
+
+            if tree {
+                for line in listing::render_tree(&entries, &prefix, long) {
+                    println!("{}", line);
+                }
+                return Ok(());
+            }
+
+            if recursive {
+                let files: Vec<&listing::RemoteEntry> = listing::matching(&entries, &prefix).collect();
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&files)?);
+                    return Ok(());
+                }
+                for file in &files {
+                    if long {
+                        println!("{}", format_listing_line(file.size, file.uploaded_at, &file.name));
                     } else {
-                        println!("{}: (unparseable JSON) => {}", i + 1, line);
+                        println!("{}", file.name);
+                    }
+                }
+                if long {
+                    let total: u64 = files.iter().map(|f| f.size).sum();
+                    println!("{} files, {}", files.len(), sync::format_file_size(total));
+                }
+                return Ok(());
+            }
+
+            let items = listing::list_level(&entries, &prefix);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&items)?);
+                return Ok(());
+            }
+            for item in &items {
+                match item {
+                    listing::ListItem::File(file) if long => {
+                        println!("{}", format_listing_line(file.size, file.uploaded_at, &file.name))
                     }
+                    listing::ListItem::File(file) => println!("{}", file.name),
+                    listing::ListItem::Dir { name, files, size } if long => println!(
+                        "{}",
+                        format_listing_line(*size, None, &format!("{} ({} files)", name, files))
+                    ),
+                    listing::ListItem::Dir { name, .. } => println!("{}", name),
                 }
             }
         }
 
+        Commands::ListUploads {
+            status,
+            prefix,
+            since,
+            until,
+            min_size,
+            hash,
+            sort,
+            limit,
+            offset,
+            columns,
+        } => {
+            let columns = columns
+                .split(',')
+                .map(|c| UploadColumn::parse(c.trim()))
+                .collect::<Result<Vec<_>>>()?;
+            let query = catalog::UploadQuery {
+                status,
+                remote_prefix: prefix,
+                since: since.as_deref().map(parse_date_arg).transpose()?,
+                // --until includes the whole day
+                until: until
+                    .as_deref()
+                    .map(parse_date_arg)
+                    .transpose()?
+                    .map(|date| date + chrono::Duration::days(1)),
+                min_size: min_size.as_deref().map(parse_size).transpose()?,
+                hash_prefix: hash,
+                sort: sort
+                    .as_deref()
+                    .map(catalog::UploadSort::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
+                limit,
+                offset,
+            };
+
+            let catalog = catalog::Catalog::open_default()?;
+            let entries = catalog.search(&query)?;
+            let totals = catalog.totals(&query)?;
+            if totals.count == 0 {
+                println!("No matching uploads in {}", catalog::Catalog::default_path().display());
+                return Ok(());
+            }
+
+            let rows: Vec<Vec<String>> = entries
+                .iter()
+                .map(|e| columns.iter().map(|c| c.value(e)).collect())
+                .collect();
+            let header: Vec<String> = columns.iter().map(|c| c.title().to_string()).collect();
+            let widths: Vec<usize> = (0..columns.len())
+                .map(|i| {
+                    rows.iter()
+                        .map(|row| row[i].chars().count())
+                        .chain(std::iter::once(header[i].len()))
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            for row in std::iter::once(&header).chain(rows.iter()) {
+                let line: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(value, width)| format!("{:width$}", value, width = width))
+                    .collect();
+                println!("{}", line.join("  ").trim_end());
+            }
+
+            println!();
+            let shown = if entries.is_empty() {
+                format!("Showing none of {} uploads", totals.count)
+            } else if entries.len() == totals.count {
+                format!("{} upload{}", totals.count, if totals.count == 1 { "" } else { "s" })
+            } else {
+                format!(
+                    "Showing {}-{} of {} uploads",
+                    offset + 1,
+                    offset + entries.len(),
+                    totals.count
+                )
+            };
+            let mut size = format!("{} total", sync::format_file_size(totals.total_size));
+            if totals.unknown_size > 0 {
+                size.push_str(&format!(" ({} without size)", totals.unknown_size));
+            }
+            let statuses: Vec<String> = totals
+                .by_status
+                .iter()
+                .map(|(status, count)| format!("{}: {}", status, count))
+                .collect();
+            println!("📊 {} · {} · {}", shown, size, statuses.join(", "));
+        }
+
         Commands::ExtendStorage {
             user_id,
             user_app_key,
@@ -6144,6 +8311,8 @@ pub async fn run_cli() -> Result<()> {
             }
 
             let resp = request.send().await?;
+            // Listings show the expiry date
+            listing::invalidate_cache();
             let status = resp.status();
             let text_body = resp.text().await?;
 
@@ -6163,8 +8332,11 @@ pub async fn run_cli() -> Result<()> {
         }
 
         Commands::VerifyFile {
-            file_name: _,
-            file_id: _,
+            file_name,
+            file_id,
+            prefix,
+            all: _,
+            parallel,
             user_id,
             user_app_key,
         } => {
@@ -6180,39 +8352,380 @@ pub async fn run_cli() -> Result<()> {
             if let Some(key) = user_app_key {
                 creds.user_app_key = key;
             }
-            
-            println!("Verifying file integrity...");
-            println!("Feature not fully implemented yet - requires server-side support");
-            // TODO: Call server API to get file hash and verify
-        }
-        
-        Commands::FindUpload {
-            query,
-            by_hash,
-        } => {
-            let entries = read_upload_log_entries(None)?;
-            let mut found = Vec::new();
-            
-            for entry in entries {
-                if by_hash {
-                    if let Some(ref hash) = entry.blake3_hash {
-                        if hash.starts_with(&query) {
-                            found.push(entry);
-                        }
-                    }
-                } else {
-                    // Search by local path
-                    if entry.local_path.contains(&query) {
-                        found.push(entry);
+
+            let catalog = catalog::Catalog::open_default()?;
+
+            let Some(file_name) = file_name else {
+                let prefix = prefix.unwrap_or_default();
+                let entries = catalog.latest_uploads(&prefix)?;
+                if entries.is_empty() {
+                    println!("No catalogued uploads under '{}'", prefix);
+                    return Ok(());
+                }
+                println!("🔍 Verifying {} file(s)...", entries.len());
+                let results = integrity::verify_entries(
+                    &client,
+                    base_url,
+                    &creds,
+                    entries,
+                    parallel,
+                    print_verification,
+                )
+                .await;
+                return report_verifications(&results);
+            };
+
+            // The newest record with a hash is what the remote copy should match
+            let records = if file_id {
+                catalog.find_by_hash_prefix(&file_name)?
+            } else {
+                catalog.find_by_remote_path(&file_name)?
+            };
+            let record = records
+                .iter()
+                .rev()
+                .find(|r| r.entry.blake3_hash.is_some())
+                .or(records.last());
+            let remote_path = match record {
+                Some(r) => r.entry.remote_path.clone(),
+                None if file_id => {
+                    return Err(anyhow!("No catalogued upload with Blake3 hash '{}'", file_name))
+                }
+                None => file_name,
+            };
+            let expected = record.and_then(|r| r.entry.blake3_hash.clone());
+
+            println!("🔍 Verifying '{}'...", remote_path);
+            match expected {
+                Some(ref hash) => println!("   Expected Blake3: {}", hash),
+                None => {
+                    // Nothing to compare with; show the hash for a manual check
+                    println!("   No Blake3 hash recorded in the upload catalog");
+                    if let integrity::StreamDigest::Blake3 { hash, size } =
+                        integrity::hash_remote_file(&client, base_url, &creds, &remote_path).await?
+                    {
+                        println!("   Remote Blake3: {} ({} bytes)", hash, size);
                     }
                 }
             }
+            let verification = integrity::Verification {
+                result: integrity::verify_remote_file(
+                    &client,
+                    base_url,
+                    &creds,
+                    &remote_path,
+                    expected.as_deref(),
+                )
+                .await,
+                remote_path,
+                expected,
+            };
+            print_verification(&verification);
+            report_verifications(std::slice::from_ref(&verification))?;
+        }
+        
+        Commands::Audit {
+            sample,
+            prefix,
+            parallel,
+        } => {
+            let sample = integrity::SampleSize::parse(&sample)?;
+            let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
+                anyhow!("No credentials found. Please create a user or login first.")
+            })?;
+            ensure_valid_token(&client, base_url, &mut creds, config_path).await?;
+
+            let prefix = prefix.unwrap_or_default();
+            let scope = if prefix.is_empty() { "all files".to_string() } else { format!("'{}'", prefix) };
+            let catalog = catalog::Catalog::open_default()?;
+            let candidates = catalog.latest_uploads(&prefix)?;
+            if candidates.is_empty() {
+                println!("No catalogued uploads under {}", scope);
+                return Ok(());
+            }
+
+            let total = candidates.len();
+            let picked = integrity::choose_sample(candidates, sample);
+            println!("🔍 Auditing {} of {} catalogued file(s) under {}...", picked.len(), total, scope);
+            let results =
+                integrity::verify_entries(&client, base_url, &creds, picked, parallel, print_verification)
+                    .await;
+            for verification in &results {
+                let (result, detail) = verification.audit_result();
+                catalog.record_audit(&verification.remote_path, result, detail.as_deref())?;
+            }
+            let outcome = report_verifications(&results);
+
+            println!("\n📋 Audit history for {}:", scope);
+            let never = catalog.never_audited(&prefix)?;
+            println!("   {} of {} file(s) never had their hash checked", never.len(), total);
+            for path in never.iter().take(10) {
+                println!("      {}", path);
+            }
+            if never.len() > 10 {
+                println!("      … and {} more", never.len() - 10);
+            }
+
+            let unverifiable = catalog.unverifiable_audits(&prefix)?;
+            if !unverifiable.is_empty() {
+                println!("   {} file(s) could not be verified in their latest audit:", unverifiable.len());
+                unverifiable.iter().take(10).for_each(print_audit_record);
+                if unverifiable.len() > 10 {
+                    println!("      … and {} more", unverifiable.len() - 10);
+                }
+            }
+
+            let failed = catalog.failed_audits(&prefix)?;
+            if failed.is_empty() {
+                println!("   No file failed its latest audit");
+            } else {
+                println!("   {} file(s) failed their latest audit:", failed.len());
+                failed.iter().for_each(print_audit_record);
+            }
+            outcome?;
+        }
+
+        Commands::Reconcile {
+            prefix,
+            fix_catalog,
+            reupload,
+            delete_orphans,
+            dry_run,
+            yes,
+        } => {
+            let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
+                anyhow!("No credentials found. Please create a user or login first.")
+            })?;
+            ensure_valid_token(&client, base_url, &mut creds, config_path).await?;
+
+            let catalog = catalog::Catalog::open_default()?;
+            let remote = sync::list_remote_files(&client, base_url, &creds, prefix.as_deref()).await?;
+            let records = catalog.search(&catalog::UploadQuery {
+                remote_prefix: prefix.clone(),
+                ..Default::default()
+            })?;
+            let scope = prefix.as_deref().map_or("all files".to_string(), |p| format!("'{}'", p));
+            println!(
+                "🔎 Reconciling {} catalog record(s) with {} remote file(s) under {}...",
+                records.len(),
+                remote.len(),
+                scope
+            );
+
+            let mut found = Vec::new();
+            for discrepancy in reconcile::reconcile(&remote, records) {
+                // Encrypted and compressed uploads are stored at a different
+                // size than the original, so only plain files can mismatch
+                if let reconcile::Discrepancy::SizeMismatch { ref record, .. } = discrepancy {
+                    let name = &record.entry.remote_path;
+                    if strip_encrypted_extension(name) != name {
+                        continue;
+                    }
+                    if let Ok(head) = fetch_file_head(&client, base_url, &creds, name, 16).await {
+                        if !matches!(
+                            encryption::detect_format(std::io::Cursor::new(&head)),
+                            Ok(encryption::EncryptionFormat::Plain)
+                        ) {
+                            continue;
+                        }
+                    }
+                }
+                found.push(discrepancy);
+            }
+
+            if found.is_empty() {
+                println!("✅ Catalog and remote storage agree");
+                return Ok(());
+            }
+            for discrepancy in &found {
+                let detail = match discrepancy {
+                    reconcile::Discrepancy::RemoteOnly { size, attempt, .. } => format!(
+                        "{}, {}",
+                        sync::format_file_size(*size),
+                        match attempt {
+                            Some(record) if record.entry.status.contains("SUCCESS") => "recorded as deleted",
+                            Some(_) => "upload recorded as failed",
+                            None => "orphan",
+                        }
+                    ),
+                    reconcile::Discrepancy::LogOnly(record) => format!("uploaded from {}", record.entry.local_path),
+                    reconcile::Discrepancy::SizeMismatch { record, remote_size } => format!(
+                        "catalog {}, remote {}",
+                        record.entry.file_size.map_or("-".to_string(), sync::format_file_size),
+                        sync::format_file_size(*remote_size)
+                    ),
+                    reconcile::Discrepancy::MissingHash(record) => format!("uploaded from {}", record.entry.local_path),
+                };
+                println!("   {:<14} {}  ({})", discrepancy.kind(), discrepancy.remote_path(), detail);
+            }
+            let count = |kind: &str| found.iter().filter(|d| d.kind() == kind).count();
+            println!(
+                "\n📊 {} remote-only ({} orphan), {} log-only, {} size mismatch, {} missing hash",
+                count("remote-only"),
+                found.iter().filter(|d| d.is_orphan()).count(),
+                count("log-only"),
+                count("size mismatch"),
+                count("missing hash")
+            );
+            if !(fix_catalog || reupload || delete_orphans) {
+                println!("Use --fix-catalog, --reupload or --delete-orphans to repair (add --dry-run to preview)");
+                return Ok(());
+            }
+            let would = if dry_run { "would " } else { "" };
+
+            if fix_catalog {
+                println!("\n📋 Catalog fixes:");
+                for discrepancy in &found {
+                    let path = discrepancy.remote_path();
+                    match discrepancy {
+                        reconcile::Discrepancy::RemoteOnly { size, attempt: Some(attempt), .. } => {
+                            println!("   {}record {} as uploaded", would, path);
+                            if !dry_run {
+                                catalog.record(&UploadLogEntry {
+                                    local_path: attempt.entry.local_path.clone(),
+                                    remote_path: attempt.entry.remote_path.clone(),
+                                    status: "SUCCESS".to_string(),
+                                    message: "Reconciled: found in remote storage".to_string(),
+                                    blake3_hash: attempt.entry.blake3_hash.clone(),
+                                    file_size: Some(*size),
+                                    timestamp: Some(chrono::Utc::now()),
+                                    cost: None,
+                                })?;
+                            }
+                        }
+                        reconcile::Discrepancy::LogOnly(record) => {
+                            println!("   {}record {} as deleted", would, path);
+                            if !dry_run {
+                                catalog.record(&UploadLogEntry {
+                                    local_path: record.entry.local_path.clone(),
+                                    remote_path: record.entry.remote_path.clone(),
+                                    status: catalog::DELETED_STATUS.to_string(),
+                                    message: "Reconciled: missing from remote storage".to_string(),
+                                    blake3_hash: None,
+                                    file_size: None,
+                                    timestamp: Some(chrono::Utc::now()),
+                                    cost: None,
+                                })?;
+                            }
+                        }
+                        reconcile::Discrepancy::MissingHash(record) => {
+                            let local_path = Path::new(&record.entry.local_path);
+                            let local_size = fs::metadata(local_path).map(|m| m.len());
+                            match local_size {
+                                Ok(size) if record.entry.file_size.is_none_or(|recorded| recorded == size) => {
+                                    println!("   {}hash {} from {}", would, path, local_path.display());
+                                    if !dry_run {
+                                        let hash = calculate_blake3(local_path).await?;
+                                        catalog.set_hash(record.id, &hash, size)?;
+                                    }
+                                }
+                                Ok(_) => println!("   skip {}: local file changed since upload", path),
+                                Err(_) => println!("   skip {}: local file {} is gone", path, local_path.display()),
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            if reupload {
+                println!("\n📤 Re-uploads:");
+                for discrepancy in &found {
+                    let record = match discrepancy {
+                        reconcile::Discrepancy::LogOnly(record)
+                        | reconcile::Discrepancy::SizeMismatch { record, .. } => record,
+                        _ => continue,
+                    };
+                    let name = &record.entry.remote_path;
+                    let local_path = Path::new(&record.entry.local_path);
+                    if strip_encrypted_extension(name) != name {
+                        println!("   skip {}: encrypted uploads need 'pipe upload-file' with a key", name);
+                        continue;
+                    }
+                    if !local_path.is_file() {
+                        println!("   skip {}: local file {} is gone", name, local_path.display());
+                        continue;
+                    }
+                    let hash = calculate_blake3(local_path).await?;
+                    if record.entry.blake3_hash.as_ref().is_some_and(|recorded| !recorded.eq_ignore_ascii_case(&hash)) {
+                        println!("   skip {}: local file changed since upload", name);
+                        continue;
+                    }
+                    println!("   {}upload {} as {}", would, local_path.display(), name);
+                    if dry_run {
+                        continue;
+                    }
+
+                    let url = format!("{}/upload?file_name={}", base_url, utf8_percent_encode(name, QUERY_ENCODE_SET));
+                    match upload_with_retry(&format!("upload of {}", name), || {
+                        upload_file_with_shared_progress(&client, local_path, &url, name, &creds, None)
+                    })
+                    .await
+                    {
+                        Ok((uploaded, cost)) => append_to_upload_log_with_hash(
+                            &record.entry.local_path,
+                            &uploaded,
+                            "SUCCESS",
+                            "Re-uploaded by reconcile",
+                            Some(hash),
+                            Some(fs::metadata(local_path)?.len()),
+                            (cost > 0.0).then_some(cost),
+                        )?,
+                        Err(e) => eprintln!("   ❌ Failed to upload {}: {}", name, e),
+                    }
+                }
+            }
+
+            if delete_orphans {
+                let orphans: Vec<&str> = found
+                    .iter()
+                    .filter(|d| d.is_orphan())
+                    .map(|d| d.remote_path())
+                    .collect();
+                // Listed before any prompt, and even with --yes, so every deletion is on record
+                println!("\n🗑️  Orphans (no upload record in this machine's catalog):");
+                for name in &orphans {
+                    println!("   {}delete {}", would, name);
+                }
+                if !dry_run && !orphans.is_empty() {
+                    let confirmed = yes || {
+                        print!("Delete {} remote file(s)? [y/N]: ", orphans.len());
+                        std::io::stdout().flush()?;
+                        let mut response = String::new();
+                        std::io::stdin().read_line(&mut response)?;
+                        response.trim().eq_ignore_ascii_case("y")
+                    };
+                    if !confirmed {
+                        println!("Deletion cancelled.");
+                    } else {
+                        for name in orphans {
+                            match delete_remote_file(&client, base_url, &creds, name).await {
+                                Ok(_) => println!("   ✅ Deleted {}", name),
+                                Err(e) => eprintln!("   ❌ Failed to delete {}: {}", name, e),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::FindUpload {
+            query,
+            by_hash,
+        } => {
+            let catalog = catalog::Catalog::open_default()?;
+            let found = if by_hash {
+                catalog.find_by_hash_prefix(&query)?
+            } else {
+                // Search by local path
+                catalog.find_by_local_path(&query)?
+            };
             
             if found.is_empty() {
                 println!("No uploads found matching '{}'", query);
             } else {
                 println!("Found {} matching upload(s):", found.len());
-                for entry in found {
+                for catalog::CatalogEntry { entry, .. } in found {
                     println!("\n  Local: {}", entry.local_path);
                     println!("  Remote: {}", entry.remote_path);
                     println!("  Status: {}", entry.status);
@@ -6230,14 +8743,15 @@ pub async fn run_cli() -> Result<()> {
         }
         
         Commands::RehashUploads { verbose } => {
-            let mut entries = read_upload_log_entries(None)?;
+            let catalog = catalog::Catalog::open_default()?;
+            let entries = catalog.entries()?;
             let total = entries.len();
             let mut updated = 0;
             let mut failed = 0;
             
             println!("Rehashing {} upload entries...", total);
             
-            for entry in &mut entries {
+            for catalog::CatalogEntry { id, entry } in &entries {
                 if entry.blake3_hash.is_none() {
                     let path = Path::new(&entry.local_path);
                     if path.exists() {
@@ -6246,8 +8760,7 @@ pub async fn run_cli() -> Result<()> {
                         }
                         match calculate_blake3(path).await {
                             Ok(hash) => {
-                                entry.blake3_hash = Some(hash);
-                                entry.file_size = Some(std::fs::metadata(path)?.len());
+                                catalog.set_hash(*id, &hash, std::fs::metadata(path)?.len())?;
                                 updated += 1;
                             }
                             Err(e) => {
@@ -6266,20 +8779,7 @@ pub async fn run_cli() -> Result<()> {
                 }
             }
             
-            // Rewrite the upload log
             if updated > 0 {
-                let log_path = get_upload_log_path();
-                let mut file = OpenOptions::new()
-                    .create(true)
-                    .truncate(true)
-                    .write(true)
-                    .open(&log_path)?;
-                    
-                for entry in entries {
-                    let json_line = serde_json::to_string(&entry)?;
-                    writeln!(file, "{}", json_line)?;
-                }
-                
                 println!("\n✅ Rehashing complete!");
                 println!("  Updated: {} entries", updated);
                 println!("  Failed: {} entries", failed);
@@ -6299,6 +8799,7 @@ pub async fn run_cli() -> Result<()> {
             max_size: _,
             newer_than: _,
             parallel,
+            compress,
         } => {
             // Load credentials
             let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
@@ -6312,6 +8813,11 @@ pub async fn run_cli() -> Result<()> {
             let conflict_strategy = sync::ConflictStrategy::from_str(&conflict)
                 .ok_or_else(|| anyhow!("Invalid conflict strategy: {}", conflict))?;
 
+            let compression = compress
+                .as_deref()
+                .map(compression::CompressionSpec::parse)
+                .transpose()?;
+
             // Execute sync
             sync::sync_command(
                 &client,
@@ -6322,6 +8828,7 @@ pub async fn run_cli() -> Result<()> {
                 conflict_strategy,
                 dry_run,
                 parallel,
+                compression,
             )
             .await?;
         }
@@ -6330,19 +8837,18 @@ pub async fn run_cli() -> Result<()> {
             input_file,
             output_file,
             password,
+            envelope,
+            recipient,
+            compress,
         } => {
-            // Get password if not provided
-            let password = match password {
-                Some(p) => p,
-                None => {
-                    let password = rpassword::prompt_password("Enter encryption password: ")?;
-                    let confirm = rpassword::prompt_password("Confirm encryption password: ")?;
-                    if password != confirm {
-                        return Err(anyhow!("Passwords do not match"));
-                    }
-                    password
-                }
-            };
+            let compression = compress
+                .as_deref()
+                .map(compression::CompressionSpec::parse)
+                .transpose()?;
+
+            // Get password or master key
+            let encryption = resolve_upload_encryption(true, envelope, true, password, recipient.as_deref())?
+                .ok_or_else(|| anyhow!("No encryption key resolved"))?;
 
             println!("Encrypting {} -> {}", input_file, output_file);
 
@@ -6363,13 +8869,11 @@ pub async fn run_cli() -> Result<()> {
                 pb.inc(bytes as u64);
             });
 
-            crate::encryption::encrypt_file_with_password(
-                input,
-                output,
-                &password,
-                Some(progress_callback),
-            )
-            .await?;
+            let metadata = crate::encryption::FileMetadata::from_path(Path::new(&input_file))?;
+
+            encryption
+                .encrypt_file(input, output, compression, Some(&metadata), Some(progress_callback))
+                .await?;
 
             println!("✅ File encrypted successfully!");
             println!("   Original: {} ({} bytes)", input_file, file_size);
@@ -6384,27 +8888,21 @@ pub async fn run_cli() -> Result<()> {
             input_file,
             output_file,
             password,
+            key,
+            force,
         } => {
-            // Check if input file has encryption header
-            let mut check_file = std::fs::File::open(&input_file)?;
-            if !crate::encryption::is_encrypted_file(&mut check_file)? {
-                return Err(anyhow!(
-                    "File '{}' does not appear to be encrypted (missing PIPE-ENC header)",
-                    input_file
-                ));
-            }
-
-            // Get password if not provided
-            let password = match password {
-                Some(p) => p,
-                None => rpassword::prompt_password("Enter decryption password: ")?,
+            let output_path = match output_file {
+                Some(o) => PathBuf::from(o),
+                None => Path::new(&input_file)
+                    .parent()
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| PathBuf::from(".")),
             };
 
-            println!("Decrypting {} -> {}", input_file, output_file);
+            println!("Decrypting {} -> {}", input_file, output_path.display());
 
-            let input = std::fs::File::open(&input_file)?;
-            let output = std::fs::File::create(&output_file)?;
-            let file_size = input.metadata()?.len();
+            let file_size = std::fs::metadata(&input_file)?.len();
 
             // Create progress bar
             let pb = ProgressBar::new(file_size);
@@ -6419,31 +8917,199 @@ pub async fn run_cli() -> Result<()> {
                 pb.inc(bytes as u64);
             });
 
-            match crate::encryption::decrypt_file_with_password(
-                input,
-                output,
-                &password,
+            let options = DecryptOptions {
+                password,
+                key,
+                quantum_password: false,
+                quantum: false,
+            };
+
+            match decrypt_detected_file(
+                Path::new(&input_file),
+                &output_path,
+                &input_file,
+                options,
+                force,
                 Some(progress_callback),
             )
             .await
             {
-                Ok(_) => {
+                Ok(None) => {
+                    return Err(anyhow!(
+                        "File '{}' does not appear to be encrypted (no PIPE-ENC, PIPE-PQ1 or quantum header)",
+                        input_file
+                    ));
+                }
+                Ok(Some((final_path, metadata))) => {
                     println!("✅ File decrypted successfully!");
                     println!("   Encrypted: {} ({} bytes)", input_file, file_size);
                     println!(
                         "   Decrypted: {} ({} bytes)",
-                        output_file,
-                        std::fs::metadata(&output_file)?.len()
+                        final_path.display(),
+                        std::fs::metadata(&final_path)?.len()
                     );
+                    if let Some(m) = metadata {
+                        println!("   Integrity: Blake3 verified ({})", m.blake3);
+                    }
                 }
                 Err(e) => {
-                    // Clean up failed output file
-                    let _ = std::fs::remove_file(&output_file);
                     return Err(anyhow!("Decryption failed: {}", e));
                 }
             }
         }
 
+        Commands::Rekey {
+            files,
+            remote,
+            rotate,
+            keyring_key,
+        } => {
+            let store_path = envelope::MasterKeyStore::default_path()?;
+            if !store_path.exists() {
+                return Err(anyhow!(
+                    "No master key store at {}. Encrypt or upload with --envelope to create one.",
+                    store_path.display()
+                ));
+            }
+            let mut store = envelope::MasterKeyStore::load_from_file(&store_path)?;
+            let (mut kek, mut unlocked) = unlock_master_keys(&store)?;
+
+            // Plain `pipe rekey` changes the password; envelope files stay untouched
+            let change_protection =
+                keyring_key.is_some() || (!rotate && files.is_empty() && remote.is_empty());
+            if change_protection {
+                let (new_kek, protection) = match keyring_key {
+                    Some(key_name) => (
+                        keyring_protection_key(&key_name, keyring::KeyUse::Encrypt)?,
+                        envelope::Protection::Keyring { key_name },
+                    ),
+                    None => envelope::new_password_protection(&prompt_new_password(
+                        "Enter new master key password: ",
+                        "Confirm new master key password: ",
+                    )?)?,
+                };
+                store.reprotect(&unlocked, &new_kek, protection)?;
+                kek = new_kek;
+            }
+
+            if rotate {
+                store.rotate(&kek, &mut unlocked)?;
+            }
+
+            // Save before touching any files so a new master key is never lost
+            store.save_to_file(&store_path)?;
+            if change_protection {
+                println!("✅ Master keys are now protected by {}", store.protection());
+                println!("   No uploaded files need to change.");
+            }
+            let active_id = unlocked.active().0.to_string();
+            if rotate {
+                println!("✅ New master key {} is active", active_id);
+                println!(
+                    "   {} older master key(s) kept for files not yet rewrapped",
+                    store.key_count() - 1
+                );
+            }
+
+            let mut failed = 0;
+            for file in &files {
+                match rewrap_local_file(Path::new(file), &unlocked) {
+                    Ok(true) => println!("  ✅ Rewrapped {}", file),
+                    Ok(false) => println!("  ⏭️  {} already uses master key {}", file, active_id),
+                    Err(e) => {
+                        eprintln!("  ❌ {}: {}", file, e);
+                        failed += 1;
+                    }
+                }
+            }
+
+            if !remote.is_empty() {
+                let mut creds = load_credentials_from_file(config_path)?.ok_or_else(|| {
+                    anyhow!("No credentials found. Please create a user or login first.")
+                })?;
+                ensure_valid_token(&client, base_url, &mut creds, config_path).await?;
+
+                for remote_name in &remote {
+                    match rewrap_remote_file(&client, &service_cache, base_url, &creds, remote_name, &unlocked)
+                        .await
+                    {
+                        Ok(true) => println!("  ✅ Rewrapped {}", remote_name),
+                        Ok(false) => {
+                            println!("  ⏭️  {} already uses master key {}", remote_name, active_id)
+                        }
+                        Err(e) => {
+                            eprintln!("  ❌ {}: {}", remote_name, e);
+                            failed += 1;
+                        }
+                    }
+                }
+            }
+
+            if failed > 0 {
+                return Err(anyhow!("{} file(s) could not be rewrapped", failed));
+            }
+        }
+
+        Commands::Inspect { file, password } => {
+            let path = Path::new(&file);
+            let file_size = std::fs::metadata(path)?.len();
+
+            println!("📄 {}", file);
+            println!("   Size on disk: {} bytes", file_size);
+
+            if crate::encryption::is_encrypted_file(std::fs::File::open(path)?)? {
+                let info = crate::encryption::read_header_info(std::fs::File::open(path)?)?;
+                println!("   Format: PIPE-ENC v{} (AES-256-GCM)", info.version);
+                match (&info.master_key_id, info.password_protected) {
+                    (Some(id), _) => println!("   Key: envelope (data key wrapped by master key {})", id),
+                    (None, true) => println!("   Key: password (Argon2id)"),
+                    (None, false) => println!("   Key: external key"),
+                }
+                println!(
+                    "   Compression: {}",
+                    if info.is_compressed() { "zstd" } else { "none" }
+                );
+
+                match info.metadata_size {
+                    None => println!("   Metadata: none"),
+                    Some(len) => {
+                        println!("   Metadata: present ({} bytes, encrypted)", len);
+                        match password {
+                            Some(password) => {
+                                if let Some(m) = crate::encryption::read_metadata_with_password(
+                                    std::fs::File::open(path)?,
+                                    &password,
+                                )? {
+                                    println!("\n🔓 Original file:");
+                                    println!("   Name: {}", m.name);
+                                    println!("   Size: {} bytes", m.size);
+                                    if let Some(modified) =
+                                        m.modified.and_then(|secs| DateTime::from_timestamp(secs, 0))
+                                    {
+                                        println!(
+                                            "   Modified: {}",
+                                            modified.format("%Y-%m-%d %H:%M:%S UTC")
+                                        );
+                                    }
+                                    if let Some(mode) = m.mode {
+                                        println!("   Mode: {:o}", mode);
+                                    }
+                                    println!("   Blake3: {}", m.blake3);
+                                }
+                            }
+                            None => {
+                                println!("\n💡 Pass --password to reveal the original name, size, timestamps and hash");
+                            }
+                        }
+                    }
+                }
+            } else if compression::is_compressed_file(std::fs::File::open(path)?)? {
+                println!("   Format: PIPE-ZST (zstd compressed, not encrypted)");
+            } else {
+                println!("   Format: not a pipe-cli encrypted or compressed file");
+            }
+        }
+
         Commands::KeyGen {
             name,
             algorithm,
@@ -6457,24 +9123,7 @@ pub async fn run_cli() -> Result<()> {
             let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
 
             // Get keyring password
-            let keyring_password = if keyring.keys().is_empty() && !keyring.has_password() {
-                // First time setup - initialize keyring password
-                println!("🔐 Setting up keyring master password...");
-                let password = rpassword::prompt_password("Enter new keyring password: ")?;
-                let confirm = rpassword::prompt_password("Confirm keyring password: ")?;
-                if password != confirm {
-                    return Err(anyhow!("Passwords do not match"));
-                }
-                keyring.initialize_password(&password)?;
-                password
-            } else if keyring.is_legacy() {
-                // Legacy keyring - use hardcoded password
-                eprintln!("⚠️  Using legacy keyring password. Run 'pipe keyring-migrate' to set a custom password.");
-                "keyring-protection".to_string()
-            } else {
-                // Normal operation - prompt for password
-                rpassword::prompt_password("Enter keyring password: ")?
-            };
+            let keyring_password = keyring_password_for_new_key(&mut keyring)?;
 
             let key_name = match algo {
                 "aes256" => {
@@ -6489,9 +9138,13 @@ pub async fn run_cli() -> Result<()> {
                     println!("✍️  Generating Dilithium5 signing keypair (post-quantum)...");
                     keyring.generate_dilithium_keypair(name, description, &keyring_password)?
                 }
+                "hybrid" => {
+                    println!("✍️  Generating hybrid Ed25519 + Dilithium5 signing keypair...");
+                    keyring.generate_hybrid_signing_keypair(name, description, &keyring_password)?
+                }
                 _ => {
                     return Err(anyhow!(
-                        "Unknown algorithm: {}. Use: aes256, kyber1024, dilithium5",
+                        "Unknown algorithm: {}. Use: aes256, kyber1024, dilithium5, hybrid",
                         algo
                     ))
                 }
@@ -6570,6 +9223,323 @@ pub async fn run_cli() -> Result<()> {
             println!("   Please remember this password - it cannot be recovered!");
         }
 
+        Commands::KeyBackup {
+            key_name,
+            all,
+            shares,
+            threshold,
+            output_dir,
+        } => {
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+
+            let names: Vec<String> = match (key_name, all) {
+                (Some(name), false) => vec![name],
+                (None, true) => {
+                    let mut names: Vec<String> = keyring
+                        .list_keys()
+                        .into_iter()
+                        .filter(|(_, key)| !key.is_public_only())
+                        .map(|(name, _)| name.clone())
+                        .collect();
+                    names.sort();
+                    names
+                }
+                _ => return Err(anyhow!("Give either a key name or --all")),
+            };
+
+            let keyring_password = if keyring.is_legacy() {
+                "keyring-protection".to_string()
+            } else {
+                rpassword::prompt_password("Enter keyring password: ")?
+            };
+            let keys = names
+                .iter()
+                .map(|name| keyring.export_plain_key(name, &keyring_password))
+                .collect::<Result<Vec<_>>>()?;
+            // .qenc uploads can only be decrypted with these, so a full backup carries them too
+            let quantum = if all {
+                keyring.export_quantum_keys(&keyring_password)?
+            } else {
+                keyring::PlainQuantumKeys::default()
+            };
+            let payload = backup::BackupPayload { keys, quantum };
+            let backup_shares = backup::split_keys(&payload, shares, threshold)?;
+
+            // Update keyring with usage stats
+            keyring.save_to_file(&keyring_path)?;
+
+            println!(
+                "🔐 Backed up {} key(s) into {} shares; any {} recover them",
+                payload.keys.len(),
+                shares,
+                threshold
+            );
+            println!("   Keys: {}", names.join(", "));
+            if !payload.quantum.is_empty() {
+                println!(
+                    "   Quantum: {} identity keypair(s), keys for {} file(s)",
+                    payload.quantum.identities.len(),
+                    payload.quantum.files.len() + payload.quantum.identity_files.len()
+                );
+            }
+            println!("   Store each share separately. Recover with 'pipe key-recover'.\n");
+
+            for share in &backup_shares {
+                let mut text = format!(
+                    "Pipe key backup share {} of {} (needs {})\n\n{}\n",
+                    share.index(),
+                    shares,
+                    threshold,
+                    share.to_text()
+                );
+                match share.to_qr() {
+                    Ok(qr) => text.push_str(&format!("\n{}\n", qr)),
+                    Err(_) => text.push_str("\n(Share is too large for a QR code; keep the text form.)\n"),
+                }
+
+                match output_dir {
+                    Some(ref dir) => {
+                        std::fs::create_dir_all(dir)?;
+                        let path = Path::new(dir).join(format!("share-{}-of-{}.txt", share.index(), shares));
+                        std::fs::write(&path, &text)?;
+                        #[cfg(unix)]
+                        {
+                            use std::os::unix::fs::PermissionsExt;
+                            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+                        }
+                        println!("   Share {} written to {}", share.index(), path.display());
+                    }
+                    None => println!("{}", text),
+                }
+            }
+        }
+
+        Commands::KeyRecover { shares } => {
+            // Each argument is a share file or the share text itself
+            let mut lines = Vec::new();
+            if shares.is_empty() {
+                println!("Paste shares, one per line, then an empty line:");
+                for line in std::io::stdin().lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    lines.push(line);
+                }
+            }
+            for arg in &shares {
+                if Path::new(arg).is_file() {
+                    lines.extend(std::fs::read_to_string(arg)?.lines().map(str::to_string));
+                } else {
+                    lines.push(arg.clone());
+                }
+            }
+
+            let parsed = lines
+                .iter()
+                .filter(|line| line.trim().starts_with("pipe-share-"))
+                .map(|line| backup::BackupShare::parse(line))
+                .collect::<Result<Vec<_>>>()?;
+            let payload = backup::recover_keys(&parsed)?;
+
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            let keyring_password = keyring_password_for_new_key(&mut keyring)?;
+            if !keyring.verify_password(&keyring_password)? {
+                return Err(anyhow!("Invalid keyring password"));
+            }
+
+            // A key that cannot be added is reported and the rest are still restored
+            let mut skipped = 0;
+            for key in &payload.keys {
+                let mut name = key.name.clone();
+                let mut suffix = 1;
+                while keyring.get_key(&name).is_some() {
+                    name = match suffix {
+                        1 => format!("{}-recovered", key.name),
+                        n => format!("{}-recovered-{}", key.name, n),
+                    };
+                    suffix += 1;
+                }
+                let renamed = keyring::PlainKey {
+                    name: name.clone(),
+                    algorithm: key.algorithm.clone(),
+                    secret: key.secret.clone(),
+                    public_key: key.public_key.clone(),
+                    metadata: key.metadata.clone(),
+                };
+                match keyring.insert_plain_key(&renamed, &keyring_password) {
+                    Ok(name) if name != key.name => println!(
+                        "✅ Recovered {} key '{}' as '{}' (the name is taken)",
+                        key.algorithm, key.name, name
+                    ),
+                    Ok(name) => println!("✅ Recovered {} key '{}'", key.algorithm, name),
+                    Err(e) => {
+                        skipped += 1;
+                        println!("⚠️  Skipped key '{}': {}", key.name, e);
+                    }
+                }
+            }
+
+            if !payload.quantum.is_empty() {
+                let restore = keyring.insert_quantum_keys(&payload.quantum, &keyring_password)?;
+                for note in &restore.notes {
+                    println!("⚠️  {}", note);
+                }
+                println!("✅ Recovered {} quantum identity and file key entries", restore.restored);
+            }
+            keyring.save_to_file(&keyring_path)?;
+            if skipped > 0 {
+                println!("{} key(s) were skipped; the others were restored", skipped);
+            }
+        }
+
+        Commands::KeyPaperExport { key_name } => {
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            let keyring_password = if keyring.is_legacy() {
+                "keyring-protection".to_string()
+            } else {
+                rpassword::prompt_password("Enter keyring password: ")?
+            };
+
+            let key = keyring.export_plain_key(&key_name, &keyring_password)?;
+            if key.algorithm != keyring::KeyAlgorithm::Aes256 {
+                return Err(anyhow!(
+                    "Paper keys are only supported for AES-256 keys; '{}' is {}",
+                    key_name,
+                    key.algorithm
+                ));
+            }
+            let secret: [u8; 32] = key
+                .secret
+                .as_deref()
+                .and_then(|secret| secret.try_into().ok())
+                .ok_or_else(|| anyhow!("Key '{}' has no 32-byte secret", key_name))?;
+            let lines = paperkey::encode(&secret)?;
+
+            // Update keyring with usage stats
+            keyring.save_to_file(&keyring_path)?;
+
+            println!("📝 Paper key for '{}'\n", key.name);
+            for line in &lines {
+                println!("   {}", line);
+            }
+            println!();
+            print_fingerprint("", "Fingerprint", &keyring::key_fingerprint(&key.algorithm, &secret));
+            println!("\nWrite down all 30 words in order; the last word of each row is a check word.");
+            println!("Anyone with these words can decrypt your files. Restore with 'pipe key-paper-import'.");
+        }
+
+        Commands::KeyPaperImport { name } => {
+            println!("Enter the paper key (6 rows of 5 words), then an empty line:");
+            let mut phrase = String::new();
+            for line in std::io::stdin().lines() {
+                let line = line?;
+                if line.trim().is_empty() && !phrase.trim().is_empty() {
+                    break;
+                }
+                phrase.push_str(&line);
+                phrase.push(' ');
+            }
+            let secret = paperkey::decode(&phrase);
+            zeroize::Zeroize::zeroize(&mut phrase);
+            let secret = secret?;
+
+            let key = keyring::PlainKey {
+                name,
+                algorithm: keyring::KeyAlgorithm::Aes256,
+                secret: Some(secret.to_vec()),
+                public_key: None,
+                metadata: keyring::KeyMetadata::new(Some("Restored from paper key".to_string())),
+            };
+
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            let keyring_password = keyring_password_for_new_key(&mut keyring)?;
+            let name = keyring.insert_plain_key(&key, &keyring_password)?;
+            keyring.save_to_file(&keyring_path)?;
+
+            println!("✅ Imported AES-256 key '{}' from paper key", name);
+            print_fingerprint("   ", "Fingerprint", &keyring::key_fingerprint(&key.algorithm, &secret));
+        }
+
+        Commands::KeyringPasswd => {
+            let keyring_path = keyring::Keyring::default_path()?;
+            if !keyring_path.exists() {
+                return Err(anyhow!("No keyring found at {}", keyring_path.display()));
+            }
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            if keyring.is_legacy() {
+                return Err(anyhow!(
+                    "Keyring still uses the default password. Run 'pipe keyring-migrate' to set one."
+                ));
+            }
+
+            let old_password = rpassword::prompt_password("Enter current keyring password: ")?;
+            if !keyring.verify_password(&old_password)? {
+                return Err(anyhow!("Invalid keyring password"));
+            }
+
+            let new_password = rpassword::prompt_password("Enter new keyring password: ")?;
+            let confirm = rpassword::prompt_password("Confirm new keyring password: ")?;
+            if new_password != confirm {
+                return Err(anyhow!("Passwords do not match"));
+            }
+            if new_password.len() < 8 {
+                return Err(anyhow!("Password must be at least 8 characters long"));
+            }
+
+            println!("\nRe-encrypting keyring...");
+            keyring.change_password(&old_password, &new_password)?;
+            keyring.save_after_password_change(&keyring_path, &new_password)?;
+
+            println!("✅ Keyring password changed");
+        }
+
+        Commands::QuantumKeysMigrate { keep } => {
+            let file_ids = crate::quantum_keyring::list_quantum_keys()?;
+            if file_ids.is_empty() {
+                println!("✅ No plaintext quantum keypairs to migrate.");
+                return Ok(());
+            }
+
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            let keyring_password = keyring_password_for_new_key(&mut keyring)?;
+
+            println!("🔐 Migrating {} quantum keypair(s) into the keyring...", file_ids.len());
+            for file_id in &file_ids {
+                let keypair = crate::quantum_keyring::load_quantum_keypair(file_id)?;
+                keyring.store_quantum_keypair(&keypair, &keyring_password)?;
+            }
+
+            // Only remove the plaintext files once the keyring is safely written
+            keyring.save_to_file(&keyring_path)?;
+            if !keep {
+                for file_id in &file_ids {
+                    crate::quantum_keyring::delete_quantum_keypair(file_id)?;
+                }
+            }
+
+            println!("✅ Migrated {} quantum keypair(s)", file_ids.len());
+            if keep {
+                println!("   Plaintext copies were kept; delete them once you have checked the keyring.");
+            }
+        }
+
+        Commands::QuantumIdentityGen { name } => {
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            let keyring_password = keyring_password_for_new_key(&mut keyring)?;
+
+            println!("🔐 Generating quantum identity keypair (Kyber1024 + Ed25519/Dilithium5)...");
+            keyring.generate_quantum_identity(&name, &keyring_password)?;
+            keyring.save_to_file(&keyring_path)?;
+            println!("✅ Quantum identity '{}' saved to keyring", name);
+        }
+
         Commands::KeyList => {
             let keyring_path = keyring::Keyring::default_path()?;
             let keyring = keyring::Keyring::load_from_file(&keyring_path)?;
@@ -6581,7 +9551,15 @@ pub async fn run_cli() -> Result<()> {
                 println!("🔑 Keys in keyring:\n");
                 for (name, key) in keys {
                     println!("  Name: {}", name);
-                    println!("  Algorithm: {}", key.algorithm);
+                    if key.is_public_only() {
+                        println!("  Algorithm: {} (public key only)", key.algorithm);
+                    } else {
+                        println!("  Algorithm: {}", key.algorithm);
+                    }
+                    match key.fingerprint_hex() {
+                        Some(fingerprint) => print_fingerprint("  ", "Fingerprint", &fingerprint),
+                        None => println!("  Fingerprint: unknown (key predates fingerprints)"),
+                    }
                     println!(
                         "  Created: {}",
                         key.metadata.created_at.format("%Y-%m-%d %H:%M:%S")
@@ -6590,14 +9568,137 @@ pub async fn run_cli() -> Result<()> {
                         println!("  Description: {}", desc);
                     }
                     if key.metadata.usage_count > 0 {
-                        println!("  Used: {} times", key.metadata.usage_count);
-                        if let Some(last_used) = key.metadata.last_used {
-                            println!("  Last used: {}", last_used.format("%Y-%m-%d %H:%M:%S"));
-                        }
+                        println!("  Used: {} times (encrypting or signing)", key.metadata.usage_count);
+                    }
+                    if let Some(last_used) = key.metadata.last_used {
+                        println!("  Last used: {}", last_used.format("%Y-%m-%d %H:%M:%S"));
+                    }
+                    if let Some(expires_at) = key.metadata.expires_at {
+                        println!("  Expires: {}", expires_at.format("%Y-%m-%d %H:%M:%S"));
+                    }
+                    if let Some(max_uses) = key.metadata.max_uses {
+                        println!("  Max uses: {}", max_uses);
+                    }
+                    if let Some(reason) = key.metadata.retired_reason(chrono::Utc::now()) {
+                        println!("  ⛔ Retired: {} (decryption only)", reason);
                     }
                     println!();
                 }
             }
+
+            let identities = keyring.quantum_identities();
+            if !identities.is_empty() {
+                println!("🔐 Quantum identities:\n");
+                for (name, identity) in identities {
+                    println!("  Name: {}", name);
+                    println!("  Created: {}", identity.created_at.format("%Y-%m-%d %H:%M:%S"));
+                    println!();
+                }
+            }
+            if keyring.quantum_file_count() > 0 {
+                println!("Quantum keys for {} uploaded file(s)", keyring.quantum_file_count());
+            }
+        }
+
+        Commands::KeyStatus { max_age_days } => {
+            let keyring_path = keyring::Keyring::default_path()?;
+            let keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+
+            let now = chrono::Utc::now();
+            let max_age = chrono::Duration::try_days(max_age_days)
+                .ok_or_else(|| anyhow!("--max-age-days {} is out of range", max_age_days))?;
+            let mut keys = keyring.list_keys();
+            keys.sort_by(|a, b| a.0.cmp(b.0));
+
+            let mut retired = Vec::new();
+            let mut due = Vec::new();
+            for (name, key) in &keys {
+                if let Some(reason) = key.metadata.retired_reason(now) {
+                    retired.push((name, key, reason));
+                } else if let Some(reason) = key.metadata.rotation_reason(now, max_age) {
+                    due.push((name, key, reason));
+                }
+            }
+
+            if !retired.is_empty() {
+                println!("⛔ Retired keys (decryption only):\n");
+                for (name, key, reason) in &retired {
+                    println!("  {} ({}): {}", name, key.algorithm, reason);
+                }
+                println!();
+            }
+            if !due.is_empty() {
+                println!("⚠️  Keys due for rotation:\n");
+                for (name, key, reason) in &due {
+                    println!("  {} ({}): {}", name, key.algorithm, reason);
+                }
+                println!();
+            }
+
+            let active = keys.len() - retired.len() - due.len();
+            println!(
+                "{} key(s): {} active, {} due for rotation, {} retired",
+                keys.len(),
+                active,
+                due.len(),
+                retired.len()
+            );
+            if !retired.is_empty() || !due.is_empty() {
+                println!("Generate replacements with 'pipe key-gen' and re-encrypt data that should move to them.");
+            }
+        }
+
+        Commands::KeyPolicy {
+            key_name,
+            expires_in_days,
+            expires,
+            max_uses,
+            clear,
+        } => {
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            let metadata = keyring
+                .get_key(&key_name)
+                .ok_or_else(|| anyhow!("Key '{}' not found in keyring", key_name))?
+                .metadata
+                .clone();
+
+            let (expires_at, max_uses) = if clear {
+                if expires_in_days.is_some() || expires.is_some() || max_uses.is_some() {
+                    return Err(anyhow!("--clear cannot be combined with other policy options"));
+                }
+                (None, None)
+            } else {
+                let expires_at = match (expires_in_days, expires) {
+                    (Some(_), Some(_)) => {
+                        return Err(anyhow!("Use either --expires-in-days or --expires"))
+                    }
+                    (Some(days), None) => Some(
+                        chrono::Duration::try_days(days)
+                            .and_then(|duration| chrono::Utc::now().checked_add_signed(duration))
+                            .ok_or_else(|| anyhow!("--expires-in-days {} is out of range", days))?,
+                    ),
+                    (None, Some(date)) => Some(parse_date_arg(&date)?),
+                    (None, None) => metadata.expires_at,
+                };
+                (expires_at, max_uses.or(metadata.max_uses))
+            };
+
+            keyring.set_key_policy(&key_name, expires_at, max_uses)?;
+            keyring.save_to_file(&keyring_path)?;
+
+            println!("✅ Updated policy for key '{}'", key_name);
+            match expires_at {
+                Some(expires_at) => println!("   Expires: {}", expires_at.format("%Y-%m-%d %H:%M:%S")),
+                None => println!("   Expires: never"),
+            }
+            match max_uses {
+                Some(max_uses) => println!(
+                    "   Max uses: {} (used {} times so far)",
+                    max_uses, metadata.usage_count
+                ),
+                None => println!("   Max uses: unlimited"),
+            }
         }
 
         Commands::KeyDelete { key_name } => {
@@ -6610,10 +9711,23 @@ pub async fn run_cli() -> Result<()> {
             println!("✅ Key '{}' deleted from keyring", key_name);
         }
 
-        Commands::KeyExport { key_name, output } => {
+        Commands::KeyExport {
+            key_name,
+            output,
+            public,
+        } => {
             let keyring_path = keyring::Keyring::default_path()?;
             let keyring = keyring::Keyring::load_from_file(&keyring_path)?;
 
+            if public {
+                keyring::export_public_key(&keyring, &key_name, Path::new(&output))?;
+                println!("✅ Public key of '{}' exported to: {}", key_name, output);
+                if let Some(fingerprint) = keyring.get_key(&key_name).and_then(|k| k.fingerprint_hex()) {
+                    print_fingerprint("   ", "Fingerprint", &fingerprint);
+                }
+                return Ok(());
+            }
+
             // Get keyring password
             let keyring_password = if keyring.is_legacy() {
                 "keyring-protection".to_string()
@@ -6629,15 +9743,56 @@ pub async fn run_cli() -> Result<()> {
 
             keyring::export_key(&keyring, &key_name, Path::new(&output), &keyring_password, &export_password)?;
             println!("✅ Key '{}' exported to: {}", key_name, output);
+            if let Some(fingerprint) = keyring.get_key(&key_name).and_then(|k| k.fingerprint_hex()) {
+                print_fingerprint("   ", "Fingerprint", &fingerprint);
+            }
+        }
+
+        Commands::KeyImport { file, name } => {
+            let exported = keyring::ExportedKey::load_from_file(Path::new(&file))?;
+            let name = match name.or_else(|| exported.name().map(str::to_string)) {
+                Some(name) => name,
+                None => Path::new(&file)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .ok_or_else(|| anyhow!("Cannot derive a key name from '{}'; use --name", file))?,
+            };
+
+            let export_password = if exported.is_public_only() {
+                None
+            } else {
+                Some(rpassword::prompt_password("Enter password of the exported key: ")?)
+            };
+
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+            let keyring_password = keyring_password_for_new_key(&mut keyring)?;
+
+            let key_name = keyring.import_key(
+                &exported,
+                name,
+                &keyring_password,
+                export_password.as_deref(),
+            )?;
+            keyring.save_to_file(&keyring_path)?;
+
+            if exported.is_public_only() {
+                println!("✅ Public {} key imported as '{}'", exported.algorithm(), key_name);
+            } else {
+                println!("✅ {} key imported as '{}'", exported.algorithm(), key_name);
+            }
+            if let Some(fingerprint) = keyring.get_key(&key_name).and_then(|k| k.fingerprint_hex()) {
+                print_fingerprint("   ", "Fingerprint", &fingerprint);
+            }
         }
 
         Commands::SignFile {
             input_file,
             signature_file,
             key,
+            comment,
         } => {
-            // Read file to sign
-            let data = std::fs::read(&input_file)?;
+            let blake3_hash = signature::hash_file(Path::new(&input_file))?;
 
             // Load key
             let keyring_path = keyring::Keyring::default_path()?;
@@ -6648,37 +9803,37 @@ pub async fn run_cli() -> Result<()> {
                 let stored_key = keyring
                     .get_key(&key)
                     .ok_or_else(|| anyhow!("Key '{}' not found in keyring", key))?;
-                if stored_key.algorithm != keyring::KeyAlgorithm::Dilithium5 {
+                if !stored_key.algorithm.is_signing() {
                     return Err(anyhow!(
-                        "Key '{}' is not a signing key (need Dilithium5)",
+                        "Key '{}' is not a signing key (need Dilithium5 or Ed25519+Dilithium5)",
                         key
                     ));
                 }
-                stored_key.public_key.clone()
+                stored_key
+                    .public_key
+                    .clone()
+                    .ok_or_else(|| anyhow!("Key '{}' has no public key", key))?
             };
 
             // Get key material
             let password = rpassword::prompt_password("Enter keyring password: ")?;
-            let key_material = keyring.get_key_material(&key, &password)?;
-
-            // Sign the data
-            let signature =
-                quantum::sign_with_dilithium(&data, key_material.private_key.as_ref().unwrap())?;
-
-            // Save signature
-            std::fs::write(&signature_file, &signature)?;
-
-            // Also save public key alongside signature for verification
-            let pubkey_file = format!("{}.pubkey", signature_file);
-            if let Some(pubkey) = public_key.as_ref() {
-                std::fs::write(&pubkey_file, pubkey)?;
-                println!("✅ File signed successfully!");
-                println!("   Signature: {}", signature_file);
-                println!("   Public key: {}", pubkey_file);
-            } else {
-                println!("✅ File signed successfully!");
-                println!("   Signature: {}", signature_file);
-            }
+            let key_material = keyring.get_key_material(&key, &password, keyring::KeyUse::Encrypt)?;
+
+            // Sign the hash, time and comment
+            let sidecar = signature::SignatureSidecar::sign(
+                &blake3_hash,
+                comment,
+                key_material
+                    .private_key
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("Key '{}' has no secret key material", key))?,
+                &public_key,
+            )?;
+            std::fs::write(&signature_file, sidecar.to_json()?)?;
+
+            println!("✅ File signed successfully!");
+            println!("   Signature: {}", signature_file);
+            print_fingerprint("   ", "Signer fingerprint", &sidecar.signer_fingerprint);
 
             // Update keyring with usage stats
             keyring.save_to_file(&keyring_path)?;
@@ -6689,33 +9844,162 @@ pub async fn run_cli() -> Result<()> {
             signature_file,
             public_key,
         } => {
-            // Read file and signature
-            let data = std::fs::read(&input_file)?;
-            let signature = std::fs::read(&signature_file)?;
+            // Required signer: a file, a contact or a keyring key
+            let keyring = keyring::Keyring::load_from_file(&keyring::Keyring::default_path()?)?;
+            let expected_key = match public_key {
+                Some(ref spec) => Some(
+                    resolve_signer_public_key(&keyring, spec)?
+                        .map(|(key, _)| key)
+                        .ok_or_else(|| anyhow!("Public key file, contact or signing key not found: {}", spec))?,
+                ),
+                None => None,
+            };
 
-            // Read public key (either from file or find .pubkey file)
-            let pubkey_bytes = if std::path::Path::new(&public_key).exists() {
-                std::fs::read(&public_key)?
-            } else {
-                // Try to find .pubkey file alongside signature
-                let pubkey_file = format!("{}.pubkey", signature_file);
-                if std::path::Path::new(&pubkey_file).exists() {
-                    std::fs::read(&pubkey_file)?
+            let input_path = Path::new(&input_file);
+            if !input_path.is_dir() {
+                let passed = verify_signature_file(
+                    &keyring,
+                    input_path,
+                    Path::new(&signature_file),
+                    expected_key.as_deref(),
+                )?;
+                if !passed {
+                    println!("   The file may have been modified or signed with a different key");
+                    return Err(anyhow!("Signature verification failed for '{}'", input_file));
+                }
+                return Ok(());
+            }
+
+            let signature_dir = Path::new(&signature_file);
+            if !signature_dir.is_dir() {
+                return Err(anyhow!(
+                    "'{}' is a directory, so '{}' must be a directory of signatures",
+                    input_file,
+                    signature_file
+                ));
+            }
+            let mut passed = 0;
+            let mut failed = 0;
+            for entry in WalkDir::new(signature_dir)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+            {
+                let sig_path = entry.path();
+                let rel_path = match sig_path
+                    .strip_prefix(signature_dir)
+                    .ok()
+                    .and_then(|p| p.to_str())
+                    .and_then(|p| p.strip_suffix(".sig"))
+                {
+                    Some(rel_path) if entry.file_type().is_file() => rel_path.to_string(),
+                    _ => continue,
+                };
+                let file_path = input_path.join(&rel_path);
+                let result = if file_path.is_file() {
+                    verify_signature_file(&keyring, &file_path, sig_path, expected_key.as_deref())
                 } else {
-                    return Err(anyhow!("Public key file not found: {}", public_key));
+                    Err(anyhow!("'{}' has a signature but no file", file_path.display()))
+                };
+                match result {
+                    Ok(true) => passed += 1,
+                    Ok(false) => failed += 1,
+                    Err(e) => {
+                        println!("❌ {}: {}", rel_path, e);
+                        failed += 1;
+                    }
                 }
-            };
+            }
 
-            // Verify signature
-            if quantum::verify_dilithium_signature(&data, &signature, &pubkey_bytes)? {
-                println!("✅ Signature verification PASSED");
-                println!(
-                    "   File '{}' was signed by the holder of the private key",
-                    input_file
-                );
-            } else {
-                println!("❌ Signature verification FAILED");
-                println!("   The file may have been modified or signed with a different key");
+            println!();
+            println!("📊 {} signature(s) passed, {} failed", passed, failed);
+            if passed + failed == 0 {
+                return Err(anyhow!("No .sig files found in '{}'", signature_file));
+            }
+            if failed > 0 {
+                return Err(anyhow!("{} signature(s) failed verification", failed));
+            }
+        }
+
+        Commands::Contact(subcmd) => {
+            let keyring_path = keyring::Keyring::default_path()?;
+            let mut keyring = keyring::Keyring::load_from_file(&keyring_path)?;
+
+            match subcmd {
+                ContactCommands::Add {
+                    name,
+                    card,
+                    kem_key,
+                    sign_key,
+                    verified,
+                } => {
+                    let mut contact = match card {
+                        Some(card) => {
+                            if kem_key.is_some() || sign_key.is_some() {
+                                return Err(anyhow!("Use either --card or --kem-key/--sign-key"));
+                            }
+                            let mut contact = keyring::Contact::load_card(Path::new(&card))?;
+                            contact.name = name.clone();
+                            contact
+                        }
+                        None => keyring::Contact::new(
+                            &name,
+                            kem_key
+                                .map(|path| read_public_key_file(&path, keyring::KeyAlgorithm::Kyber1024))
+                                .transpose()?,
+                            sign_key
+                                .map(|path| read_signing_public_key_file(&path))
+                                .transpose()?,
+                        )?,
+                    };
+                    contact.verified = verified;
+
+                    // A keyring without keys gets its master password set up first
+                    if !keyring.has_password() && keyring.keys().is_empty() {
+                        keyring_password_for_new_key(&mut keyring)?;
+                    }
+
+                    print_contact_fingerprints(&contact);
+                    keyring.add_contact(contact)?;
+                    keyring.save_to_file(&keyring_path)?;
+                    println!("✅ Contact '{}' added", name);
+                    if !verified {
+                        println!("   Compare the fingerprints with {} out of band, then run 'pipe contact verify {}'", name, name);
+                    }
+                }
+                ContactCommands::List => {
+                    let contacts = keyring.contacts();
+                    if contacts.is_empty() {
+                        println!("No contacts. Use 'pipe contact add' to add one.");
+                    }
+                    for contact in contacts {
+                        println!("  Name: {}", contact.name);
+                        println!(
+                            "  Status: {}",
+                            if contact.verified { "verified" } else { "unverified" }
+                        );
+                        println!("  Added: {}", contact.added_at.format("%Y-%m-%d %H:%M:%S"));
+                        print_contact_fingerprints(contact);
+                        println!();
+                    }
+                }
+                ContactCommands::Verify { name } => {
+                    keyring.verify_contact(&name)?;
+                    keyring.save_to_file(&keyring_path)?;
+                    println!("✅ Contact '{}' marked as verified", name);
+                }
+                ContactCommands::Remove { name } => {
+                    keyring.remove_contact(&name)?;
+                    keyring.save_to_file(&keyring_path)?;
+                    println!("✅ Contact '{}' removed", name);
+                }
+                ContactCommands::Export { name, output } => {
+                    let contact = keyring
+                        .get_contact(&name)
+                        .ok_or_else(|| anyhow!("Contact '{}' not found", name))?;
+                    contact.save_card(Path::new(&output))?;
+                    println!("✅ Contact card for '{}' written to: {}", name, output);
+                }
             }
         }
 
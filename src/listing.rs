@@ -0,0 +1,333 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{add_auth_headers, SavedCredentials};
+
+/// Files requested per `/listFiles` page
+const PAGE_SIZE: usize = 1000;
+
+/// How long a cached listing is reused, so repeated calls (e.g. from shell
+/// completion) do not hit the server every time
+const CACHE_TTL_SECS: i64 = 60;
+
+/// A file in remote storage
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uploaded_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl RemoteEntry {
+    fn from_json(json: &serde_json::Value) -> Option<Self> {
+        let date = |key: &str| {
+            json[key]
+                .as_str()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc))
+        };
+        Some(Self {
+            name: json["file_name"].as_str()?.to_string(),
+            size: json["size"].as_u64().unwrap_or(0),
+            uploaded_at: date("uploaded_at"),
+            tier: json["tier"].as_str().map(str::to_string),
+            expires_at: date("expires_at").or_else(|| date("expiry")),
+        })
+    }
+}
+
+/// One line of a non-recursive listing
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ListItem {
+    File(RemoteEntry),
+    /// A path prefix shared by several files, with their count and total size
+    Dir {
+        name: String,
+        files: usize,
+        size: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedListing {
+    fetched_at: DateTime<Utc>,
+    base_url: String,
+    user_id: String,
+    entries: Vec<RemoteEntry>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("pipe-cli").join("remote-listing.json"))
+}
+
+/// All remote files, sorted by name. A listing fetched in the last minute
+/// for the same account is reused unless `refresh` is set.
+pub async fn fetch_remote_entries(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+    refresh: bool,
+) -> Result<Vec<RemoteEntry>> {
+    if !refresh {
+        if let Some(entries) = read_cache(base_url, &creds.user_id) {
+            return Ok(entries);
+        }
+    }
+
+    let mut entries = fetch_all_pages(client, base_url, creds).await?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    write_cache(base_url, &creds.user_id, &entries);
+    Ok(entries)
+}
+
+/// Fetch `/listFiles` page by page. Servers that ignore the paging
+/// parameters return everything at once; that is detected and accepted.
+async fn fetch_all_pages(
+    client: &Client,
+    base_url: &str,
+    creds: &SavedCredentials,
+) -> Result<Vec<RemoteEntry>> {
+    let url = format!("{}/listFiles", base_url);
+    let mut entries: Vec<RemoteEntry> = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        let request = client
+            .get(&url)
+            .query(&[("limit", PAGE_SIZE), ("offset", offset)]);
+        let response = add_auth_headers(request, creds, true).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to list files: {}", response.status()));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        let files = json
+            .as_array()
+            .or_else(|| json["files"].as_array())
+            .ok_or_else(|| anyhow!("Unexpected /listFiles response"))?;
+        let page: Vec<RemoteEntry> = files.iter().filter_map(RemoteEntry::from_json).collect();
+
+        // A repeated first page means the server ignored the offset
+        let repeated = offset > 0 && !page.is_empty() && entries.first() == page.first();
+        if repeated {
+            break;
+        }
+        let full_page = files.len() == PAGE_SIZE;
+        offset += files.len();
+        entries.extend(page);
+        if !full_page {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+fn read_cache(base_url: &str, user_id: &str) -> Option<Vec<RemoteEntry>> {
+    let cached: CachedListing =
+        serde_json::from_str(&fs::read_to_string(cache_path()?).ok()?).ok()?;
+    let fresh = (Utc::now() - cached.fetched_at).num_seconds() < CACHE_TTL_SECS;
+    (fresh && cached.base_url == base_url && cached.user_id == user_id).then_some(cached.entries)
+}
+
+// The cache is an optimisation; failing to write it is not an error
+fn write_cache(base_url: &str, user_id: &str, entries: &[RemoteEntry]) {
+    let Some(path) = cache_path() else { return };
+    let cached = CachedListing {
+        fetched_at: Utc::now(),
+        base_url: base_url.to_string(),
+        user_id: user_id.to_string(),
+        entries: entries.to_vec(),
+    };
+    if let (Some(parent), Ok(json)) = (path.parent(), serde_json::to_string(&cached)) {
+        let _ = fs::create_dir_all(parent).and_then(|_| fs::write(&path, json));
+    }
+}
+
+/// Drop the cached listing after an upload or delete, so the next listing
+/// shows the change
+pub fn invalidate_cache() {
+    if let Some(path) = cache_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Directory part of a prefix: everything up to and including the last '/'
+fn base_of(prefix: &str) -> &str {
+    prefix.rfind('/').map_or("", |i| &prefix[..=i])
+}
+
+/// Files whose name starts with `prefix`
+pub fn matching<'a>(
+    entries: &'a [RemoteEntry],
+    prefix: &'a str,
+) -> impl Iterator<Item = &'a RemoteEntry> {
+    entries.iter().filter(move |e| e.name.starts_with(prefix))
+}
+
+/// Like `ls`: files directly under the prefix's directory and one entry per
+/// subdirectory. `pipe ls photos` shows `photos/` and `photos.zip`.
+pub fn list_level(entries: &[RemoteEntry], prefix: &str) -> Vec<ListItem> {
+    let base = base_of(prefix);
+    let mut dirs: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    let mut items = Vec::new();
+    for entry in matching(entries, prefix) {
+        let rest = &entry.name[base.len()..];
+        match rest.find('/') {
+            Some(i) => {
+                let dir = dirs.entry(format!("{}{}", base, &rest[..=i])).or_default();
+                dir.0 += 1;
+                dir.1 += entry.size;
+            }
+            None => items.push(ListItem::File(entry.clone())),
+        }
+    }
+    items.extend(
+        dirs.into_iter()
+            .map(|(name, (files, size))| ListItem::Dir { name, files, size }),
+    );
+    items.sort_by(|a, b| item_name(a).cmp(item_name(b)));
+    items
+}
+
+fn item_name(item: &ListItem) -> &str {
+    match item {
+        ListItem::File(entry) => &entry.name,
+        ListItem::Dir { name, .. } => name,
+    }
+}
+
+#[derive(Default)]
+struct TreeNode {
+    children: BTreeMap<String, TreeNode>,
+    size: Option<u64>,
+}
+
+/// Matching files as an indented tree, one line per directory or file
+pub fn render_tree(entries: &[RemoteEntry], prefix: &str, show_size: bool) -> Vec<String> {
+    let base = base_of(prefix);
+    let mut root = TreeNode::default();
+    for entry in matching(entries, prefix) {
+        let mut node = &mut root;
+        for part in entry.name[base.len()..].split('/') {
+            node = node.children.entry(part.to_string()).or_default();
+        }
+        node.size = Some(entry.size);
+    }
+
+    let mut lines = vec![if base.is_empty() {
+        ".".to_string()
+    } else {
+        base.to_string()
+    }];
+    render_children(&root, "", show_size, &mut lines);
+    lines
+}
+
+fn render_children(node: &TreeNode, indent: &str, show_size: bool, lines: &mut Vec<String>) {
+    let count = node.children.len();
+    for (i, (name, child)) in node.children.iter().enumerate() {
+        let last = i + 1 == count;
+        let branch = if last { "└── " } else { "├── " };
+        let label = match child.size {
+            Some(size) if child.children.is_empty() && show_size => {
+                format!("{} ({})", name, crate::sync::format_file_size(size))
+            }
+            _ if !child.children.is_empty() => format!("{}/", name),
+            _ => name.clone(),
+        };
+        lines.push(format!("{}{}{}", indent, branch, label));
+        let child_indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+        render_children(child, &child_indent, show_size, lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: u64) -> RemoteEntry {
+        RemoteEntry {
+            name: name.to_string(),
+            size,
+            uploaded_at: None,
+            tier: None,
+            expires_at: None,
+        }
+    }
+
+    fn sample() -> Vec<RemoteEntry> {
+        vec![
+            file("notes.txt", 5),
+            file("photos.zip", 100),
+            file("photos/2024/a.jpg", 10),
+            file("photos/2024/b.jpg", 20),
+            file("photos/cover.jpg", 30),
+        ]
+    }
+
+    #[test]
+    fn test_list_level_groups_directories() {
+        let entries = sample();
+        let top = list_level(&entries, "");
+        let names: Vec<&str> = top.iter().map(item_name).collect();
+        assert_eq!(names, ["notes.txt", "photos.zip", "photos/"]);
+
+        assert_eq!(
+            list_level(&entries, "photos/"),
+            vec![
+                ListItem::Dir {
+                    name: "photos/2024/".to_string(),
+                    files: 2,
+                    size: 30
+                },
+                ListItem::File(file("photos/cover.jpg", 30)),
+            ]
+        );
+        assert_eq!(list_level(&entries, "photos").len(), 2);
+        assert!(list_level(&entries, "videos/").is_empty());
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let entries = sample();
+        assert_eq!(
+            render_tree(&entries, "photos/", false),
+            [
+                "photos/",
+                "├── 2024/",
+                "│   ├── a.jpg",
+                "│   └── b.jpg",
+                "└── cover.jpg"
+            ]
+        );
+        let with_sizes = render_tree(&entries, "", true);
+        assert_eq!(with_sizes[0], ".");
+        assert!(with_sizes.contains(&"├── notes.txt (5.00 B)".to_string()));
+    }
+
+    #[test]
+    fn test_parse_listing_entry() {
+        let json = serde_json::json!({
+            "file_name": "a.txt",
+            "size": 12,
+            "uploaded_at": "2025-01-02T03:04:05Z",
+            "tier": "normal"
+        });
+        let entry = RemoteEntry::from_json(&json).unwrap();
+        assert_eq!(entry.size, 12);
+        assert_eq!(entry.tier.as_deref(), Some("normal"));
+        assert!(entry.uploaded_at.is_some() && entry.expires_at.is_none());
+        assert!(RemoteEntry::from_json(&serde_json::json!({"size": 1})).is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::keyring::{key_fingerprint, KeyAlgorithm};
+use crate::quantum::{sign_message, verify_signature};
+use crate::signature::hash_file;
+
+/// Current manifest format version
+const MANIFEST_VERSION: u8 = 1;
+
+/// Prefix of the signed bytes, so a manifest signature is never valid as a
+/// signature over a file
+const SIGNATURE_CONTEXT: &[u8] = b"pipe-cli upload manifest v1\n";
+
+/// One uploaded file. Size and hash are of the local plaintext, so the
+/// entry can be checked against the original or a decrypted download.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    /// Path relative to the uploaded directory
+    pub path: String,
+    /// Name of the uploaded object
+    pub remote_path: String,
+    pub size: u64,
+    pub blake3: String,
+    /// none, password, envelope or recipient
+    pub encryption: String,
+    /// Compression level, when compressed before upload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+}
+
+/// Record of a directory upload
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadManifest {
+    pub version: u8,
+    pub created_at: DateTime<Utc>,
+    /// Name of the uploaded directory
+    pub source: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Manifest with a Dilithium5 or hybrid signature and the signer's public key
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignedManifest {
+    pub manifest: UploadManifest,
+    /// Hex-encoded Dilithium5 or hybrid Ed25519+Dilithium5 public key
+    signer_public_key: String,
+    /// Hex-encoded detached signature
+    signature: String,
+}
+
+/// Result of checking a file against its manifest entry
+#[derive(Debug, PartialEq)]
+pub enum EntryCheck {
+    Ok,
+    Missing,
+    SizeMismatch { actual: u64 },
+    HashMismatch { actual: String },
+}
+
+impl UploadManifest {
+    /// Manifest over the given entries, sorted by path
+    pub fn new(source: &str, mut entries: Vec<ManifestEntry>) -> Self {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Self {
+            version: MANIFEST_VERSION,
+            created_at: Utc::now(),
+            source: source.to_string(),
+            entries,
+        }
+    }
+
+    /// Sign with a Dilithium5 or hybrid secret key
+    pub fn sign(self, secret_key: &[u8], public_key: &[u8]) -> Result<SignedManifest> {
+        let signature = sign_message(&signed_bytes(&self)?, secret_key)?;
+        let signed = SignedManifest {
+            manifest: self,
+            signer_public_key: hex::encode(public_key),
+            signature: hex::encode(signature),
+        };
+        // Catch a secret key that does not match the public key
+        signed.verify()?;
+        Ok(signed)
+    }
+}
+
+impl SignedManifest {
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let signed: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("Not a valid upload manifest: {}", e))?;
+        if signed.manifest.version > MANIFEST_VERSION {
+            return Err(anyhow!(
+                "Manifest version {} is newer than supported version {}",
+                signed.manifest.version,
+                MANIFEST_VERSION
+            ));
+        }
+        Ok(signed)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn signer_public_key(&self) -> Result<Vec<u8>> {
+        hex::decode(&self.signer_public_key).map_err(|_| anyhow!("Manifest has an invalid signer key"))
+    }
+
+    pub fn signer_fingerprint(&self) -> Result<String> {
+        let public_key = self.signer_public_key()?;
+        Ok(key_fingerprint(&KeyAlgorithm::for_signing_key(&public_key), &public_key))
+    }
+
+    /// Check the signature against the embedded public key. Who that key
+    /// belongs to is up to the caller.
+    pub fn verify(&self) -> Result<()> {
+        let signature =
+            hex::decode(&self.signature).map_err(|_| anyhow!("Manifest has an invalid signature"))?;
+        if !verify_signature(&signed_bytes(&self.manifest)?, &signature, &self.signer_public_key()?)? {
+            return Err(anyhow!("Manifest signature is invalid; the manifest was modified after signing"));
+        }
+        Ok(())
+    }
+}
+
+/// Bytes covered by the signature. Struct fields serialize in declaration
+/// order, so a parsed manifest re-serializes to the same bytes.
+fn signed_bytes(manifest: &UploadManifest) -> Result<Vec<u8>> {
+    let mut bytes = SIGNATURE_CONTEXT.to_vec();
+    bytes.extend_from_slice(&serde_json::to_vec(manifest)?);
+    Ok(bytes)
+}
+
+/// Compare a local file with its manifest entry
+pub fn check_file(entry: &ManifestEntry, path: &Path) -> Result<EntryCheck> {
+    if !path.is_file() {
+        return Ok(EntryCheck::Missing);
+    }
+    let size = fs::metadata(path)?.len();
+    if size != entry.size {
+        return Ok(EntryCheck::SizeMismatch { actual: size });
+    }
+    let actual = hash_file(path)?;
+    if actual != entry.blake3 {
+        return Ok(EntryCheck::HashMismatch { actual });
+    }
+    Ok(EntryCheck::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pqcrypto_mldsa::mldsa87 as dilithium5;
+    use pqcrypto_traits::sign::{PublicKey, SecretKey};
+    use tempfile::TempDir;
+
+    fn entry(path: &str, data: &[u8]) -> ManifestEntry {
+        ManifestEntry {
+            path: path.to_string(),
+            remote_path: format!("{}.enc", path),
+            size: data.len() as u64,
+            blake3: blake3::hash(data).to_hex().to_string(),
+            encryption: "password".to_string(),
+            compression: None,
+        }
+    }
+
+    #[test]
+    fn test_manifest_sign_and_verify() {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let manifest = UploadManifest::new("photos", vec![entry("b.txt", b"bbb"), entry("a.txt", b"aaa")]);
+        assert_eq!(manifest.entries[0].path, "a.txt");
+
+        let signed = manifest.sign(secret_key.as_bytes(), public_key.as_bytes()).unwrap();
+        let parsed = SignedManifest::from_json(&signed.to_json().unwrap()).unwrap();
+        parsed.verify().unwrap();
+        assert_eq!(
+            parsed.signer_fingerprint().unwrap(),
+            key_fingerprint(&KeyAlgorithm::Dilithium5, public_key.as_bytes())
+        );
+
+        // Any change to an entry breaks the signature
+        let mut tampered = SignedManifest::from_json(&signed.to_json().unwrap()).unwrap();
+        tampered.manifest.entries[1].blake3 = blake3::hash(b"evil").to_hex().to_string();
+        assert!(tampered.verify().is_err());
+
+        // A mismatched key pair is caught when signing
+        let (other_public, _) = dilithium5::keypair();
+        let manifest = UploadManifest::new("photos", vec![entry("a.txt", b"aaa")]);
+        assert!(manifest.sign(secret_key.as_bytes(), other_public.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_check_file_against_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        let entry = entry("a.txt", b"original");
+
+        assert_eq!(check_file(&entry, &path).unwrap(), EntryCheck::Missing);
+        fs::write(&path, b"original").unwrap();
+        assert_eq!(check_file(&entry, &path).unwrap(), EntryCheck::Ok);
+        fs::write(&path, b"modified").unwrap();
+        assert!(matches!(check_file(&entry, &path).unwrap(), EntryCheck::HashMismatch { .. }));
+        fs::write(&path, b"longer content").unwrap();
+        assert_eq!(check_file(&entry, &path).unwrap(), EntryCheck::SizeMismatch { actual: 14 });
+    }
+}
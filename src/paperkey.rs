@@ -0,0 +1,244 @@
+use anyhow::{anyhow, Result};
+use bip39::{Language, Mnemonic};
+
+/// Words encoding the key: a BIP39 mnemonic of the 256-bit key
+const KEY_WORDS: usize = 24;
+
+/// Key words per row; each row is followed by a check word
+const WORDS_PER_ROW: usize = 4;
+
+/// Rows on the paper key
+const ROWS: usize = KEY_WORDS / WORDS_PER_ROW;
+
+/// Encode an AES-256 key as rows of words for writing down. Each row holds
+/// four words of a standard 24-word BIP39 mnemonic followed by a check word,
+/// so a typo can be traced to the word that caused it.
+pub fn encode(key: &[u8; 32]) -> Result<Vec<String>> {
+    let mnemonic = Mnemonic::from_entropy_in(Language::English, key)
+        .map_err(|e| anyhow!("Failed to encode key: {}", e))?;
+    let indexes: Vec<u16> = mnemonic
+        .words()
+        .map(|word| Language::English.find_word(word).unwrap_or_default())
+        .collect();
+
+    let word_list = Language::English.word_list();
+    Ok(indexes
+        .chunks(WORDS_PER_ROW)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut words: Vec<&str> = chunk.iter().map(|&i| word_list[i as usize]).collect();
+            words.push(word_list[row_check(row, chunk) as usize]);
+            format!("{}: {}", row + 1, words.join(" "))
+        })
+        .collect())
+}
+
+/// Decode a paper key written by `encode`. Row labels ("1:") are optional
+/// and words may be shortened to their first four letters.
+pub fn decode(phrase: &str) -> Result<[u8; 32]> {
+    let tokens: Vec<&str> = phrase
+        .split_whitespace()
+        .filter(|token| !token.ends_with(':'))
+        .collect();
+    let expected = ROWS * (WORDS_PER_ROW + 1);
+    if tokens.len() != expected {
+        return Err(anyhow!(
+            "Paper key must have {} words ({} rows of {}), got {}",
+            expected,
+            ROWS,
+            WORDS_PER_ROW + 1,
+            tokens.len()
+        ));
+    }
+
+    let mut indexes = Vec::with_capacity(expected);
+    for (position, token) in tokens.iter().enumerate() {
+        let index = find_word(token)
+            .ok_or_else(|| anyhow!("Word {} ('{}') is not in the word list", position + 1, token))?;
+        indexes.push(index);
+    }
+
+    let rows: Vec<&[u16]> = indexes.chunks(WORDS_PER_ROW + 1).collect();
+    let mut key_indexes: Vec<u16> = rows
+        .iter()
+        .flat_map(|row| row[..WORDS_PER_ROW].iter().copied())
+        .collect();
+    let bad_rows: Vec<usize> = rows
+        .iter()
+        .enumerate()
+        .filter(|(row, words)| row_check(*row, &words[..WORDS_PER_ROW]) != words[WORDS_PER_ROW])
+        .map(|(row, _)| row)
+        .collect();
+
+    if bad_rows.is_empty() {
+        if let Some(key) = key_from_indexes(&key_indexes) {
+            return Ok(key);
+        }
+        return Err(anyhow!(
+            "Paper key checksum failed; more than one word is wrong. Check every word against your copy."
+        ));
+    }
+
+    let word_list = Language::English.word_list();
+    let row = bad_rows[0];
+    if bad_rows.len() == 1 {
+        // A wrong check word leaves the key words valid
+        if key_from_indexes(&key_indexes).is_some() {
+            let position = row * (WORDS_PER_ROW + 1) + WORDS_PER_ROW;
+            return Err(anyhow!(
+                "Word {} ('{}', the check word of row {}) failed the checksum; did you mean '{}'?",
+                position + 1,
+                tokens[position],
+                row + 1,
+                word_list[row_check(row, &key_indexes[row * WORDS_PER_ROW..(row + 1) * WORDS_PER_ROW]) as usize]
+            ));
+        }
+
+        // Find the single substitution in the row that satisfies both checksums
+        let check = rows[row][WORDS_PER_ROW];
+        let mut candidates = Vec::new();
+        for offset in 0..WORDS_PER_ROW {
+            let slot = row * WORDS_PER_ROW + offset;
+            let original = key_indexes[slot];
+            for candidate in 0..word_list.len() as u16 {
+                if candidate == original {
+                    continue;
+                }
+                key_indexes[slot] = candidate;
+                let row_words = &key_indexes[row * WORDS_PER_ROW..(row + 1) * WORDS_PER_ROW];
+                if row_check(row, row_words) == check && key_from_indexes(&key_indexes).is_some() {
+                    candidates.push((offset, candidate));
+                }
+            }
+            key_indexes[slot] = original;
+        }
+
+        if let [(offset, candidate)] = candidates[..] {
+            let position = row * (WORDS_PER_ROW + 1) + offset;
+            return Err(anyhow!(
+                "Word {} ('{}') failed the checksum; did you mean '{}'?",
+                position + 1,
+                tokens[position],
+                word_list[candidate as usize]
+            ));
+        }
+    }
+
+    let first = row * (WORDS_PER_ROW + 1) + 1;
+    Err(anyhow!(
+        "Row {} (words {}-{}) failed its checksum; check those words against your copy",
+        row + 1,
+        first,
+        first + WORDS_PER_ROW
+    ))
+}
+
+/// Look up a word, also accepting an unambiguous prefix of at least four
+/// letters (BIP39 words are unique in their first four letters)
+fn find_word(token: &str) -> Option<u16> {
+    let token = token.to_lowercase();
+    if let Some(index) = Language::English.find_word(&token) {
+        return Some(index);
+    }
+    if token.len() < 4 {
+        return None;
+    }
+    let mut matches = Language::English
+        .word_list()
+        .iter()
+        .enumerate()
+        .filter(|(_, word)| word.starts_with(&token));
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Some(index as u16),
+        _ => None,
+    }
+}
+
+/// Key bytes if the 24 word indexes form a valid BIP39 mnemonic
+fn key_from_indexes(indexes: &[u16]) -> Option<[u8; 32]> {
+    let word_list = Language::English.word_list();
+    let phrase: Vec<&str> = indexes.iter().map(|&i| word_list[i as usize]).collect();
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, &phrase.join(" ")).ok()?;
+    let (entropy, len) = mnemonic.to_entropy_array();
+    if len != 32 {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&entropy[..32]);
+    Some(key)
+}
+
+/// Check word index for a row: 11 bits of a hash over the row number and its words
+fn row_check(row: usize, words: &[u16]) -> u16 {
+    let mut hasher = blake3::Hasher::new_derive_key("pipe-cli paper key row v1");
+    hasher.update(&[row as u8]);
+    for word in words {
+        hasher.update(&word.to_be_bytes());
+    }
+    let hash = hasher.finalize();
+    let bytes = hash.as_bytes();
+    (u16::from(bytes[0]) << 3) | u16::from(bytes[1] >> 5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace_word(lines: &[String], position: usize, replacement: &str) -> String {
+        let mut words: Vec<String> = lines
+            .join(" ")
+            .split_whitespace()
+            .filter(|t| !t.ends_with(':'))
+            .map(str::to_string)
+            .collect();
+        words[position] = replacement.to_string();
+        words.join(" ")
+    }
+
+    #[test]
+    fn test_paper_key_roundtrip() {
+        let key = *blake3::hash(b"paper key test").as_bytes();
+        let lines = encode(&key).unwrap();
+        assert_eq!(lines.len(), ROWS);
+        assert!(lines[0].starts_with("1: "));
+
+        assert_eq!(decode(&lines.join("\n")).unwrap(), key);
+
+        // Labels are optional, case is ignored and four-letter prefixes are enough
+        let shortened: Vec<String> = lines
+            .join(" ")
+            .split_whitespace()
+            .filter(|t| !t.ends_with(':'))
+            .map(|w| w.chars().take(4).collect::<String>().to_uppercase())
+            .collect();
+        assert_eq!(decode(&shortened.join(" ")).unwrap(), key);
+    }
+
+    #[test]
+    fn test_paper_key_typos_name_the_word() {
+        let key = [42u8; 32];
+        let lines = encode(&key).unwrap();
+        let words: Vec<String> = lines
+            .join(" ")
+            .split_whitespace()
+            .filter(|t| !t.ends_with(':'))
+            .map(str::to_string)
+            .collect();
+
+        // A valid word in the wrong place is traced back to its position
+        let wrong = if words[6] == "abandon" { "ability" } else { "abandon" };
+        let err = decode(&replace_word(&lines, 6, wrong)).unwrap_err().to_string();
+        assert!(err.contains("Word 7"), "{}", err);
+        assert!(err.contains(&format!("did you mean '{}'", words[6])), "{}", err);
+
+        // A wrong check word is reported as such
+        let wrong = if words[4] == "abandon" { "ability" } else { "abandon" };
+        let err = decode(&replace_word(&lines, 4, wrong)).unwrap_err().to_string();
+        assert!(err.contains("Word 5") && err.contains("check word"), "{}", err);
+
+        // Words outside the list and missing words
+        let err = decode(&replace_word(&lines, 12, "qwerty")).unwrap_err().to_string();
+        assert!(err.contains("Word 13 ('qwerty') is not in the word list"), "{}", err);
+        assert!(decode(&words[..29].join(" ")).is_err());
+    }
+}
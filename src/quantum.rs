@@ -8,10 +8,10 @@ use pqcrypto_traits::sign::{
     DetachedSignature, PublicKey as SignPublicKey, SecretKey as SignSecretKey,
 };
 use sha3::{Digest, Sha3_256};
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use zeroize::Zeroize;
 
-use crate::encryption::{decrypt_data, encrypt_data, EncryptionKey};
+use crate::encryption::{decrypt_data, encrypt_data, EncryptionKey, FileMetadata};
 
 /// Size of the shared secret from Kyber
 #[allow(dead_code)]
@@ -131,12 +131,149 @@ pub fn verify_dilithium_signature(
     Ok(dilithium5::verify_detached_signature(&signature, data, &public_key).is_ok())
 }
 
-/// Hybrid encryption: Kyber + AES with streaming support
-#[allow(dead_code)]
+/// Magic bytes for hybrid signature envelopes
+const HYBRID_SIGNATURE_MAGIC: &[u8] = b"PIPE-HS";
+
+/// Current hybrid signature envelope version
+const HYBRID_SIGNATURE_VERSION: u8 = 1;
+
+/// Prefix of the message signed by both halves of a hybrid signature, so
+/// neither half is valid as a plain signature over the same data
+const HYBRID_SIGNATURE_CONTEXT: &[u8] = b"pipe-cli hybrid signature v1\n";
+
+/// Ed25519 key and seed size
+const ED25519_KEY_SIZE: usize = 32;
+
+/// Generate a hybrid Ed25519 + ML-DSA (Dilithium5) signing keypair.
+///
+/// Keys are the ML-DSA key followed by the 32-byte Ed25519 key (the seed for
+/// the secret key), so they are stored wherever a Dilithium5 key is.
+pub fn generate_hybrid_signing_keypair() -> (Vec<u8>, Vec<u8>) {
+    use rand::RngCore;
+
+    let (dilithium_public, dilithium_secret) = dilithium5::keypair();
+    let mut seed = [0u8; ED25519_KEY_SIZE];
+    rand::thread_rng().fill_bytes(&mut seed);
+    let ed25519_secret = ed25519_dalek::SigningKey::from_bytes(&seed);
+
+    let mut public_key = dilithium_public.as_bytes().to_vec();
+    public_key.extend_from_slice(ed25519_secret.verifying_key().as_bytes());
+    let mut secret_key = dilithium_secret.as_bytes().to_vec();
+    secret_key.extend_from_slice(&seed);
+    seed.zeroize();
+
+    (public_key, secret_key)
+}
+
+/// Whether a signing public key is a hybrid Ed25519 + ML-DSA key
+pub fn is_hybrid_public_key(public_key: &[u8]) -> bool {
+    public_key.len() == dilithium5::public_key_bytes() + ED25519_KEY_SIZE
+}
+
+/// Whether a key is a signing public key, pure ML-DSA or hybrid
+pub fn is_signing_public_key(public_key: &[u8]) -> bool {
+    public_key.len() == dilithium5::public_key_bytes() || is_hybrid_public_key(public_key)
+}
+
+/// Sign data with a Dilithium5 or hybrid secret key. Hybrid keys produce a
+/// versioned envelope holding both signatures; Dilithium5 keys produce a
+/// plain ML-DSA signature as before.
+pub fn sign_message(data: &[u8], secret_key: &[u8]) -> Result<Vec<u8>> {
+    if secret_key.len() != dilithium5::secret_key_bytes() + ED25519_KEY_SIZE {
+        return sign_with_dilithium(data, secret_key);
+    }
+
+    let (dilithium_secret, seed) = secret_key.split_at(dilithium5::secret_key_bytes());
+    let mut seed_bytes = [0u8; ED25519_KEY_SIZE];
+    seed_bytes.copy_from_slice(seed);
+    let ed25519_secret = ed25519_dalek::SigningKey::from_bytes(&seed_bytes);
+    seed_bytes.zeroize();
+
+    let message = hybrid_signed_message(data);
+    let ed25519_signature = ed25519_dalek::Signer::sign(&ed25519_secret, &message);
+    let dilithium_signature = sign_with_dilithium(&message, dilithium_secret)?;
+
+    let mut envelope = HYBRID_SIGNATURE_MAGIC.to_vec();
+    envelope.push(HYBRID_SIGNATURE_VERSION);
+    envelope.extend_from_slice(&ed25519_signature.to_bytes());
+    envelope.extend_from_slice(&dilithium_signature);
+    Ok(envelope)
+}
+
+/// Verify a signature made by `sign_message`. A hybrid signature is only
+/// valid if both the Ed25519 and the ML-DSA signature are; a plain ML-DSA
+/// signature is only accepted for a Dilithium5 key, so a hybrid key cannot
+/// be downgraded to one of its halves.
+pub fn verify_signature(data: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool> {
+    if !signature.starts_with(HYBRID_SIGNATURE_MAGIC) {
+        if is_hybrid_public_key(public_key) {
+            return Ok(false);
+        }
+        return verify_dilithium_signature(data, signature, public_key);
+    }
+
+    let body = &signature[HYBRID_SIGNATURE_MAGIC.len()..];
+    let (&version, body) = body
+        .split_first()
+        .ok_or_else(|| anyhow!("Invalid hybrid signature"))?;
+    if version != HYBRID_SIGNATURE_VERSION {
+        return Err(anyhow!(
+            "Hybrid signature version {} is not supported",
+            version
+        ));
+    }
+    if !is_hybrid_public_key(public_key) {
+        return Err(anyhow!("Hybrid signature needs an Ed25519+Dilithium5 public key"));
+    }
+    if body.len() < ed25519_dalek::SIGNATURE_LENGTH {
+        return Err(anyhow!("Invalid hybrid signature"));
+    }
+
+    let (dilithium_public, ed25519_public) = public_key.split_at(dilithium5::public_key_bytes());
+    let (ed25519_signature, dilithium_signature) = body.split_at(ed25519_dalek::SIGNATURE_LENGTH);
+    let ed25519_public = ed25519_dalek::VerifyingKey::try_from(ed25519_public)
+        .map_err(|_| anyhow!("Invalid Ed25519 public key"))?;
+    let ed25519_signature = ed25519_dalek::Signature::from_slice(ed25519_signature)
+        .map_err(|_| anyhow!("Invalid Ed25519 signature"))?;
+
+    let message = hybrid_signed_message(data);
+    let ed25519_valid = ed25519_public.verify_strict(&message, &ed25519_signature).is_ok();
+    let dilithium_valid = verify_dilithium_signature(&message, dilithium_signature, dilithium_public)?;
+    Ok(ed25519_valid && dilithium_valid)
+}
+
+fn hybrid_signed_message(data: &[u8]) -> Vec<u8> {
+    let mut message = HYBRID_SIGNATURE_CONTEXT.to_vec();
+    message.extend_from_slice(data);
+    message
+}
+
+/// Magic bytes for Kyber-wrapped streams
+const KYBER_MAGIC: &[u8] = b"PIPE-PQ1";
+
+/// Domain separation for the AES key derived from the Kyber shared secret
+const KYBER_AES_CONTEXT: &[u8] = b"pipe-cli-kyber-aes-v1";
+
+/// Derives the stream key from a Kyber shared secret
+fn kyber_stream_key(shared_secret: &kyber1024::SharedSecret) -> EncryptionKey {
+    let mut hasher = Sha3_256::new();
+    hasher.update(shared_secret.as_bytes());
+    hasher.update(KYBER_AES_CONTEXT);
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&hasher.finalize());
+    EncryptionKey { key }
+}
+
+/// Hybrid encryption: Kyber + AES with streaming support.
+/// Writes `PIPE-PQ1`, the Kyber ciphertext, then a key-encrypted PIPE-ENC stream,
+/// so only the holder of the recipient's Kyber secret key can read it.
 pub async fn encrypt_file_with_kyber<R: Read, W: Write>(
     reader: R,
     mut writer: W,
     recipient_public_key: &[u8],
+    compression: Option<crate::compression::CompressionSpec>,
+    metadata: Option<&FileMetadata>,
     progress_callback: Option<Box<dyn Fn(usize) + Send>>,
 ) -> Result<()> {
     // Parse the public key
@@ -145,56 +282,37 @@ pub async fn encrypt_file_with_kyber<R: Read, W: Write>(
 
     // Generate ephemeral shared secret
     let (shared_secret, ciphertext) = kyber1024::encapsulate(&public_key);
+    let encryption_key = kyber_stream_key(&shared_secret);
 
-    // Derive AES key
-    let mut hasher = Sha3_256::new();
-    hasher.update(shared_secret.as_bytes());
-    hasher.update(b"pipe-cli-kyber-aes-v1"); // Domain separation
-    let aes_key_bytes = hasher.finalize();
-
-    let mut aes_key = [0u8; 32];
-    aes_key.copy_from_slice(&aes_key_bytes);
-
-    // Write header: version, ciphertext length, ciphertext
-    writer.write_all(b"PIPE-PQ1")?; // 8 bytes magic/version
+    // Write header: magic, ciphertext length, ciphertext
+    writer.write_all(KYBER_MAGIC)?;
     let ciphertext_bytes = ciphertext.as_bytes();
     writer.write_all(&(ciphertext_bytes.len() as u32).to_le_bytes())?;
     writer.write_all(ciphertext_bytes)?;
 
-    // Now encrypt the file content using AES-256-GCM streaming
-    let _encryption_key = EncryptionKey { key: aes_key };
-
-    // Use a temporary buffer for encrypted output
-    let mut temp_output = Vec::new();
-    crate::encryption::encrypt_file_with_password(
+    // Stream the file content with AES-256-GCM under the derived key
+    crate::encryption::encrypt_file_with_key(
         reader,
-        &mut temp_output,
-        "dummy", // We'll replace this with our derived key
+        writer,
+        &encryption_key,
+        compression,
+        metadata,
         progress_callback,
     )
-    .await?;
-
-    // Write the encrypted content
-    writer.write_all(&temp_output)?;
-
-    // Clear sensitive data
-    aes_key.zeroize();
-
-    Ok(())
+    .await
 }
 
-/// Hybrid decryption: Kyber + AES with streaming support  
-#[allow(dead_code)]
+/// Hybrid decryption: Kyber + AES with streaming support
 pub async fn decrypt_file_with_kyber<R: Read, W: Write>(
     mut reader: R,
     writer: W,
     recipient_secret_key: &[u8],
     progress_callback: Option<Box<dyn Fn(usize) + Send>>,
-) -> Result<()> {
+) -> Result<Option<FileMetadata>> {
     // Read and verify header
     let mut magic = [0u8; 8];
     reader.read_exact(&mut magic)?;
-    if &magic != b"PIPE-PQ1" {
+    if magic != KYBER_MAGIC {
         return Err(anyhow!("Not a Kyber-encrypted file"));
     }
 
@@ -202,6 +320,9 @@ pub async fn decrypt_file_with_kyber<R: Read, W: Write>(
     let mut len_bytes = [0u8; 4];
     reader.read_exact(&mut len_bytes)?;
     let ciphertext_len = u32::from_le_bytes(len_bytes) as usize;
+    if ciphertext_len != kyber1024::ciphertext_bytes() {
+        return Err(anyhow!("Invalid Kyber ciphertext length"));
+    }
 
     // Read ciphertext
     let mut ciphertext_bytes = vec![0u8; ciphertext_len];
@@ -214,36 +335,45 @@ pub async fn decrypt_file_with_kyber<R: Read, W: Write>(
         .map_err(|_| anyhow!("Invalid Kyber ciphertext"))?;
 
     let shared_secret = kyber1024::decapsulate(&ciphertext, &secret_key);
+    let decryption_key = kyber_stream_key(&shared_secret);
 
-    // Derive AES key
-    let mut hasher = Sha3_256::new();
-    hasher.update(shared_secret.as_bytes());
-    hasher.update(b"pipe-cli-kyber-aes-v1");
-    let aes_key_bytes = hasher.finalize();
-
-    let mut aes_key = [0u8; 32];
-    aes_key.copy_from_slice(&aes_key_bytes);
-
-    // Decrypt the remaining content
-    let _decryption_key = EncryptionKey { key: aes_key };
-
-    // For now, read all remaining data (in production, should stream)
-    let mut encrypted_content = Vec::new();
-    reader.read_to_end(&mut encrypted_content)?;
+    // Decrypt the remaining stream
+    crate::encryption::decrypt_file_with_key(reader, writer, &decryption_key, progress_callback)
+        .await
+}
 
-    // Decrypt using our existing AES-GCM decryption
-    crate::encryption::decrypt_file_with_password(
-        &encrypted_content[..],
-        writer,
-        "dummy", // We'll use our derived key instead
-        progress_callback,
-    )
-    .await?;
+/// Check if a file is a Kyber-wrapped stream by reading the magic bytes
+pub fn is_kyber_encrypted_file<R: Read + Seek>(mut reader: R) -> Result<bool> {
+    let mut magic = [0u8; 8];
+    match reader.read_exact(&mut magic) {
+        Ok(_) => {
+            reader.seek(SeekFrom::Start(0))?;
+            Ok(magic == KYBER_MAGIC)
+        }
+        Err(_) => {
+            reader.seek(SeekFrom::Start(0))?;
+            Ok(false)
+        }
+    }
+}
 
-    // Clear sensitive data
-    aes_key.zeroize();
+/// Check if a file looks like a sign-and-encrypt (.qenc) container. These carry
+/// no magic bytes, so this checks for the Kyber1024 ciphertext length prefix and
+/// a size large enough to hold the ciphertext, nonce and tag.
+pub fn is_quantum_container<R: Read + Seek>(mut reader: R) -> Result<bool> {
+    let ciphertext_len = kyber1024::ciphertext_bytes();
+    let min_size = (4 + ciphertext_len + 12 + 16) as u64;
+
+    let total_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    if total_size < min_size {
+        return Ok(false);
+    }
 
-    Ok(())
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes)?;
+    reader.seek(SeekFrom::Start(0))?;
+    Ok(u32::from_le_bytes(len_bytes) as usize == ciphertext_len)
 }
 
 /// Container for signed data
@@ -264,7 +394,7 @@ pub fn sign_and_encrypt(
     recipient_kyber_public_key: &[u8],
 ) -> Result<Vec<u8>> {
     // First sign the data
-    let signature = sign_with_dilithium(data, signing_secret_key)?;
+    let signature = sign_message(data, signing_secret_key)?;
 
     // Create signed payload
     let mut signed_payload = Vec::new();
@@ -340,7 +470,7 @@ pub fn decrypt_and_verify(
     let data = signed_payload[offset..].to_vec();
 
     // Verify signature
-    if !verify_dilithium_signature(&data, &signature, &signer_public_key)? {
+    if !verify_signature(&data, &signature, &signer_public_key)? {
         return Err(anyhow!("Signature verification failed"));
     }
 
@@ -400,6 +530,92 @@ mod tests {
         assert!(!invalid);
     }
 
+    #[test]
+    fn test_hybrid_signatures_need_both_halves() {
+        let (public_key, secret_key) = generate_hybrid_signing_keypair();
+        assert!(is_hybrid_public_key(&public_key));
+        let message = b"Signed twice over";
+
+        let signature = sign_message(message, &secret_key).unwrap();
+        assert!(signature.starts_with(HYBRID_SIGNATURE_MAGIC));
+        assert!(verify_signature(message, &signature, &public_key).unwrap());
+        assert!(!verify_signature(b"Tampered", &signature, &public_key).unwrap());
+
+        // Breaking either half fails the whole signature
+        let ed25519_start = HYBRID_SIGNATURE_MAGIC.len() + 1;
+        for position in [ed25519_start + 5, signature.len() - 100] {
+            let mut broken = signature.clone();
+            broken[position] ^= 0x01;
+            assert!(!verify_signature(message, &broken, &public_key).unwrap_or(false));
+        }
+
+        // A plain ML-DSA signature from the same key is not accepted for the hybrid key
+        let dilithium_secret = &secret_key[..dilithium5::secret_key_bytes()];
+        let downgraded = sign_with_dilithium(message, dilithium_secret).unwrap();
+        assert!(!verify_signature(message, &downgraded, &public_key).unwrap());
+
+        // Unknown envelope versions are rejected
+        let mut future = signature.clone();
+        future[HYBRID_SIGNATURE_MAGIC.len()] = HYBRID_SIGNATURE_VERSION + 1;
+        assert!(verify_signature(message, &future, &public_key).is_err());
+    }
+
+    #[test]
+    fn test_plain_dilithium_signatures_still_verify() {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let message = b"Signed before hybrid signatures";
+
+        let signature = sign_with_dilithium(message, secret_key.as_bytes()).unwrap();
+        assert!(verify_signature(message, &signature, public_key.as_bytes()).unwrap());
+        assert_eq!(sign_message(message, secret_key.as_bytes()).unwrap().len(), signature.len());
+
+        // A hybrid envelope cannot be checked against a Dilithium-only key
+        let (_, hybrid_secret) = generate_hybrid_signing_keypair();
+        let hybrid = sign_message(message, &hybrid_secret).unwrap();
+        assert!(verify_signature(message, &hybrid, public_key.as_bytes()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_kyber_stream_roundtrip_and_detection() {
+        use crate::encryption::{detect_format, EncryptionFormat};
+        use std::io::Cursor;
+
+        let (public_key, secret_key) = kyber1024::keypair();
+        let data = b"streamed through Kyber".repeat(5000);
+
+        let mut encrypted = Vec::new();
+        encrypt_file_with_kyber(Cursor::new(&data), &mut encrypted, public_key.as_bytes(), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            detect_format(Cursor::new(&encrypted)).unwrap(),
+            EncryptionFormat::Kyber
+        );
+
+        let mut decrypted = Vec::new();
+        decrypt_file_with_kyber(
+            Cursor::new(&encrypted),
+            &mut decrypted,
+            secret_key.as_bytes(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(decrypted, data);
+
+        // A different keypair cannot open the stream
+        let (_, other_secret) = kyber1024::keypair();
+        let mut out = Vec::new();
+        assert!(decrypt_file_with_kyber(
+            Cursor::new(&encrypted),
+            &mut out,
+            other_secret.as_bytes(),
+            None
+        )
+        .await
+        .is_err());
+    }
+
     #[test]
     fn test_sign_and_encrypt_works() {
         // Generate keypairs
@@ -422,5 +638,23 @@ mod tests {
             .expect("Decrypt and verify should work");
         
         assert_eq!(secret_data.to_vec(), signed_data.data);
+
+        // The container is only recognised when a .qenc container is expected
+        assert_eq!(
+            crate::encryption::detect_format_expecting(std::io::Cursor::new(&encrypted), true).unwrap(),
+            crate::encryption::EncryptionFormat::QuantumSigned
+        );
+        assert_eq!(
+            crate::encryption::detect_format(std::io::Cursor::new(&encrypted)).unwrap(),
+            crate::encryption::EncryptionFormat::Plain
+        );
+
+        // A plain file that happens to start with the ciphertext length prefix
+        let mut lookalike = (kyber1024::ciphertext_bytes() as u32).to_le_bytes().to_vec();
+        lookalike.resize(4096, 0);
+        assert_eq!(
+            crate::encryption::detect_format(std::io::Cursor::new(&lookalike)).unwrap(),
+            crate::encryption::EncryptionFormat::Plain
+        );
     }
 }
@@ -1,8 +1,6 @@
 use anyhow::{anyhow, Result};
 use pqcrypto_mlkem::mlkem1024 as kyber1024;
-use pqcrypto_mldsa::mldsa87 as dilithium5;
 use pqcrypto_traits::kem::{PublicKey as KemPublicKey, SecretKey as KemSecretKey};
-use pqcrypto_traits::sign::{PublicKey as SignPublicKey, SecretKey as SignSecretKey};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -14,9 +12,9 @@ pub struct QuantumKeyPair {
     pub kyber_public: Vec<u8>,
     /// Kyber secret key for decryption
     pub kyber_secret: Vec<u8>,
-    /// Dilithium public key for verification
+    /// Dilithium (or hybrid Ed25519 + Dilithium) public key for verification
     pub dilithium_public: Vec<u8>,
-    /// Dilithium secret key for signing
+    /// Dilithium (or hybrid Ed25519 + Dilithium) secret key for signing
     pub dilithium_secret: Vec<u8>,
     /// File identifier (hash or name)
     pub file_id: String,
@@ -24,7 +22,8 @@ pub struct QuantumKeyPair {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Get the quantum keyring directory
+/// Get the directory of plaintext per-file keypairs written by older versions.
+/// New keypairs are stored encrypted in the main keyring.
 fn get_keyring_dir() -> Result<PathBuf> {
     let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
     let keyring_dir = home.join(".pipe-cli").join("quantum-keys");
@@ -39,20 +38,21 @@ pub fn generate_quantum_keypair(file_id: &str) -> Result<QuantumKeyPair> {
     // Generate Kyber keypair for encryption
     let (kyber_public, kyber_secret) = kyber1024::keypair();
     
-    // Generate Dilithium keypair for signing
-    let (dilithium_public, dilithium_secret) = dilithium5::keypair();
+    // Generate hybrid Ed25519 + Dilithium keypair for signing
+    let (dilithium_public, dilithium_secret) = crate::quantum::generate_hybrid_signing_keypair();
     
     Ok(QuantumKeyPair {
         kyber_public: kyber_public.as_bytes().to_vec(),
         kyber_secret: kyber_secret.as_bytes().to_vec(),
-        dilithium_public: dilithium_public.as_bytes().to_vec(),
-        dilithium_secret: dilithium_secret.as_bytes().to_vec(),
+        dilithium_public,
+        dilithium_secret,
         file_id: file_id.to_string(),
         created_at: chrono::Utc::now(),
     })
 }
 
-/// Save quantum keypair to keyring
+/// Save quantum keypair as plaintext (legacy format, see `Keyring::store_quantum_keypair`)
+#[allow(dead_code)]
 pub fn save_quantum_keypair(keypair: &QuantumKeyPair) -> Result<()> {
     let keyring_dir = get_keyring_dir()?;
     let key_file = keyring_dir.join(format!("{}.quantum", keypair.file_id));
@@ -64,7 +64,7 @@ pub fn save_quantum_keypair(keypair: &QuantumKeyPair) -> Result<()> {
     Ok(())
 }
 
-/// Load quantum keypair from keyring
+/// Load a plaintext quantum keypair written by older versions
 pub fn load_quantum_keypair(file_id: &str) -> Result<QuantumKeyPair> {
     let keyring_dir = get_keyring_dir()?;
     let key_file = keyring_dir.join(format!("{}.quantum", file_id));
@@ -78,8 +78,7 @@ pub fn load_quantum_keypair(file_id: &str) -> Result<QuantumKeyPair> {
     Ok(keypair)
 }
 
-/// List all quantum keys in the keyring
-#[allow(dead_code)]
+/// List all plaintext quantum keypairs awaiting migration
 pub fn list_quantum_keys() -> Result<Vec<String>> {
     let keyring_dir = get_keyring_dir()?;
     let mut keys = Vec::new();
@@ -97,8 +96,7 @@ pub fn list_quantum_keys() -> Result<Vec<String>> {
     Ok(keys)
 }
 
-/// Delete a quantum keypair
-#[allow(dead_code)]
+/// Delete a plaintext quantum keypair
 pub fn delete_quantum_keypair(file_id: &str) -> Result<()> {
     let keyring_dir = get_keyring_dir()?;
     let key_file = keyring_dir.join(format!("{}.quantum", file_id));
@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::catalog::{CatalogEntry, DELETED_STATUS};
+use crate::sync::FileState;
+
+/// Files pipe-cli uploads next to other files without cataloguing them:
+/// signatures from `--sign` and signed directory manifests
+pub fn is_sidecar(remote_path: &str) -> bool {
+    remote_path.ends_with(".sig") || remote_path.ends_with(".pipe-manifest.json")
+}
+
+/// A difference between the upload catalog and remote storage
+#[derive(Debug)]
+pub enum Discrepancy {
+    /// In remote storage but not recorded as uploaded. `attempt` is the
+    /// newest upload record for the path, if any: a failed upload that landed
+    /// anyway, or an upload since recorded as deleted.
+    RemoteOnly {
+        remote_path: String,
+        size: u64,
+        attempt: Option<CatalogEntry>,
+    },
+    /// Recorded as uploaded but gone from remote storage
+    LogOnly(CatalogEntry),
+    /// The recorded size differs from the remote size
+    SizeMismatch { record: CatalogEntry, remote_size: u64 },
+    /// Recorded as uploaded without a Blake3 hash
+    MissingHash(CatalogEntry),
+}
+
+impl Discrepancy {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Discrepancy::RemoteOnly { .. } => "remote-only",
+            Discrepancy::LogOnly(_) => "log-only",
+            Discrepancy::SizeMismatch { .. } => "size mismatch",
+            Discrepancy::MissingHash(_) => "missing hash",
+        }
+    }
+
+    pub fn remote_path(&self) -> &str {
+        match self {
+            Discrepancy::RemoteOnly { remote_path, .. } => remote_path,
+            Discrepancy::LogOnly(record)
+            | Discrepancy::SizeMismatch { record, .. }
+            | Discrepancy::MissingHash(record) => &record.entry.remote_path,
+        }
+    }
+
+    /// A remote-only file nobody ever tried to upload from this machine
+    pub fn is_orphan(&self) -> bool {
+        matches!(self, Discrepancy::RemoteOnly { attempt: None, .. })
+    }
+}
+
+#[derive(Default)]
+struct PathRecords {
+    /// Newest successful upload, unless recorded as deleted since
+    uploaded: Option<CatalogEntry>,
+    /// Newest failed or deleted upload
+    attempt: Option<CatalogEntry>,
+}
+
+/// Compare the remote listing with catalog records, given oldest first.
+/// Results are ordered by remote path.
+pub fn reconcile(remote: &HashMap<String, FileState>, records: Vec<CatalogEntry>) -> Vec<Discrepancy> {
+    let mut by_path: BTreeMap<String, PathRecords> = BTreeMap::new();
+    for record in records {
+        let seen = by_path.entry(record.entry.remote_path.clone()).or_default();
+        if record.entry.status == DELETED_STATUS {
+            if let Some(deleted) = seen.uploaded.take() {
+                seen.attempt = Some(deleted);
+            }
+        } else if record.entry.status.contains("SUCCESS") {
+            seen.uploaded = Some(record);
+        } else {
+            seen.attempt = Some(record);
+        }
+    }
+
+    let mut found = Vec::new();
+    for (remote_path, seen) in by_path.iter_mut() {
+        match (seen.uploaded.take(), remote.get(remote_path)) {
+            (Some(record), None) => found.push(Discrepancy::LogOnly(record)),
+            (Some(record), Some(state)) => {
+                // A size mismatch is the bigger problem; report that alone
+                if record.entry.file_size.is_some_and(|size| size != state.size) {
+                    found.push(Discrepancy::SizeMismatch { record, remote_size: state.size });
+                } else if record.entry.blake3_hash.is_none() {
+                    found.push(Discrepancy::MissingHash(record));
+                }
+            }
+            (None, Some(state)) if !is_sidecar(remote_path) => found.push(Discrepancy::RemoteOnly {
+                remote_path: remote_path.clone(),
+                size: state.size,
+                attempt: seen.attempt.take(),
+            }),
+            _ => {}
+        }
+    }
+
+    for (remote_path, state) in remote {
+        if !by_path.contains_key(remote_path) && !is_sidecar(remote_path) {
+            found.push(Discrepancy::RemoteOnly {
+                remote_path: remote_path.clone(),
+                size: state.size,
+                attempt: None,
+            });
+        }
+    }
+
+    found.sort_by(|a, b| a.remote_path().cmp(b.remote_path()));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UploadLogEntry;
+    use chrono::Utc;
+
+    fn record(id: i64, remote: &str, status: &str, hash: Option<&str>, size: Option<u64>) -> CatalogEntry {
+        CatalogEntry {
+            id,
+            entry: UploadLogEntry {
+                local_path: format!("/local/{}", remote),
+                remote_path: remote.to_string(),
+                status: status.to_string(),
+                message: String::new(),
+                blake3_hash: hash.map(str::to_string),
+                file_size: size,
+                timestamp: None,
+                cost: None,
+            },
+        }
+    }
+
+    fn remote_file(path: &str, size: u64) -> (String, FileState) {
+        let state = FileState {
+            path: path.to_string(),
+            size,
+            modified: Utc::now(),
+            hash: None,
+            last_synced: None,
+            sync_version: 0,
+            remote_modified: None,
+            remote_size: None,
+        };
+        (path.to_string(), state)
+    }
+
+    #[test]
+    fn test_reconcile_classifies_differences() {
+        let remote: HashMap<String, FileState> = [
+            remote_file("ok.txt", 10),
+            remote_file("landed.txt", 5),
+            remote_file("stray.txt", 7),
+            remote_file("stray.txt.sig", 900),
+            remote_file("resized.txt", 11),
+            remote_file("nohash.txt", 3),
+            remote_file("again.txt", 4),
+        ]
+        .into_iter()
+        .collect();
+        let records = vec![
+            record(1, "ok.txt", "SUCCESS", Some("aa"), Some(10)),
+            record(2, "landed.txt", "FAIL", None, None),
+            record(3, "resized.txt", "SUCCESS", Some("bb"), Some(10)),
+            record(4, "nohash.txt", "PRIORITY SUCCESS", None, None),
+            record(5, "gone.txt", "SUCCESS", Some("cc"), Some(1)),
+            record(6, "again.txt", "SUCCESS", Some("dd"), Some(4)),
+            record(7, "again.txt", DELETED_STATUS, None, None),
+            record(8, "forgotten.txt", "SUCCESS", Some("ee"), Some(2)),
+            record(9, "forgotten.txt", DELETED_STATUS, None, None),
+        ];
+
+        let found = reconcile(&remote, records);
+        let summary: Vec<(&str, &str, bool)> = found
+            .iter()
+            .map(|d| (d.remote_path(), d.kind(), d.is_orphan()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("again.txt", "remote-only", false),
+                ("gone.txt", "log-only", false),
+                ("landed.txt", "remote-only", false),
+                ("nohash.txt", "missing hash", false),
+                ("resized.txt", "size mismatch", false),
+                ("stray.txt", "remote-only", true),
+            ]
+        );
+        match &found[0] {
+            Discrepancy::RemoteOnly { attempt: Some(attempt), .. } => assert_eq!(attempt.id, 6),
+            other => panic!("unexpected {:?}", other),
+        }
+        match &found[2] {
+            Discrepancy::RemoteOnly { attempt: Some(attempt), size, .. } => {
+                assert_eq!(attempt.entry.local_path, "/local/landed.txt");
+                assert_eq!(*size, 5);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::keyring::{key_fingerprint, KeyAlgorithm};
+use crate::quantum::{sign_message, verify_signature};
+
+/// Current sidecar format version
+const SIDECAR_VERSION: u8 = 2;
+
+/// Prefix of the signed message, so a sidecar signature is never valid in
+/// another context. Version 1 signed only the hash.
+const SIGNATURE_CONTEXT_V1: &[u8] = b"pipe-cli file signature v1\n";
+const SIGNATURE_CONTEXT: &[u8] = b"pipe-cli file signature v2\n";
+
+/// Signature envelope, written by `sign-file` and stored next to uploaded
+/// files as `<name>.sig`.
+///
+/// The Dilithium5 or hybrid Ed25519+Dilithium5 signature covers the version,
+/// signer fingerprint, signing time, Blake3 hash of the file's plaintext and
+/// comment, so it is checked after a download is decrypted.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignatureSidecar {
+    pub version: u8,
+    pub signer_fingerprint: String,
+    /// Hex-encoded Dilithium5 or hybrid Ed25519+Dilithium5 public key
+    signer_public_key: String,
+    /// Missing in version 1 envelopes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_at: Option<DateTime<Utc>>,
+    /// Blake3 hash of the signed file
+    pub blake3: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Hex-encoded detached signature
+    signature: String,
+}
+
+/// Fields covered by a version 2 signature, in this order
+#[derive(Serialize)]
+struct SignedFields<'a> {
+    version: u8,
+    signer_fingerprint: &'a str,
+    signed_at: &'a Option<DateTime<Utc>>,
+    blake3: &'a str,
+    comment: &'a Option<String>,
+}
+
+impl SignatureSidecar {
+    /// Sign a file given its Blake3 hash
+    pub fn sign(
+        blake3: &str,
+        comment: Option<String>,
+        secret_key: &[u8],
+        public_key: &[u8],
+    ) -> Result<Self> {
+        let mut sidecar = Self {
+            version: SIDECAR_VERSION,
+            signer_fingerprint: key_fingerprint(&KeyAlgorithm::for_signing_key(public_key), public_key),
+            signer_public_key: hex::encode(public_key),
+            signed_at: Some(Utc::now()),
+            blake3: blake3.to_string(),
+            comment,
+            signature: String::new(),
+        };
+        sidecar.signature = hex::encode(sign_message(&sidecar.signed_message()?, secret_key)?);
+        // Catch a secret key that does not match the public key
+        sidecar.verify_signature()?;
+        Ok(sidecar)
+    }
+
+    /// Parse an envelope. Raw signature bytes from older `sign-file` versions
+    /// are not envelopes and fail here.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let sidecar: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("Not a valid signature file: {}", e))?;
+        if sidecar.version > SIDECAR_VERSION {
+            return Err(anyhow!(
+                "Signature version {} is newer than supported version {}",
+                sidecar.version,
+                SIDECAR_VERSION
+            ));
+        }
+        // Version 1 signed only the hash, so a time or comment would be unsigned
+        if sidecar.version == 1 && (sidecar.signed_at.is_some() || sidecar.comment.is_some()) {
+            return Err(anyhow!(
+                "Version 1 signature carries a signing time or comment it does not cover"
+            ));
+        }
+        Ok(sidecar)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn signer_public_key(&self) -> Result<Vec<u8>> {
+        hex::decode(&self.signer_public_key).map_err(|_| anyhow!("Signature has an invalid signer key"))
+    }
+
+    /// Check that the signature is valid for the recorded hash and that the
+    /// recorded fingerprint belongs to the embedded key
+    pub fn verify_signature(&self) -> Result<()> {
+        let public_key = self.signer_public_key()?;
+        if key_fingerprint(&KeyAlgorithm::for_signing_key(&public_key), &public_key) != self.signer_fingerprint {
+            return Err(anyhow!("Signer fingerprint does not match the signer key"));
+        }
+        let signature =
+            hex::decode(&self.signature).map_err(|_| anyhow!("Signature is not valid hex"))?;
+        if !verify_signature(&self.signed_message()?, &signature, &public_key)? {
+            return Err(anyhow!("Signature is invalid"));
+        }
+        Ok(())
+    }
+
+    /// Check the signature and that the file is the one that was signed
+    pub fn verify_file(&self, path: &Path) -> Result<()> {
+        self.verify_signature()?;
+        if hash_file(path)? != self.blake3 {
+            return Err(anyhow!(
+                "'{}' does not match its signature; the file was modified",
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    fn signed_message(&self) -> Result<Vec<u8>> {
+        if self.version == 1 {
+            let mut message = SIGNATURE_CONTEXT_V1.to_vec();
+            message.extend_from_slice(self.blake3.as_bytes());
+            return Ok(message);
+        }
+        let fields = SignedFields {
+            version: self.version,
+            signer_fingerprint: &self.signer_fingerprint,
+            signed_at: &self.signed_at,
+            blake3: &self.blake3,
+            comment: &self.comment,
+        };
+        let mut message = SIGNATURE_CONTEXT.to_vec();
+        message.extend_from_slice(&serde_json::to_vec(&fields)?);
+        Ok(message)
+    }
+}
+
+/// Blake3 hash of a file as hex
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pqcrypto_mldsa::mldsa87 as dilithium5;
+    use pqcrypto_traits::sign::{PublicKey, SecretKey};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sidecar_sign_and_verify_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("report.pdf");
+        fs::write(&path, b"quarterly numbers").unwrap();
+
+        let (public_key, secret_key) = dilithium5::keypair();
+        let sidecar = SignatureSidecar::sign(
+            &hash_file(&path).unwrap(),
+            Some("Q3 final".to_string()),
+            secret_key.as_bytes(),
+            public_key.as_bytes(),
+        )
+        .unwrap();
+        let parsed = SignatureSidecar::from_json(&sidecar.to_json().unwrap()).unwrap();
+        parsed.verify_file(&path).unwrap();
+        assert_eq!(parsed.signer_public_key().unwrap(), public_key.as_bytes());
+
+        // A modified file fails
+        fs::write(&path, b"quarterly numbers, revised").unwrap();
+        assert!(parsed.verify_file(&path).is_err());
+
+        // A sidecar whose hash was swapped fails
+        let mut forged = SignatureSidecar::from_json(&sidecar.to_json().unwrap()).unwrap();
+        forged.blake3 = hash_file(&path).unwrap();
+        assert!(forged.verify_file(&path).is_err());
+
+        // So does one whose fingerprint was relabelled
+        let mut relabelled = SignatureSidecar::from_json(&sidecar.to_json().unwrap()).unwrap();
+        relabelled.signer_fingerprint = "00".repeat(32);
+        assert!(relabelled.verify_signature().is_err());
+
+        // The comment and signing time are covered too
+        let mut recommented = SignatureSidecar::from_json(&sidecar.to_json().unwrap()).unwrap();
+        recommented.comment = Some("Q3 draft".to_string());
+        assert!(recommented.verify_signature().is_err());
+        let mut backdated = SignatureSidecar::from_json(&sidecar.to_json().unwrap()).unwrap();
+        backdated.signed_at = Some(Utc::now() - chrono::Duration::days(30));
+        assert!(backdated.verify_signature().is_err());
+    }
+
+    #[test]
+    fn test_version_1_sidecars_still_verify() {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let blake3 = blake3::hash(b"signed by an older version").to_hex().to_string();
+        let mut message = SIGNATURE_CONTEXT_V1.to_vec();
+        message.extend_from_slice(blake3.as_bytes());
+        let json = serde_json::json!({
+            "version": 1,
+            "signer_fingerprint": key_fingerprint(&KeyAlgorithm::Dilithium5, public_key.as_bytes()),
+            "signer_public_key": hex::encode(public_key.as_bytes()),
+            "blake3": blake3,
+            "signature": hex::encode(sign_message(&message, secret_key.as_bytes()).unwrap()),
+        });
+
+        let sidecar = SignatureSidecar::from_json(&json.to_string()).unwrap();
+        sidecar.verify_signature().unwrap();
+        assert!(sidecar.signed_at.is_none());
+        assert!(SignatureSidecar::from_json("not an envelope").is_err());
+
+        // A comment added to a version 1 envelope is not covered by its signature
+        let mut forged = json.clone();
+        forged["comment"] = serde_json::json!("approved for release");
+        assert!(SignatureSidecar::from_json(&forged.to_string()).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::collections::HashMap;
 use std::time::{SystemTime, Duration, Instant};
 use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
@@ -13,6 +13,7 @@ use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
 use blake3;
 
 use crate::{SavedCredentials, upload_file_with_auth, improved_download_file_with_auth};
+use crate::compression::{self, CompressionSpec};
 use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 
 // Same encoding set as in lib.rs
@@ -48,7 +49,7 @@ struct FileToUpload {
     local_path: PathBuf,
     relative_path: String,
     size: u64,
-    _hash: String,
+    hash: String,
     _modified: DateTime<Utc>,
 }
 
@@ -81,6 +82,9 @@ pub struct FileState {
     pub last_synced: Option<DateTime<Utc>>, // When this file was last synced
     pub sync_version: u32, // Version number for tracking changes
     pub remote_modified: Option<DateTime<Utc>>, // Remote file modification time
+    /// Size in remote storage, when it differs from the local size (compressed uploads)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_size: Option<u64>,
 }
 
 impl FileState {
@@ -103,6 +107,7 @@ pub enum ConflictStrategy {
 }
 
 impl ConflictStrategy {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "newer" => Some(Self::Newer),
@@ -174,7 +179,7 @@ impl SyncState {
 }
 
 /// Format file size in human-readable format
-fn format_file_size(size: u64) -> String {
+pub(crate) fn format_file_size(size: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size = size as f64;
     let mut unit_idx = 0;
@@ -198,6 +203,7 @@ pub struct SyncContext {
     pub conflict_strategy: ConflictStrategy,
     pub dry_run: bool,
     pub state: SyncState,
+    pub compression: Option<CompressionSpec>,
 }
 
 /// Get file metadata as FileState
@@ -211,11 +217,12 @@ async fn get_file_state(path: &Path, relative_path: &str) -> Result<FileState> {
         path: relative_path.to_string(),
         size: metadata.len(),
         modified: DateTime::from_timestamp(modified as i64, 0)
-            .unwrap_or_else(|| Utc::now()),
+            .unwrap_or_else(Utc::now),
         hash: calculate_file_hash(path).await.ok(),
         last_synced: None,
         sync_version: 0,
         remote_modified: None,
+        remote_size: None,
     })
 }
 
@@ -380,7 +387,7 @@ async fn list_local_files_recursive_with_bytes(
                     .duration_since(SystemTime::UNIX_EPOCH)?
                     .as_secs();
                 let current_modified = DateTime::from_timestamp(modified as i64, 0)
-                    .unwrap_or_else(|| Utc::now());
+                    .unwrap_or_else(Utc::now);
                 
                 if existing_state.size == file_size && existing_state.modified == current_modified {
                     // File unchanged, skip hashing
@@ -503,7 +510,7 @@ async fn scan_files_recursive(
                 .duration_since(SystemTime::UNIX_EPOCH)?
                 .as_secs();
             let modified_dt = DateTime::from_timestamp(modified as i64, 0)
-                .unwrap_or_else(|| Utc::now());
+                .unwrap_or_else(Utc::now);
             
             // Get relative path
             let relative_path = path.strip_prefix(base_path)?
@@ -588,6 +595,7 @@ async fn hash_pipeline(
                 last_synced: None,
                 sync_version: 0,
                 remote_modified: None,
+                remote_size: None,
             });
         }
         
@@ -600,7 +608,7 @@ async fn hash_pipeline(
                 local_path: file.path,
                 relative_path: file.relative_path,
                 size: file.size,
-                _hash: hash,
+                hash,
                 _modified: file.modified,
             };
             
@@ -642,6 +650,24 @@ async fn save_partial_state(progress: &Arc<StreamingProgress>) -> Result<()> {
     Ok(())
 }
 
+/// Upload a single file, compressing it to a temp file first if requested
+async fn upload_sync_file(
+    client: &Client,
+    local_path: &Path,
+    full_url: &str,
+    remote_path: &str,
+    creds: &SavedCredentials,
+    compression: Option<CompressionSpec>,
+) -> Result<String> {
+    match compression {
+        Some(spec) => {
+            let temp = compression::compress_to_temp_file(local_path, spec)?;
+            upload_file_with_auth(client, temp.path(), full_url, remote_path, creds).await
+        }
+        None => upload_file_with_auth(client, local_path, full_url, remote_path, creds).await,
+    }
+}
+
 /// Upload pipeline with concurrent workers
 async fn upload_pipeline(
     upload_rx: mpsc::Receiver<FileToUpload>,
@@ -663,6 +689,7 @@ async fn upload_pipeline(
         let base_url = ctx.base_url.clone();
         let creds = ctx.creds.clone();
         let remote_path = ctx.remote_path.clone();
+        let compression = ctx.compression;
         
         let handle = tokio::spawn(async move {
             loop {
@@ -694,14 +721,28 @@ async fn upload_pipeline(
                     base_url,
                     utf8_percent_encode(&remote_file_path, QUERY_ENCODE_SET)
                 );
-                match upload_file_with_auth(
+                match upload_sync_file(
                     &client,
                     &file.local_path,
                     &full_url,
                     &remote_file_path,
                     &creds,
+                    compression,
                 ).await {
                     Ok(_) => {
+                        // Record the upload so reconcile does not take it for an orphan
+                        if let Err(e) = crate::append_to_upload_log_with_hash(
+                            &file.local_path.display().to_string(),
+                            &remote_file_path,
+                            "SUCCESS",
+                            "Sync upload",
+                            Some(file.hash.clone()),
+                            Some(file.size),
+                            None,
+                        ) {
+                            eprintln!("Failed to record upload of {}: {}", file.relative_path, e);
+                        }
+
                         // Update progress
                         progress.files_uploaded.fetch_add(1, Ordering::Relaxed);
                         progress.bytes_uploaded.fetch_add(file.size, Ordering::Relaxed);
@@ -798,11 +839,12 @@ async fn execute_streaming_sync(
     let (upload_tx, upload_rx) = mpsc::channel::<FileToUpload>(100);
     
     // Create progress tracking
-    let progress = create_streaming_progress(
-        ctx.state.files.is_empty().then(|| ctx.local_path.join(".pipe-sync.partial"))
-            .unwrap_or_else(|| ctx.local_path.join(".pipe-sync")),
-        ctx.state.files.clone(),
-    );
+    let state_path = if ctx.state.files.is_empty() {
+        ctx.local_path.join(".pipe-sync.partial")
+    } else {
+        ctx.local_path.join(".pipe-sync")
+    };
+    let progress = create_streaming_progress(state_path, ctx.state.files.clone());
     
     // Spawn progress updater
     let progress_updater = {
@@ -882,55 +924,30 @@ pub async fn list_remote_files(
     creds: &SavedCredentials,
     prefix: Option<&str>,
 ) -> Result<HashMap<String, FileState>> {
-    use crate::add_auth_headers;
-    
     let mut files = HashMap::new();
-    
-    // Build list files request
-    let url = format!("{}/listFiles", base_url);
-    let mut request = client.get(&url);
-    request = add_auth_headers(request, creds, true);
-    
-    let response = request.send().await?;
-    if !response.status().is_success() {
-        return Err(anyhow::anyhow!("Failed to list files: {}", response.status()));
-    }
-    
-    // Parse response
-    let json_response: serde_json::Value = response.json().await?;
-    
-    if let Some(files_array) = json_response.as_array() {
-        for file_json in files_array {
-            let path = file_json["file_name"].as_str().unwrap_or_default().to_string();
-            
-            // Filter by prefix if provided
-            if let Some(prefix) = prefix {
-                if !path.starts_with(prefix) {
-                    continue;
-                }
+    let entries = crate::listing::fetch_remote_entries(client, base_url, creds, true).await?;
+    for entry in entries {
+        // Filter by prefix if provided
+        if let Some(prefix) = prefix {
+            if !entry.name.starts_with(prefix) {
+                continue;
             }
-            
-            // Extract file info
-            let size = file_json["size"].as_u64().unwrap_or(0);
-            let uploaded_at = file_json["uploaded_at"].as_str()
-                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|| Utc::now());
-            
-            let file_state = FileState {
-                path: path.clone(),
-                size,
-                modified: uploaded_at,
-                hash: None,
-                last_synced: None,
-                sync_version: 0,
-                remote_modified: None,
-            };
-            
-            files.insert(path, file_state);
         }
+
+        let file_state = FileState {
+            path: entry.name.clone(),
+            size: entry.size,
+            modified: entry.uploaded_at.unwrap_or_else(Utc::now),
+            hash: None,
+            last_synced: None,
+            sync_version: 0,
+            remote_modified: entry.uploaded_at,
+            remote_size: None,
+        };
+
+        files.insert(entry.name, file_state);
     }
-    
+
     Ok(files)
 }
 
@@ -972,7 +989,7 @@ pub fn compare_files(
     }
     
     // Check remote files not in local
-    for (path, _remote_state) in remote_files {
+    for path in remote_files.keys() {
         if !local_files.contains_key(path) {
             // File only exists remotely - download
             operations.push(SyncOperation::Download(path.clone()));
@@ -1175,7 +1192,6 @@ pub async fn execute_sync(
     let semaphore = Arc::new(tokio::sync::Semaphore::new(ctx.state.files.len().min(10)));
     let results = stream::iter(operations)
         .map(|op| {
-            let ctx = ctx;
             let semaphore = semaphore.clone();
             let multi_progress = multi_progress.clone();
             let overall_pb = overall_pb.clone();
@@ -1254,12 +1270,13 @@ async fn execute_upload(
         ctx.base_url,
         utf8_percent_encode(&remote_path, QUERY_ENCODE_SET)
     );
-    let result = upload_file_with_auth(
+    let result = upload_sync_file(
         &ctx.client,
         &full_path,
         &full_url,
         &remote_path,
         &ctx.creds,
+        ctx.compression,
     ).await;
     
     pb.finish_and_clear();
@@ -1328,7 +1345,230 @@ async fn execute_download(
     }
 }
 
+/// What a download sync does with one remote file
+#[derive(Debug, PartialEq)]
+enum DownloadPlan {
+    Download,
+    /// Neither side changed since the last sync
+    Unchanged,
+    /// The local copy changed since the last sync, or was never synced
+    LocalChanged,
+}
+
+/// Decide whether a remote file needs downloading, from the state recorded
+/// at the last sync and the current local file (hashed), if any. The remote
+/// file counts as changed when its size or, if known, its upload time differs.
+fn plan_download(remote: &FileState, last: Option<&FileState>, local: Option<&FileState>) -> DownloadPlan {
+    let (Some(last), Some(local)) = (last, local) else {
+        return if local.is_none() { DownloadPlan::Download } else { DownloadPlan::LocalChanged };
+    };
+    if local.size != last.size || local.hash.is_none() || local.hash != last.hash {
+        return DownloadPlan::LocalChanged;
+    }
+    let resized = last.remote_size.unwrap_or(last.size) != remote.size;
+    let replaced = matches!(
+        (last.remote_modified, remote.remote_modified),
+        (Some(synced), Some(uploaded)) if synced != uploaded
+    );
+    if resized || replaced {
+        DownloadPlan::Download
+    } else {
+        DownloadPlan::Unchanged
+    }
+}
+
+/// Path of a remote file relative to the synced prefix. `photos` and
+/// `photos/` both cover `photos/a.jpg` but not `photos.zip`.
+fn relative_remote_path<'a>(prefix: &str, name: &'a str) -> Option<&'a str> {
+    let base = prefix.trim_end_matches('/');
+    if base.is_empty() {
+        Some(name)
+    } else {
+        name.strip_prefix(base)?.strip_prefix('/')
+    }
+}
+
+/// Whether a relative path stays inside the directory it is joined to
+fn stays_inside(relative: &str) -> bool {
+    !relative.is_empty()
+        && Path::new(relative)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Download one file next to its destination, then move it into place, so an
+/// interrupted sync never leaves a partial file under the real name
+async fn download_sync_file(ctx: &SyncContext, remote_name: &str, local_path: &Path) -> Result<()> {
+    if let Some(parent) = local_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let file_name = local_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    // The .pipe-sync prefix keeps upload sync from picking up leftovers
+    let temp_path = local_path.with_file_name(format!(".pipe-sync.{}.part", file_name));
+
+    let result = improved_download_file_with_auth(
+        &ctx.client,
+        &ctx.base_url,
+        &ctx.creds,
+        remote_name,
+        temp_path.to_string_lossy().as_ref(),
+    )
+    .await;
+    match result {
+        Ok(()) => Ok(fs::rename(&temp_path, local_path).await?),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path).await;
+            Err(e)
+        }
+    }
+}
+
+/// Mirror remote files under `ctx.remote_path` into `ctx.local_path`,
+/// `parallel` at a time. Returns the sync state for the downloaded and
+/// unchanged files.
+async fn execute_download_sync(ctx: &SyncContext, parallel: usize) -> Result<SyncState> {
+    use futures_util::stream::{self, StreamExt};
+
+    let remote_files = list_remote_files(&ctx.client, &ctx.base_url, &ctx.creds, Some(&ctx.remote_path)).await?;
+    let mut state = SyncState {
+        last_sync: ctx.state.last_sync,
+        files: HashMap::new(),
+    };
+    let mut to_download = Vec::new();
+    let mut unchanged = 0;
+
+    let mut names: Vec<&String> = remote_files.keys().collect();
+    names.sort();
+    for name in names {
+        let remote = &remote_files[name];
+        let Some(relative) = relative_remote_path(&ctx.remote_path, name) else {
+            continue;
+        };
+        if !stays_inside(relative) {
+            eprintln!("⚠️  Skipping {}: path leaves the sync directory", name);
+            continue;
+        }
+        let skip_name = Path::new(relative)
+            .file_name()
+            .map(|n| n.to_string_lossy().starts_with(".pipe-sync"))
+            .unwrap_or(false);
+        if skip_name {
+            continue;
+        }
+
+        let local_path = ctx.local_path.join(relative);
+        let local = if local_path.is_file() {
+            Some(get_file_state(&local_path, relative).await?)
+        } else {
+            None
+        };
+        let last = ctx.state.files.get(relative);
+
+        let download = match plan_download(remote, last, local.as_ref()) {
+            DownloadPlan::Download => true,
+            DownloadPlan::Unchanged => {
+                unchanged += 1;
+                false
+            }
+            DownloadPlan::LocalChanged => {
+                let local = local.as_ref().expect("only planned for existing files");
+                match resolve_conflict(local, remote, ctx.conflict_strategy) {
+                    ConflictResolution::UseRemote => true,
+                    ConflictResolution::UseLocal => {
+                        println!("⚠️  Keeping local changes to {}", relative);
+                        false
+                    }
+                    ConflictResolution::Conflict => {
+                        eprintln!("⚠️  Conflict: {} changed locally - skipping", relative);
+                        false
+                    }
+                }
+            }
+        };
+        if download {
+            to_download.push((name.clone(), relative.to_string(), local_path, remote));
+            continue;
+        }
+        // Keep the last synced state, so remote changes are still noticed
+        if let Some(last) = last {
+            state.files.insert(relative.to_string(), last.clone());
+        }
+    }
+
+    // Local copies are kept; they drop out of the sync state
+    let base = ctx.remote_path.trim_end_matches('/');
+    for path in ctx.state.files.keys() {
+        let remote_name = if base.is_empty() { path.clone() } else { format!("{}/{}", base, path) };
+        if !remote_files.contains_key(&remote_name) {
+            eprintln!("⚠️  File {} was deleted remotely", path);
+        }
+    }
+
+    println!(
+        "📋 {} file(s) to download, {} unchanged",
+        to_download.len(),
+        unchanged
+    );
+    if ctx.dry_run {
+        for (name, _, local_path, _) in &to_download {
+            println!("Would download: {} → {}", name, local_path.display());
+        }
+        return Ok(ctx.state.clone());
+    }
+
+    let total = to_download.len();
+    let results = stream::iter(to_download)
+        .map(|(name, relative, local_path, remote)| async move {
+            let result = async {
+                download_sync_file(ctx, &name, &local_path).await?;
+                let mut file_state = get_file_state(&local_path, &relative).await?;
+                let now = Utc::now();
+                file_state.last_synced = Some(now);
+                file_state.sync_version = ctx.state.files.get(&relative).map_or(0, |last| last.sync_version) + 1;
+                file_state.remote_modified = remote.remote_modified;
+                file_state.remote_size = (remote.size != file_state.size).then_some(remote.size);
+                Ok::<_, anyhow::Error>(file_state)
+            }
+            .await;
+            match &result {
+                Ok(_) => println!("✅ Downloaded: {}", relative),
+                Err(e) => eprintln!("❌ Failed to download {}: {}", relative, e),
+            }
+            (relative, result)
+        })
+        .buffer_unordered(parallel.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut failed = 0;
+    for (relative, result) in results {
+        match result {
+            Ok(file_state) => {
+                state.files.insert(relative, file_state);
+            }
+            Err(_) => failed += 1,
+        }
+    }
+    state.last_sync = Some(Utc::now());
+    // Save what succeeded, so the next run only retries the failures
+    state.save(&ctx.local_path.join(".pipe-sync")).await?;
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} download(s) failed", failed, total));
+    }
+    if total == 0 {
+        println!("✅ Everything is in sync!");
+    } else {
+        println!("\n✅ Downloaded {} file(s)", total);
+    }
+    Ok(state)
+}
+
 /// Main sync entry point
+#[allow(clippy::too_many_arguments)]
 pub async fn sync_command(
     client: &Client,
     base_url: &str,
@@ -1337,12 +1577,18 @@ pub async fn sync_command(
     destination: Option<&str>,
     conflict_strategy: ConflictStrategy,
     dry_run: bool,
-    _parallel: usize,
+    parallel: usize,
+    compression: Option<CompressionSpec>,
 ) -> Result<()> {
     println!("🔄 Starting sync...");
     
-    // Determine sync direction and paths
-    let (local_path, remote_path, is_upload) = if let Some(dest) = destination {
+    // Determine sync direction and paths. A "remote:" prefix marks the remote side.
+    let (local_path, remote_path, is_upload) = if let Some(remote) = path.strip_prefix("remote:") {
+        // Download into the destination, or the current directory
+        (PathBuf::from(destination.unwrap_or(".")), remote.to_string(), false)
+    } else if let Some(remote) = destination.and_then(|dest| dest.strip_prefix("remote:")) {
+        (PathBuf::from(path), remote.to_string(), true)
+    } else if let Some(dest) = destination {
         // Explicit source and destination
         if dest.starts_with("./") || dest.starts_with("/") || !path.contains('/') {
             // path is remote, dest is local (download)
@@ -1368,7 +1614,7 @@ pub async fn sync_command(
     let mut state = SyncState::load(&state_path).await?;
     
     // Show sync state info if exists
-    if state.files.len() > 0 || state.last_sync.is_some() {
+    if !state.files.is_empty() || state.last_sync.is_some() {
         println!("📊 Sync state: {}", state.summary());
     }
     
@@ -1382,6 +1628,7 @@ pub async fn sync_command(
         conflict_strategy,
         dry_run,
         state: state.clone(),
+        compression,
     };
     
     if is_upload {
@@ -1403,9 +1650,13 @@ pub async fn sync_command(
         return Err(anyhow::anyhow!("Local path does not exist: {}", local_path.display()));
     }
     
-    // For now, we only support upload sync
     if !is_upload {
-        return Err(anyhow::anyhow!("Download sync not yet implemented"));
+        let new_state = execute_download_sync(&ctx, parallel).await?;
+        if !dry_run {
+            println!("📝 Sync state saved to: {}", state_path.display());
+            println!("📊 Sync state: {}", new_state.summary());
+        }
+        return Ok(());
     }
     
     // Execute streaming sync
@@ -1433,4 +1684,77 @@ pub async fn sync_command(
     }
     
     Ok(())
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(size: u64, hash: &str) -> FileState {
+        FileState {
+            path: "a.txt".to_string(),
+            size,
+            modified: Utc::now(),
+            hash: Some(hash.to_string()),
+            last_synced: None,
+            sync_version: 1,
+            remote_modified: None,
+            remote_size: None,
+        }
+    }
+
+    fn remote(size: u64, uploaded_at: Option<&str>) -> FileState {
+        FileState {
+            hash: None,
+            remote_modified: uploaded_at.map(|t| t.parse().unwrap()),
+            ..state(size, "")
+        }
+    }
+
+    #[test]
+    fn test_plan_download() {
+        let last = state(10, "aa");
+        let local = state(10, "aa");
+        assert_eq!(plan_download(&remote(10, None), None, None), DownloadPlan::Download);
+        assert_eq!(plan_download(&remote(10, None), Some(&last), None), DownloadPlan::Download);
+        assert_eq!(plan_download(&remote(10, None), None, Some(&local)), DownloadPlan::LocalChanged);
+        assert_eq!(plan_download(&remote(10, None), Some(&last), Some(&local)), DownloadPlan::Unchanged);
+        assert_eq!(plan_download(&remote(12, None), Some(&last), Some(&local)), DownloadPlan::Download);
+        assert_eq!(
+            plan_download(&remote(10, None), Some(&last), Some(&state(10, "bb"))),
+            DownloadPlan::LocalChanged
+        );
+
+        // Compressed uploads are stored smaller than the local file
+        let compressed = FileState { remote_size: Some(4), ..state(10, "aa") };
+        assert_eq!(plan_download(&remote(4, None), Some(&compressed), Some(&local)), DownloadPlan::Unchanged);
+    }
+
+    #[test]
+    fn test_plan_download_same_size_remote_change() {
+        let last = FileState {
+            remote_modified: Some("2025-03-01T10:00:00Z".parse().unwrap()),
+            ..state(10, "aa")
+        };
+        let local = state(10, "aa");
+        let same = remote(10, Some("2025-03-01T10:00:00Z"));
+        let replaced = remote(10, Some("2025-04-01T08:30:00Z"));
+        assert_eq!(plan_download(&same, Some(&last), Some(&local)), DownloadPlan::Unchanged);
+        assert_eq!(plan_download(&replaced, Some(&last), Some(&local)), DownloadPlan::Download);
+        // Without an upload time in the listing, only the size is compared
+        assert_eq!(plan_download(&remote(10, None), Some(&last), Some(&local)), DownloadPlan::Unchanged);
+    }
+
+    #[test]
+    fn test_relative_remote_path() {
+        assert_eq!(relative_remote_path("photos", "photos/a.jpg"), Some("a.jpg"));
+        assert_eq!(relative_remote_path("photos/", "photos/2024/b.jpg"), Some("2024/b.jpg"));
+        assert_eq!(relative_remote_path("photos", "photos.zip"), None);
+        assert_eq!(relative_remote_path("", "notes.txt"), Some("notes.txt"));
+
+        assert!(stays_inside("2024/b.jpg"));
+        assert!(!stays_inside("../etc/passwd"));
+        assert!(!stays_inside("/etc/passwd"));
+        assert!(!stays_inside(""));
+    }
+}